@@ -2,10 +2,39 @@ use serde::{Deserialize, Serialize};
 use ratatui::text::Line;
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
     pub content: String,
+    /// Tokens billed for the request that produced this reply. Kept in saved
+    /// conversations so the totals can be recomputed when messages are removed;
+    /// never sent to the API.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Identifies the message in the conversation tree; assigned when the
+    /// message is first recorded there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    /// The message this one answers or follows; None at the start of the conversation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u64>,
+}
+
+/// Only the fields the Messages API accepts
+fn serialize_request_messages<S: serde::Serializer>(messages: &[Message], serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeSeq;
+
+    #[derive(Serialize)]
+    struct RequestMessage<'a> {
+        role: &'a str,
+        content: &'a str,
+    }
+
+    let mut seq = serializer.serialize_seq(Some(messages.len()))?;
+    for message in messages {
+        seq.serialize_element(&RequestMessage { role: &message.role, content: &message.content })?;
+    }
+    seq.end()
 }
 
 // Cache for highlighted content
@@ -15,6 +44,8 @@ pub struct HighlightCache {
     cache: HashMap<u64, Vec<Line<'static>>>,
     // Maximum number of entries before we start evicting
     max_size: usize,
+    /// Report hits and misses on stderr (the `show_debug_messages` setting)
+    pub show_debug_messages: bool,
 }
 
 impl HighlightCache {
@@ -22,6 +53,7 @@ impl HighlightCache {
         Self {
             cache: HashMap::new(),
             max_size: 100, // Keep up to 100 cached messages
+            show_debug_messages: false,
         }
     }
     
@@ -66,7 +98,12 @@ pub struct ApiRequest {
     pub model: String,
     pub max_tokens: u32,
     pub temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(serialize_with = "serialize_request_messages")]
     pub messages: Vec<Message>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
 }
 
 #[derive(Deserialize, Debug)]
@@ -89,7 +126,7 @@ pub struct ApiResponse {
     pub usage: Usage,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
 pub struct Usage {
     pub input_tokens: u32,
     pub output_tokens: u32,
@@ -105,4 +142,48 @@ pub struct ErrorDetail {
     #[serde(rename = "type")]
     pub error_type: String,
     pub message: String,
-}
\ No newline at end of file
+}
+
+/// Events sent by the Messages API when `stream` is enabled
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    MessageStart {
+        message: StreamMessage,
+    },
+    ContentBlockDelta {
+        delta: ContentDelta,
+    },
+    MessageDelta {
+        usage: DeltaUsage,
+    },
+    MessageStop,
+    Error {
+        error: ErrorDetail,
+    },
+    // ping, content_block_start/stop and anything added to the API later
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StreamMessage {
+    pub usage: Usage,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentDelta {
+    TextDelta {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeltaUsage {
+    #[serde(default)]
+    pub input_tokens: Option<u32>,
+    pub output_tokens: u32,
+}
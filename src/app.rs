@@ -1,18 +1,86 @@
 // src/app.rs
 use crate::client::ConversationClient;
+use crate::find::{IndexedConversation, SearchHit};
+use crate::keymap::Keymap;
+use crate::library::SessionSummary;
+use crate::session::Autosave;
+use crate::settings::{ModelPreset, UiSettings};
+use crate::title::PendingTitle;
+use crate::tree::ConversationTree;
+use crate::backend::SimulatedBackend;
 use crate::api::HighlightCache;
-use crate::config::ColorConfig;
+use crate::config::{ColorConfig, DEFAULT_TITLE_MODEL};
 use rustyline::Editor;
 use ratatui::widgets::ListState;
 use std::path::PathBuf;
-use std::collections::HashMap;
-use crate::handlers::file_ops::get_saves_directory;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::task::JoinHandle;
+use crate::handlers::file_ops::{get_saves_directory, SavedConversation};
+
+/// A retry the backend is waiting to make, shown in the status bar
+#[derive(Debug, Clone, Copy)]
+pub struct RetryStatus {
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub resume_at: Instant,
+}
+
+/// A regeneration in flight
+#[derive(Debug, Clone, Copy)]
+pub struct Regeneration {
+    /// Id of the reply being replaced, put back if the request is cancelled.
+    /// None when the prompt had no reply, e.g. after an error.
+    pub replaced: Option<u64>,
+}
+
+/// The search bar over the conversation. The number of hits depends on how
+/// the chat is drawn, so it is worked out by the renderer.
+#[derive(Debug, Clone, Default)]
+pub struct ChatSearch {
+    pub query: String,
+    /// The query is being typed; otherwise n/N step through the hits
+    pub editing: bool,
+    /// The hit being shown, counting from the top of the conversation
+    pub current: usize,
+    /// Hits found when the chat was last drawn
+    pub match_count: usize,
+    /// Scroll to the current hit the next time the chat is drawn
+    pub jump: bool,
+    /// Make the first hit in this message (or after it) the current one the
+    /// next time the chat is drawn
+    pub start_at: Option<usize>,
+}
+
+impl ChatSearch {
+    /// Status bar text: where the current hit is among them all
+    pub fn status(&self) -> String {
+        let keys = if self.editing {
+            "Enter to step through matches, Esc to close"
+        } else {
+            "n/N for next/previous, / to change the search, Esc to close"
+        };
+        if self.query.is_empty() {
+            "Type to search the conversation (Esc to close)".to_string()
+        } else if self.match_count == 0 {
+            format!("No matches for \"{}\" ({})", self.query, keys)
+        } else {
+            format!("Match {} of {} ({})", self.current + 1, self.match_count, keys)
+        }
+    }
+}
 
 pub struct AppState {
     pub client: ConversationClient,
     pub input: String,
     pub status: String,
     pub waiting: bool,
+    pub pending_request: Option<JoinHandle<()>>,
+    /// Number of the latest request started; updates tagged with any other are stale
+    pub request_id: u64,
+    pub retry_status: Option<RetryStatus>,
+    pub regeneration: Option<Regeneration>,
     pub progress_i: usize,
     pub history_index: Option<usize>,
     pub chat_scroll_offset: u16,
@@ -22,7 +90,67 @@ pub struct AppState {
     pub input_scroll_offset: u16,
     pub input_draft: Option<String>,
     pub simulate_mode: bool,
+    /// Behaviour from the `ui` settings, and the options a project file sets
+    pub ui: UiSettings,
+    pub ui_from_project: BTreeMap<String, PathBuf>,
+    /// Model presets from the settings, and the one in use if the model
+    /// came from a preset
+    pub presets: BTreeMap<String, ModelPreset>,
+    pub active_preset: Option<String>,
+    /// Key bindings for the shortcuts
+    pub keymap: Keymap,
     pub rl: Editor<(), rustyline::history::DefaultHistory>,
+    /// Where save files go and the file dialogs open
+    pub saves_directory: PathBuf,
+    /// Title being generated for the conversation, and the model asked for it
+    pub pending_title: Option<PendingTitle>,
+    pub title_model: String,
+    
+    // Message editing: the user message highlighted in selection mode, the one
+    // loaded into the input box, and what the input box held before that
+    pub message_selection: Option<usize>,
+    pub editing_message: Option<usize>,
+    pub input_before_edit: Option<String>,
+    
+    /// Searching the conversation, while the search bar is open
+    pub search: Option<ChatSearch>,
+    
+    // Session autosave and the restore prompt shown at startup
+    pub autosave: Autosave,
+    pub show_restore_dialog: bool,
+    pub restore_selected: usize,
+    pub restore_candidate: Option<SavedConversation>,
+    
+    // Session library dialog: every session, the ones matching the filter
+    // (positions in `library_sessions`), the highlighted match, and the
+    // title being typed or the session awaiting delete confirmation
+    pub show_library_dialog: bool,
+    pub library_sessions: Vec<SessionSummary>,
+    pub library_filter: String,
+    pub library_matches: Vec<usize>,
+    pub library_selection: usize,
+    pub library_rename: Option<String>,
+    pub library_confirm_delete: bool,
+    
+    // Find dialog: every saved conversation, read when the dialog opens, the
+    // query, the messages matching it and the highlighted one
+    pub show_find_dialog: bool,
+    pub find_conversations: Vec<IndexedConversation>,
+    pub find_query: String,
+    pub find_hits: Vec<SearchHit>,
+    pub find_selection: usize,
+    
+    // Settings dialog: the highlighted option in `settings::UI_OPTIONS`
+    pub show_settings_dialog: bool,
+    pub settings_selection: usize,
+    
+    // Model picker: the highlighted preset, in name order
+    pub show_model_dialog: bool,
+    pub model_selection: usize,
+    
+    // Key binding help and how far it is scrolled
+    pub show_help_dialog: bool,
+    pub help_scroll_offset: u16,
     
     // Highlighting cache
     pub highlight_cache: HighlightCache,
@@ -41,6 +169,8 @@ pub struct AppState {
     pub new_dir_name: String,
     pub show_exit_dialog: bool,
     pub exit_selected: usize,
+    pub show_system_dialog: bool,
+    pub system_prompt_input: String,
     
     // Color configuration
     pub colors: ColorConfig,
@@ -70,10 +200,19 @@ impl AppState {
         colors: ColorConfig,
     ) -> anyhow::Result<Self> {
         Ok(Self {
-            client: ConversationClient::new(api_key, model, max_tokens, temperature),
+            client: if simulate_mode {
+                ConversationClient::new(api_key, model, max_tokens, temperature)
+                    .with_backend(Arc::new(SimulatedBackend::new()))
+            } else {
+                ConversationClient::new(api_key, model, max_tokens, temperature)
+            },
             input: String::new(),
             status: String::new(),
             waiting: false,
+            pending_request: None,
+            request_id: 0,
+            retry_status: None,
+            regeneration: None,
             progress_i: 0,
             history_index: None,
             chat_scroll_offset: 0,
@@ -83,8 +222,56 @@ impl AppState {
             input_scroll_offset: 0,
             input_draft: None,
             simulate_mode,
+            ui: UiSettings::default(),
+            ui_from_project: BTreeMap::new(),
+            presets: BTreeMap::new(),
+            active_preset: None,
+            keymap: Keymap::default(),
             rl: Editor::<(), rustyline::history::DefaultHistory>::new()?,
+            saves_directory: get_saves_directory(),
+            pending_title: None,
+            title_model: DEFAULT_TITLE_MODEL.to_string(),
             
+            // Message editing
+            message_selection: None,
+            editing_message: None,
+            input_before_edit: None,
+            search: None,
+            
+            // Autosave stays off unless main turns it on
+            autosave: Autosave::disabled(),
+            show_restore_dialog: false,
+            restore_selected: 0,
+            restore_candidate: None,
+            
+            // Session library dialog
+            show_library_dialog: false,
+            library_sessions: Vec::new(),
+            library_filter: String::new(),
+            library_matches: Vec::new(),
+            library_selection: 0,
+            library_rename: None,
+            library_confirm_delete: false,
+
+            // Find dialog
+            show_find_dialog: false,
+            find_conversations: Vec::new(),
+            find_query: String::new(),
+            find_hits: Vec::new(),
+            find_selection: 0,
+
+            // Settings dialog
+            show_settings_dialog: false,
+            settings_selection: 0,
+
+            // Model picker
+            show_model_dialog: false,
+            model_selection: 0,
+
+            // Key binding help
+            show_help_dialog: false,
+            help_scroll_offset: 0,
+
             // Highlighting cache
             highlight_cache: HighlightCache::new(),
             
@@ -102,6 +289,8 @@ impl AppState {
             new_dir_name: String::new(),
             show_exit_dialog: false,
             exit_selected: 0,
+            show_system_dialog: false,
+            system_prompt_input: String::new(),
             
             // Color configuration
             colors,
@@ -139,6 +328,117 @@ impl AppState {
         dirty
     }
     
+    /// Abort the in-flight API request, if any. A prompt that got no reply is
+    /// removed from the conversation and put back in the input box; a partial
+    /// reply is kept but marked as cancelled. Returns false if nothing was pending.
+    pub fn cancel_request(&mut self) -> bool {
+        if !self.waiting {
+            return false;
+        }
+        
+        if let Some(handle) = self.pending_request.take() {
+            handle.abort();
+        }
+        self.waiting = false;
+        self.retry_status = None;
+        
+        if let Some(regeneration) = self.regeneration.take() {
+            // Whatever arrived of the new reply goes; the prompt stays put
+            if self.client.messages.last().is_some_and(|m| m.role == "assistant") {
+                if let Some(id) = self.client.messages.pop().and_then(|m| m.id) {
+                    self.client.tree.remove_leaf(id);
+                }
+            }
+            self.status = match regeneration.replaced {
+                Some(id) => {
+                    self.client.switch_branch(id);
+                    "Regeneration cancelled - previous reply restored".to_string()
+                }
+                None => "Request cancelled".to_string(),
+            };
+            self.mark_dirty();
+            return true;
+        }
+        
+        match self.client.messages.last_mut() {
+            Some(last) if last.role == "assistant" => {
+                last.content.push_str("\n\n[response cancelled]");
+                self.client.record_branch();
+                self.status = "Request cancelled - partial response kept".to_string();
+            }
+            Some(last) if last.role == "user" => {
+                let prompt = self.client.messages.pop().unwrap_or_default();
+                // The prompt was never answered, so it doesn't start a branch
+                if let Some(id) = prompt.id {
+                    self.client.tree.remove_leaf(id);
+                }
+                let prompt = prompt.content;
+                // Don't clobber anything typed while waiting; the prompt is still in history
+                if self.input.is_empty() {
+                    self.cursor_position = prompt.chars().count();
+                    self.input = prompt;
+                }
+                self.status = "Request cancelled".to_string();
+            }
+            _ => {
+                self.status = "Request cancelled".to_string();
+            }
+        }
+        
+        self.mark_dirty();
+        true
+    }
+    
+    /// Replace the current conversation with one loaded from disk
+    pub fn apply_saved_conversation(&mut self, conversation: SavedConversation) {
+        self.client.tree = ConversationTree::from_messages(conversation.messages);
+        self.client.messages = match conversation.current {
+            Some(id) => self.client.tree.branch_through(id),
+            None => self.client.tree.latest_branch(),
+        };
+        self.client.total_input_tokens = conversation.total_input_tokens;
+        self.client.total_output_tokens = conversation.total_output_tokens;
+        self.client.system_prompt = conversation.system_prompt;
+        self.client.title = conversation.title;
+        self.autosave.resume(&self.client, conversation.library_id);
+        // Indexes into the old conversation mean nothing in the new one
+        self.message_selection = None;
+        self.editing_message = None;
+        self.search = None;
+        self.auto_scroll = true;
+        // Clear the highlight cache since we have new messages
+        self.clear_highlight_cache();
+    }
+    
+    /// Send the following requests to the preset called `name`, or to the
+    /// model `name` with the current parameters if there's no such preset.
+    /// Returns a description of the change for the status line.
+    pub fn switch_model(&mut self, name: &str) -> String {
+        match self.presets.get(name) {
+            Some(preset) => {
+                self.client.use_preset(preset);
+                self.active_preset = Some(name.to_string());
+                format!(
+                    "Switched to {} ({}, max tokens {}, temperature {})",
+                    name, self.client.model, self.client.max_tokens, self.client.temperature
+                )
+            }
+            None => {
+                self.client.model = name.to_string();
+                self.active_preset = None;
+                format!("Switched to {}", name)
+            }
+        }
+    }
+
+    /// The model for the status bar, with the preset it came from
+    pub fn model_label(&self) -> String {
+        match &self.active_preset {
+            Some(preset) => format!("{} ({})", self.client.model, preset),
+            None => self.client.model.clone(),
+        }
+    }
+    
     /// Show error dialog for config loading issues
     pub fn show_config_error(&mut self, error_msg: String) {
         self.show_error_dialog = true;
@@ -0,0 +1,352 @@
+// src/backend/anthropic.rs
+use std::time::Duration;
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Client;
+use crate::api::{ApiRequest, ApiResponse, ContentDelta, ErrorResponse, StreamEvent};
+use super::retry::{is_retryable_error_type, is_retryable_status, parse_retry_after, RetryPolicy};
+use super::{ApiSender, ApiUpdate, ChatBackend, ChatResponse};
+
+/// Default API host; the messages endpoint is appended to it
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+
+/// API version sent in the `anthropic-version` header unless overridden
+pub const DEFAULT_ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Backend that talks to the Anthropic Messages API over HTTP
+#[derive(Clone)]
+pub struct AnthropicBackend {
+    client: Client,
+    api_key: String,
+    base_url: String,
+    anthropic_version: String,
+    extra_headers: HeaderMap,
+    retry: RetryPolicy,
+}
+
+/// Why a single attempt failed, and whether trying again could help
+struct AttemptError {
+    error: anyhow::Error,
+    retryable: bool,
+    retry_after: Option<Duration>,
+}
+
+impl AttemptError {
+    fn fatal(error: anyhow::Error) -> Self {
+        Self { error, retryable: false, retry_after: None }
+    }
+
+    fn transient(error: anyhow::Error) -> Self {
+        Self { error, retryable: true, retry_after: None }
+    }
+}
+
+impl From<anyhow::Error> for AttemptError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::fatal(error)
+    }
+}
+
+impl AnthropicBackend {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            anthropic_version: DEFAULT_ANTHROPIC_VERSION.to_string(),
+            extra_headers: HeaderMap::new(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Send requests to a different host, e.g. a proxy or a local mock server
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Override the `anthropic-version` header
+    pub fn with_anthropic_version(mut self, version: impl Into<String>) -> Self {
+        self.anthropic_version = version.into();
+        self
+    }
+
+    /// Add headers to every request. They are applied last, so they can also
+    /// replace the defaults (e.g. a gateway that wants its own auth header).
+    pub fn with_headers<'a>(mut self, headers: impl IntoIterator<Item = (&'a String, &'a String)>) -> Result<Self> {
+        for (name, value) in headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid header name '{}'", name))?;
+            let header_value = HeaderValue::from_str(value)
+                .with_context(|| format!("Invalid value for header '{}'", name))?;
+            self.extra_headers.insert(header_name, header_value);
+        }
+        Ok(self)
+    }
+
+    /// Replace the retry policy used for rate limits and transient failures
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn endpoint(&self) -> String {
+        format!("{}/v1/messages", self.base_url.trim_end_matches('/'))
+    }
+
+    /// Send a request, retrying with exponential backoff while the failure
+    /// looks temporary. Each wait is announced as `ApiUpdate::Retrying`.
+    async fn send_request(&self, request: ApiRequest, updates: Option<&ApiSender>) -> Result<ChatResponse> {
+        let mut retries = 0;
+        loop {
+            let failure = match self.attempt(&request, updates).await {
+                Ok(response) => return Ok(response),
+                Err(failure) => failure,
+            };
+
+            if !failure.retryable || retries >= self.retry.max_retries {
+                if failure.retryable && retries > 0 {
+                    anyhow::bail!("{} (gave up after {} retries)", failure.error, retries);
+                }
+                return Err(failure.error);
+            }
+
+            retries += 1;
+            let delay = self.retry.delay(retries, failure.retry_after);
+            if let Some(updates) = updates {
+                updates.send(ApiUpdate::Retrying {
+                    attempt: retries,
+                    max_retries: self.retry.max_retries,
+                    delay,
+                }).await.ok();
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn attempt(&self, request: &ApiRequest, updates: Option<&ApiSender>) -> Result<ChatResponse, AttemptError> {
+        let mut response = self.client
+            .post(self.endpoint())
+            .header("Content-Type", "application/json")
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", &self.anthropic_version)
+            .headers(self.extra_headers.clone())
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| {
+                let transient = e.is_connect() || e.is_timeout() || e.is_request();
+                let error = anyhow::Error::new(e).context("Failed to send request to API");
+                if transient { AttemptError::transient(error) } else { AttemptError::fatal(error) }
+            })?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let retry_after = parse_retry_after(response.headers());
+            let response_text = response.text().await.unwrap_or_default();
+            let (error, error_type) = match serde_json::from_str::<ErrorResponse>(&response_text) {
+                Ok(error_response) => (
+                    anyhow::anyhow!(
+                        "API Error ({}): {}",
+                        error_response.error.error_type,
+                        error_response.error.message
+                    ),
+                    Some(error_response.error.error_type),
+                ),
+                // Proxies and load balancers don't always answer in the API's format
+                Err(_) => (anyhow::anyhow!("API Error (HTTP {}): {}", status, response_text.trim()), None),
+            };
+            let retryable = is_retryable_status(status.as_u16())
+                || error_type.as_deref().is_some_and(is_retryable_error_type);
+            return Err(AttemptError { error, retryable, retry_after });
+        }
+
+        if request.stream {
+            let mut parser = SseParser::new();
+            let mut accumulator = StreamAccumulator::new();
+
+            loop {
+                // Once text has been shown, a retry would duplicate it
+                let untouched = accumulator.text.is_empty();
+                let chunk = match response.chunk().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => break,
+                    Err(e) => {
+                        let error = anyhow::Error::new(e).context("Failed to read streamed response");
+                        return Err(AttemptError { error, retryable: untouched, retry_after: None });
+                    }
+                };
+                for sse in parser.push(&chunk) {
+                    let event: StreamEvent = serde_json::from_str(&sse.data)
+                        .context("Failed to parse stream event")?;
+                    if let StreamEvent::Error { error } = &event {
+                        return Err(AttemptError {
+                            error: anyhow::anyhow!("API Error ({}): {}", error.error_type, error.message),
+                            retryable: untouched && is_retryable_error_type(&error.error_type),
+                            retry_after: None,
+                        });
+                    }
+                    if let Some(text) = accumulator.apply(event)? {
+                        if let Some(updates) = updates {
+                            updates.send(ApiUpdate::Delta(text)).await.ok();
+                        }
+                    }
+                }
+            }
+
+            // A dropped connection or proxy timeout closes the body early; like a
+            // failed read, it's only retried while nothing has been shown
+            let untouched = accumulator.text.is_empty();
+            return accumulator.finish()
+                .map_err(|error| AttemptError { error, retryable: untouched, retry_after: None });
+        }
+
+        let response_text = response.text().await
+            .map_err(|e| AttemptError::transient(anyhow::Error::new(e).context("Failed to read API response")))?;
+        let api_response: ApiResponse = serde_json::from_str(&response_text)
+            .context("Failed to parse API response")?;
+
+        let assistant_response = api_response
+            .content
+            .iter()
+            .filter(|block| block.content_type == "text")
+            .map(|block| block.text.as_str())
+            .collect::<Vec<_>>()
+            .join("");
+
+        if let Some(updates) = updates {
+            updates.send(ApiUpdate::Delta(assistant_response.clone())).await.ok();
+        }
+
+        Ok(ChatResponse {
+            text: assistant_response,
+            input_tokens: api_response.usage.input_tokens,
+            output_tokens: api_response.usage.output_tokens,
+        })
+    }
+}
+
+impl ChatBackend for AnthropicBackend {
+    fn send<'a>(
+        &'a self,
+        request: ApiRequest,
+        updates: Option<&'a ApiSender>,
+    ) -> BoxFuture<'a, Result<ChatResponse>> {
+        Box::pin(self.send_request(request, updates))
+    }
+}
+
+/// A single server-sent event, before its data is parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental parser for a `text/event-stream` body.
+/// Network chunks can split events (and UTF-8 sequences) anywhere, so bytes
+/// are buffered until a complete line is available.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of bytes and return every event it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(newline) = self.buffer.iter().position(|&b| b == b'\n') {
+            let raw: Vec<u8> = self.buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                // A blank line dispatches the event collected so far
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take(),
+                        data: self.data.join("\n"),
+                    });
+                }
+                self.event = None;
+                self.data.clear();
+            } else if let Some(value) = line.strip_prefix("event:") {
+                self.event = Some(value.trim_start().to_string());
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+            // Comments (":") and unknown fields are ignored
+        }
+
+        events
+    }
+}
+
+/// Collects the assistant text and token usage from a stream of events
+#[derive(Debug, Default)]
+pub struct StreamAccumulator {
+    pub text: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    /// Whether `message_stop` arrived; a body that ends without it was cut off
+    pub stopped: bool,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply one event, returning any new text it carried
+    pub fn apply(&mut self, event: StreamEvent) -> Result<Option<String>> {
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.input_tokens = message.usage.input_tokens;
+                self.output_tokens = message.usage.output_tokens;
+                Ok(None)
+            }
+            StreamEvent::ContentBlockDelta { delta: ContentDelta::TextDelta { text } } => {
+                self.text.push_str(&text);
+                Ok(Some(text))
+            }
+            StreamEvent::MessageDelta { usage } => {
+                // The final message_delta carries cumulative usage
+                if let Some(input_tokens) = usage.input_tokens {
+                    self.input_tokens = input_tokens;
+                }
+                self.output_tokens = usage.output_tokens;
+                Ok(None)
+            }
+            StreamEvent::MessageStop => {
+                self.stopped = true;
+                Ok(None)
+            }
+            StreamEvent::Error { error } => {
+                anyhow::bail!("API Error ({}): {}", error.error_type, error.message)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The complete reply, or an error if the stream ended before `message_stop`
+    pub fn finish(self) -> Result<ChatResponse> {
+        if !self.stopped {
+            anyhow::bail!("The response stream ended before the reply was complete");
+        }
+        Ok(ChatResponse {
+            text: self.text,
+            input_tokens: self.input_tokens,
+            output_tokens: self.output_tokens,
+        })
+    }
+}
@@ -0,0 +1,66 @@
+// src/backend/mod.rs
+//! Chat backends: everything that turns an `ApiRequest` into a reply.
+//! The TUI and `ConversationClient` both go through `ChatBackend`, so request
+//! handling (headers, error parsing, streaming) lives in one place.
+pub mod anthropic;
+pub mod retry;
+pub mod simulate;
+
+// Test modules
+#[cfg(test)]
+mod tests;
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use crate::api::ApiRequest;
+
+pub use anthropic::AnthropicBackend;
+pub use retry::RetryPolicy;
+pub use simulate::SimulatedBackend;
+
+/// Updates sent from a running request back to whoever is displaying it
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiUpdate {
+    /// A chunk of assistant text, appended to the reply as it arrives
+    Delta(String),
+    /// The reply is complete; carries the token usage for the whole request
+    Done { input_tokens: u32, output_tokens: u32 },
+    /// The last attempt failed in a way that may clear up; retry `attempt`
+    /// of `max_retries` starts after `delay`
+    Retrying { attempt: u32, max_retries: u32, delay: Duration },
+    /// The request failed
+    Error(String),
+}
+
+pub type ApiSender = mpsc::Sender<ApiUpdate>;
+
+/// An update tagged with the number of the request it came from, so the UI
+/// can drop what an aborted request sent before it stopped
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestUpdate {
+    pub request: u64,
+    pub update: ApiUpdate,
+}
+
+pub type RequestSender = mpsc::Sender<RequestUpdate>;
+
+/// The complete result of a request
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatResponse {
+    pub text: String,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+pub trait ChatBackend: Send + Sync {
+    /// Send a request and wait for the full reply. When `updates` is given,
+    /// assistant text is also forwarded through it as `ApiUpdate::Delta` while
+    /// it arrives; sending `Done` or `Error` is left to the caller.
+    fn send<'a>(
+        &'a self,
+        request: ApiRequest,
+        updates: Option<&'a ApiSender>,
+    ) -> BoxFuture<'a, Result<ChatResponse>>;
+}
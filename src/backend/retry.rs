@@ -0,0 +1,60 @@
+// src/backend/retry.rs
+use std::time::Duration;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+/// How many times a failed request is retried and how long to wait in between
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; zero disables retrying
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for every retry after that
+    pub base_delay: Duration,
+    /// Upper bound for any wait, including one asked for by `retry-after`
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait before retry number `retry` (starting at 1)
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_delay);
+        }
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+/// Status codes worth another try: rate limiting, overload and gateway trouble
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+/// Error types the API uses for conditions that clear up on their own
+pub fn is_retryable_error_type(error_type: &str) -> bool {
+    matches!(error_type, "rate_limit_error" | "overloaded_error" | "api_error")
+}
+
+/// Read a `retry-after` header, given either in seconds or as an HTTP date
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<f64>() {
+        // Too large for a Duration is as good as forever; the policy caps it anyway
+        return (seconds.is_finite() && seconds >= 0.0)
+            .then(|| Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
@@ -0,0 +1,77 @@
+// src/backend/simulate.rs
+use anyhow::Result;
+use futures::future::BoxFuture;
+use tokio::time::Duration;
+use crate::api::ApiRequest;
+use super::{ApiSender, ApiUpdate, ChatBackend, ChatResponse};
+
+/// Backend used by `--simulate`: answers locally without any network access
+#[derive(Debug, Clone)]
+pub struct SimulatedBackend {
+    /// Pause before the reply starts, to mimic network latency
+    pub delay: Duration,
+    /// Pause between streamed words
+    pub word_delay: Duration,
+}
+
+impl SimulatedBackend {
+    pub fn new() -> Self {
+        Self {
+            delay: Duration::from_millis(500),
+            word_delay: Duration::from_millis(20),
+        }
+    }
+}
+
+impl Default for SimulatedBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatBackend for SimulatedBackend {
+    fn send<'a>(
+        &'a self,
+        request: ApiRequest,
+        updates: Option<&'a ApiSender>,
+    ) -> BoxFuture<'a, Result<ChatResponse>> {
+        Box::pin(async move {
+            // Simulate API delay
+            tokio::time::sleep(self.delay).await;
+
+            let user_input = request.messages.iter()
+                .rev()
+                .find(|m| m.role == "user")
+                .map(|m| m.content.clone())
+                .unwrap_or_default();
+
+            // Generate mock response
+            let mock_response = format!(
+                "This is a simulated response to your message: \"{}\". \
+                In simulate mode, no actual API calls are made. \
+                Your message had {} characters.",
+                user_input.trim(),
+                user_input.len()
+            );
+
+            if let Some(updates) = updates {
+                if request.stream {
+                    // Trickle the response out word by word so streaming can be exercised offline
+                    for word in mock_response.split_inclusive(' ') {
+                        updates.send(ApiUpdate::Delta(word.to_string())).await.ok();
+                        tokio::time::sleep(self.word_delay).await;
+                    }
+                } else {
+                    updates.send(ApiUpdate::Delta(mock_response.clone())).await.ok();
+                }
+            }
+
+            // Simulate token counts
+            Ok(ChatResponse {
+                input_tokens: user_input.len() as u32 / 4,
+                output_tokens: mock_response.len() as u32 / 4,
+                text: mock_response,
+            })
+        })
+    }
+}
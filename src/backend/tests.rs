@@ -0,0 +1,465 @@
+//! Unit tests for chat backends
+//! Tests server-sent event parsing, stream accumulation and the simulated backend
+
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+use crate::api::{ApiRequest, Message, StreamEvent};
+use crate::backend::anthropic::{SseParser, StreamAccumulator};
+use crate::backend::{ApiUpdate, ChatBackend, SimulatedBackend};
+
+/// Helper to parse a single stream event from its JSON payload
+fn event(json: &str) -> StreamEvent {
+    serde_json::from_str(json).expect("Failed to parse stream event")
+}
+
+#[cfg(test)]
+mod sse_parser_tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_complete_event() {
+        let mut parser = SseParser::new();
+        let events = parser.push(b"event: ping\ndata: {\"type\": \"ping\"}\n\n");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event.as_deref(), Some("ping"));
+        assert_eq!(events[0].data, "{\"type\": \"ping\"}");
+    }
+
+    #[test]
+    fn test_event_split_across_chunks() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"event: message_stop\nda").is_empty());
+        assert!(parser.push(b"ta: {\"type\":\"message_stop\"}\n").is_empty());
+
+        // Only the blank line completes the event
+        let events = parser.push(b"\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "{\"type\":\"message_stop\"}");
+    }
+
+    #[test]
+    fn test_multibyte_character_split_across_chunks() {
+        let mut parser = SseParser::new();
+        let payload = "data: héllo\n\n".as_bytes();
+        // Split in the middle of the two-byte 'é'
+        let split = payload.iter().position(|&b| b == 0xC3).unwrap() + 1;
+
+        assert!(parser.push(&payload[..split]).is_empty());
+        let events = parser.push(&payload[split..]);
+        assert_eq!(events[0].data, "héllo");
+    }
+
+    #[test]
+    fn test_crlf_line_endings_and_comments() {
+        let mut parser = SseParser::new();
+        let events = parser.push(b": keep-alive\r\ndata: one\r\n\r\ndata: two\r\n\r\n");
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].data, "one");
+        assert_eq!(events[1].data, "two");
+    }
+}
+
+#[cfg(test)]
+mod stream_accumulator_tests {
+    use super::*;
+
+    #[test]
+    fn test_accumulates_text_and_usage() {
+        let mut acc = StreamAccumulator::new();
+
+        acc.apply(event(r#"{"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","content":[],"model":"m","usage":{"input_tokens":25,"output_tokens":1}}}"#)).unwrap();
+        acc.apply(event(r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#)).unwrap();
+        let first = acc.apply(event(r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#)).unwrap();
+        let second = acc.apply(event(r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":", world"}}"#)).unwrap();
+        acc.apply(event(r#"{"type":"ping"}"#)).unwrap();
+        acc.apply(event(r#"{"type":"message_delta","delta":{"stop_reason":"end_turn","stop_sequence":null},"usage":{"output_tokens":15}}"#)).unwrap();
+        acc.apply(event(r#"{"type":"message_stop"}"#)).unwrap();
+
+        assert_eq!(first.as_deref(), Some("Hello"));
+        assert_eq!(second.as_deref(), Some(", world"));
+        assert_eq!(acc.text, "Hello, world");
+        assert_eq!(acc.input_tokens, 25);
+        assert_eq!(acc.output_tokens, 15);
+        assert_eq!(acc.finish().unwrap().text, "Hello, world");
+    }
+
+    /// A body that closes before `message_stop` is an error, not a short reply
+    #[test]
+    fn test_truncated_stream_is_an_error() {
+        let mut acc = StreamAccumulator::new();
+        acc.apply(event(r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hel"}}"#)).unwrap();
+
+        let err = acc.finish().expect_err("Truncated stream should fail");
+        assert!(err.to_string().contains("ended before"));
+    }
+
+    #[test]
+    fn test_error_event_fails_the_stream() {
+        let mut acc = StreamAccumulator::new();
+        let result = acc.apply(event(r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#));
+
+        let err = result.expect_err("Error event should fail");
+        assert!(err.to_string().contains("overloaded_error"));
+    }
+}
+
+#[cfg(test)]
+mod simulate_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_simulated_stream_sends_deltas_matching_response() {
+        let (tx, mut rx) = mpsc::channel(256);
+        let request = ApiRequest {
+            model: "test_model".to_string(),
+            max_tokens: 100,
+            temperature: 0.7,
+            system: None,
+            messages: vec![Message { role: "user".to_string(), content: "Hello".to_string(), ..Default::default() }],
+            stream: true,
+        };
+
+        let backend = SimulatedBackend { delay: Duration::ZERO, word_delay: Duration::ZERO };
+        let response = backend.send(request, Some(&tx))
+            .await
+            .expect("Simulated request should succeed");
+        drop(tx);
+
+        let mut streamed = String::new();
+        let mut chunks = 0;
+        while let Some(update) = rx.recv().await {
+            if let ApiUpdate::Delta(text) = update {
+                streamed.push_str(&text);
+                chunks += 1;
+            }
+        }
+
+        assert!(chunks > 1, "Simulated streaming should arrive in several pieces");
+        assert_eq!(streamed, response.text);
+        assert!(response.output_tokens > 0);
+    }
+}
+
+#[cfg(test)]
+mod client_tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::client::ConversationClient;
+
+    #[tokio::test]
+    async fn test_client_send_message_records_exchange() {
+        let mut client = ConversationClient::new("key".to_string(), "m".to_string(), 100, 0.5)
+            .with_backend(Arc::new(SimulatedBackend { delay: Duration::ZERO, word_delay: Duration::ZERO }));
+
+        let reply = client.send_message("Ping", None).await.expect("Send should succeed");
+
+        assert_eq!(client.messages.len(), 2);
+        assert_eq!(client.messages[0].content, "Ping");
+        assert_eq!(client.messages[1].content, reply);
+        assert!(client.total_tokens() > 0);
+    }
+}
+
+/// A minimal HTTP server on a local port that answers each request with the
+/// next canned response, repeating the last one once the list runs out.
+/// Returns the base URL and a log of the raw requests received.
+async fn mock_server(responses: Vec<String>) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = requests.clone();
+
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            // Read the headers, then as much body as Content-Length promises
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end].lines()
+                        .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length {
+                        break;
+                    }
+                }
+            }
+
+            let index = {
+                let mut log = log.lock().unwrap();
+                log.push(String::from_utf8_lossy(&request).into_owned());
+                log.len() - 1
+            };
+            let response = &responses[index.min(responses.len() - 1)];
+            socket.write_all(response.as_bytes()).await.ok();
+            socket.shutdown().await.ok();
+        }
+    });
+
+    (base_url, requests)
+}
+
+/// Build a raw HTTP response with a JSON body and optional extra header lines
+fn http_response(status: &str, extra_headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+        status,
+        body.len(),
+        extra_headers,
+        body
+    )
+}
+
+#[cfg(test)]
+mod retry_tests {
+    use super::*;
+    use crate::backend::{AnthropicBackend, RetryPolicy};
+    use crate::backend::retry::{is_retryable_status, parse_retry_after};
+
+    const OK_BODY: &str = r#"{"id":"msg_1","type":"message","role":"assistant","model":"m","stop_reason":"end_turn","content":[{"type":"text","text":"Hello!"}],"usage":{"input_tokens":5,"output_tokens":2}}"#;
+    const OVERLOADED_BODY: &str = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+    const RATE_LIMIT_BODY: &str = r#"{"type":"error","error":{"type":"rate_limit_error","message":"Slow down"}}"#;
+    const INVALID_BODY: &str = r#"{"type":"error","error":{"type":"invalid_request_error","message":"Bad request"}}"#;
+
+    fn instant_retries(max_retries: u32) -> RetryPolicy {
+        RetryPolicy { max_retries, base_delay: Duration::ZERO, max_delay: Duration::ZERO }
+    }
+
+    fn request() -> ApiRequest {
+        ApiRequest {
+            model: "m".to_string(),
+            max_tokens: 10,
+            temperature: 0.0,
+            system: None,
+            messages: vec![Message { role: "user".to_string(), content: "Hi".to_string(), ..Default::default() }],
+            stream: false,
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(10),
+        };
+        assert_eq!(policy.delay(1, None), Duration::from_secs(2));
+        assert_eq!(policy.delay(2, None), Duration::from_secs(4));
+        assert_eq!(policy.delay(3, None), Duration::from_secs(8));
+        assert_eq!(policy.delay(4, None), Duration::from_secs(10));
+        assert_eq!(policy.delay(40, None), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_retry_after_overrides_backoff() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(7))), Duration::from_secs(7));
+    }
+
+    /// A server asking for an hour doesn't leave the request hanging that long
+    #[test]
+    fn test_retry_after_is_capped() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(3600))), policy.max_delay);
+    }
+
+    /// A huge but finite number of seconds is waited out at the cap, not a panic
+    #[test]
+    fn test_parse_huge_retry_after() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("retry-after", "1e30".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::MAX));
+
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1, parse_retry_after(&headers)), policy.max_delay);
+    }
+
+    #[test]
+    fn test_parse_retry_after_formats() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert("retry-after", "3".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(3)));
+
+        headers.insert("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert("retry-after", "soon".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(429));
+        assert!(is_retryable_status(529));
+        assert!(!is_retryable_status(400));
+        assert!(!is_retryable_status(401));
+    }
+
+    #[tokio::test]
+    async fn test_overloaded_then_success() {
+        let (base_url, requests) = mock_server(vec![
+            http_response("529 Overloaded", "retry-after: 0\r\n", OVERLOADED_BODY),
+            http_response("200 OK", "", OK_BODY),
+        ]).await;
+        let backend = AnthropicBackend::new("key".to_string())
+            .with_base_url(base_url)
+            .with_retry(instant_retries(5));
+
+        let (tx, mut rx) = mpsc::channel(16);
+        let response = backend.send(request(), Some(&tx)).await.expect("Retry should succeed");
+        drop(tx);
+
+        assert_eq!(response.text, "Hello!");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(
+            rx.recv().await,
+            Some(ApiUpdate::Retrying { attempt: 1, max_retries: 5, delay: Duration::ZERO })
+        );
+        assert_eq!(rx.recv().await, Some(ApiUpdate::Delta("Hello!".to_string())));
+    }
+
+    /// A stream cut off before any text arrived is tried again
+    #[tokio::test]
+    async fn test_truncated_stream_is_retried() {
+        let start = r#"data: {"type":"message_start","message":{"usage":{"input_tokens":5,"output_tokens":1}}}"#;
+        let text = r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#;
+        let stop = r#"data: {"type":"message_stop"}"#;
+        let (base_url, requests) = mock_server(vec![
+            http_response("200 OK", "", &format!("{}\n\n", start)),
+            http_response("200 OK", "", &format!("{}\n\n{}\n\n{}\n\n", start, text, stop)),
+        ]).await;
+        let backend = AnthropicBackend::new("key".to_string())
+            .with_base_url(base_url)
+            .with_retry(instant_retries(2));
+
+        let response = backend.send(ApiRequest { stream: true, ..request() }, None).await
+            .expect("Retry should succeed");
+
+        assert_eq!(response.text, "Hi");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_when_retries_exhausted() {
+        let (base_url, requests) = mock_server(vec![
+            http_response("429 Too Many Requests", "", RATE_LIMIT_BODY),
+        ]).await;
+        let backend = AnthropicBackend::new("key".to_string())
+            .with_base_url(base_url)
+            .with_retry(instant_retries(2));
+
+        let error = backend.send(request(), None).await.unwrap_err().to_string();
+
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        assert!(error.contains("rate_limit_error"), "{}", error);
+        assert!(error.contains("gave up after 2 retries"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let (base_url, requests) = mock_server(vec![
+            http_response("400 Bad Request", "", INVALID_BODY),
+        ]).await;
+        let backend = AnthropicBackend::new("key".to_string())
+            .with_base_url(base_url)
+            .with_retry(instant_retries(5));
+
+        let error = backend.send(request(), None).await.unwrap_err().to_string();
+
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert_eq!(error, "API Error (invalid_request_error): Bad request");
+    }
+
+    #[tokio::test]
+    async fn test_connection_failure_is_retried() {
+        // Grab a free port and close it again so nothing is listening
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let backend = AnthropicBackend::new("key".to_string())
+            .with_base_url(base_url)
+            .with_retry(instant_retries(1));
+        let (tx, mut rx) = mpsc::channel(16);
+
+        assert!(backend.send(request(), Some(&tx)).await.is_err());
+        assert!(matches!(rx.recv().await, Some(ApiUpdate::Retrying { attempt: 1, .. })));
+    }
+}
+
+#[cfg(test)]
+mod endpoint_tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::backend::AnthropicBackend;
+
+    const OK_BODY: &str = r#"{"id":"msg_1","type":"message","role":"assistant","model":"m","stop_reason":"end_turn","content":[{"type":"text","text":"Hi"}],"usage":{"input_tokens":1,"output_tokens":1}}"#;
+
+    fn request() -> ApiRequest {
+        ApiRequest {
+            model: "m".to_string(),
+            max_tokens: 10,
+            temperature: 0.0,
+            system: None,
+            messages: vec![Message { role: "user".to_string(), content: "Hi".to_string(), ..Default::default() }],
+            stream: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_base_url_version_and_extra_headers_are_sent() {
+        let (base_url, requests) = mock_server(vec![http_response("200 OK", "", OK_BODY)]).await;
+        let headers = BTreeMap::from([
+            ("X-Gateway-Token".to_string(), "secret".to_string()),
+        ]);
+        let backend = AnthropicBackend::new("key".to_string())
+            .with_base_url(format!("{}/", base_url))
+            .with_anthropic_version("2099-01-01")
+            .with_headers(&headers)
+            .unwrap();
+
+        backend.send(request(), None).await.expect("Request should succeed");
+
+        let raw = requests.lock().unwrap()[0].to_ascii_lowercase();
+        assert!(raw.starts_with("post /v1/messages "), "{}", raw);
+        assert!(raw.contains("anthropic-version: 2099-01-01"), "{}", raw);
+        assert!(raw.contains("x-gateway-token: secret"), "{}", raw);
+        assert!(raw.contains("x-api-key: key"), "{}", raw);
+    }
+
+    #[tokio::test]
+    async fn test_extra_headers_replace_defaults() {
+        let (base_url, requests) = mock_server(vec![http_response("200 OK", "", OK_BODY)]).await;
+        let headers = BTreeMap::from([("x-api-key".to_string(), "gateway-key".to_string())]);
+        let backend = AnthropicBackend::new("key".to_string())
+            .with_base_url(base_url)
+            .with_headers(&headers)
+            .unwrap();
+
+        backend.send(request(), None).await.expect("Request should succeed");
+
+        let raw = requests.lock().unwrap()[0].to_ascii_lowercase();
+        assert!(raw.contains("x-api-key: gateway-key"), "{}", raw);
+        assert!(!raw.contains("x-api-key: key\r\n"), "{}", raw);
+    }
+
+    #[test]
+    fn test_invalid_header_name_is_rejected() {
+        let headers = BTreeMap::from([("bad header".to_string(), "v".to_string())]);
+        assert!(AnthropicBackend::new("key".to_string()).with_headers(&headers).is_err());
+    }
+}
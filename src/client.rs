@@ -1,16 +1,28 @@
-use anyhow::{Context, Result};
-use reqwest::Client;
-use crate::api::{ApiRequest, ApiResponse, ErrorResponse, Message};
-use serde_json;
+use anyhow::Result;
+use std::sync::Arc;
+use crate::api::{ApiRequest, Message, Usage};
+use crate::backend::{AnthropicBackend, ApiSender, ChatBackend};
+use crate::settings::ModelPreset;
+use crate::tree::ConversationTree;
 
 #[derive(Clone)]
 pub struct ConversationClient {
-    pub client: Client,
-    pub api_key: String,
+    pub backend: Arc<dyn ChatBackend>,
     pub model: String,
     pub max_tokens: u32,
     pub temperature: f32,
+    pub system_prompt: Option<String>,
+    /// The project's context files, sent after the system prompt. Kept apart
+    /// from it so they aren't saved with the conversation or edited as part
+    /// of the prompt.
+    pub context: Option<String>,
+    /// Name the conversation is listed under in the session library
+    pub title: Option<String>,
+    pub stream: bool,
+    /// The branch of the conversation on screen and sent with each request
     pub messages: Vec<Message>,
+    /// Every branch, including the one in `messages` as of its last recording
+    pub tree: ConversationTree,
     pub total_input_tokens: u32,
     pub total_output_tokens: u32,
 }
@@ -18,84 +30,137 @@ pub struct ConversationClient {
 impl ConversationClient {
     pub fn new(api_key: String, model: String, max_tokens: u32, temperature: f32) -> Self {
         Self {
-            client: Client::new(),
-            api_key,
+            backend: Arc::new(AnthropicBackend::new(api_key)),
             model,
             max_tokens,
             temperature,
+            system_prompt: None,
+            context: None,
+            title: None,
+            stream: true,
             messages: Vec::new(),
+            tree: ConversationTree::default(),
             total_input_tokens: 0,
             total_output_tokens: 0,
         }
     }
 
-    pub async fn send_message(&mut self, user_input: &str) -> Result<String> {
-        self.messages.push(Message {
-            role: "user".to_string(),
-            content: user_input.to_string(),
-        });
+    /// Replace the backend requests are sent to
+    pub fn with_backend(mut self, backend: Arc<dyn ChatBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Send the following requests to the preset's model with its parameters
+    pub fn use_preset(&mut self, preset: &ModelPreset) {
+        self.model = preset.model.clone();
+        if let Some(max_tokens) = preset.max_tokens {
+            self.max_tokens = max_tokens;
+        }
+        if let Some(temperature) = preset.temperature {
+            self.temperature = temperature;
+        }
+    }
 
-        let request = ApiRequest {
+    /// Build a request for the conversation so far
+    pub fn build_request(&self) -> ApiRequest {
+        ApiRequest {
             model: self.model.clone(),
             max_tokens: self.max_tokens,
             temperature: self.temperature,
+            system: match (&self.system_prompt, &self.context) {
+                (Some(prompt), Some(context)) => Some(format!("{}\n\n{}", prompt, context)),
+                (prompt, context) => prompt.clone().or_else(|| context.clone()),
+            },
             messages: self.messages.clone(),
-        };
-
-        let response = self
-            .client
-            .post("https://api.anthropic.com/v1/messages")
-            .header("Content-Type", "application/json")
-            .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send request to API")?;
-
-        let status = response.status();
-        let response_text = response.text().await?;
-
-        if !status.is_success() {
-            let error_response: ErrorResponse = serde_json::from_str(&response_text)
-                .context("Failed to parse error response")?;
-            anyhow::bail!(
-                "API Error ({}): {}",
-                error_response.error.error_type,
-                error_response.error.message
-            );
+            stream: self.stream,
         }
+    }
 
-        let api_response: ApiResponse = serde_json::from_str(&response_text)
-            .context("Failed to parse API response")?;
+    /// Send a user message and record the reply and its token usage.
+    /// When `updates` is given the reply is also streamed through it as it arrives.
+    pub async fn send_message(&mut self, user_input: &str, updates: Option<&ApiSender>) -> Result<String> {
+        self.messages.push(Message {
+            role: "user".to_string(),
+            content: user_input.to_string(),
+            ..Default::default()
+        });
 
-        // Track tokens
-        self.total_input_tokens += api_response.usage.input_tokens;
-        self.total_output_tokens += api_response.usage.output_tokens;
+        let mut request = self.build_request();
+        // Nobody is listening for partial text, so don't ask for it
+        request.stream = self.stream && updates.is_some();
 
-        let assistant_response = api_response
-            .content
-            .iter()
-            .filter(|block| block.content_type == "text")
-            .map(|block| block.text.as_str())
-            .collect::<Vec<_>>()
-            .join("");
+        let response = match self.backend.send(request, updates).await {
+            Ok(response) => response,
+            Err(e) => {
+                // Leave the conversation as it was so the message can be retried
+                self.messages.pop();
+                return Err(e);
+            }
+        };
+
+        // Track tokens
+        self.total_input_tokens += response.input_tokens;
+        self.total_output_tokens += response.output_tokens;
 
         self.messages.push(Message {
             role: "assistant".to_string(),
-            content: assistant_response.clone(),
+            content: response.text.clone(),
+            usage: Some(Usage {
+                input_tokens: response.input_tokens,
+                output_tokens: response.output_tokens,
+            }),
+            ..Default::default()
         });
 
-        Ok(assistant_response)
+        Ok(response.text)
     }
 
-    pub fn total_tokens(&self) -> u32 {
-        self.total_input_tokens + self.total_output_tokens
+    /// Record the branch on screen in the conversation tree
+    pub fn record_branch(&mut self) {
+        self.tree.record(&mut self.messages);
+    }
+
+    /// Drop the message at `index` and everything after it from the branch on
+    /// screen, taking the tokens recorded on the dropped replies off the totals.
+    /// They stay in the tree, so the next message sent starts a new branch.
+    pub fn truncate_messages(&mut self, index: usize) {
+        if index >= self.messages.len() {
+            return;
+        }
+        self.record_branch();
+        for message in self.messages.drain(index..) {
+            if let Some(usage) = message.usage {
+                self.total_input_tokens = self.total_input_tokens.saturating_sub(usage.input_tokens);
+                self.total_output_tokens = self.total_output_tokens.saturating_sub(usage.output_tokens);
+            }
+        }
     }
 
-    pub fn clear_conversation(&mut self) {
-        self.messages.clear();
-        self.total_input_tokens = 0;
-        self.total_output_tokens = 0;
+    /// Put the most recent branch through message `id` on screen, swapping the
+    /// tokens of the replies that leave for those of the ones that arrive
+    pub fn switch_branch(&mut self, id: u64) {
+        let branch = self.tree.branch_through(id);
+        if branch.is_empty() {
+            return;
+        }
+        let shared = self.messages
+            .iter()
+            .zip(&branch)
+            .take_while(|(shown, other)| shown.id.is_some() && shown.id == other.id)
+            .count();
+
+        self.truncate_messages(shared);
+        for message in branch.into_iter().skip(shared) {
+            if let Some(usage) = message.usage {
+                self.total_input_tokens += usage.input_tokens;
+                self.total_output_tokens += usage.output_tokens;
+            }
+            self.messages.push(message);
+        }
     }
-}
\ No newline at end of file
+
+    pub fn total_tokens(&self) -> u32 {
+        self.total_input_tokens + self.total_output_tokens
+    }
+}
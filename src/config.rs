@@ -1,19 +1,24 @@
 // src/config.rs
-use clap::{Parser, ValueEnum};
+use clap::{parser::ValueSource, ArgMatches, Parser, Subcommand, ValueEnum};
 use ratatui::style::Color;
-use ratatui::symbols::border;
+use ratatui::symbols::{border, line};
 use serde::{Deserialize, Serialize};
-use serde_json;
 use std::path::PathBuf;
 use std::fmt;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use base64::prelude::*;
 
-/// Feature flags and configuration constants
-pub const SCROLL_ON_USER_INPUT: bool = true;
-pub const SCROLL_ON_API_RESPONSE: bool = true;
-pub const SHIFT_ENTER_SENDS: bool = false;
-pub const SHOW_DEBUG_MESSAGES: bool = false;
+/// How often the session file is brought up to date between replies
+pub const AUTOSAVE_INTERVAL_SECS: u64 = 30;
+
+/// Room given to the side request that names a conversation
+pub const TITLE_MAX_TOKENS: u32 = 24;
+
+/// Model that names conversations unless the `title_model` setting says otherwise
+pub const DEFAULT_TITLE_MODEL: &str = "claude-3-5-haiku-20241022";
+
+/// How much of each message in the first exchange the title request sends, in characters
+pub const TITLE_MESSAGE_CHARS: usize = 400;
 
 /// Terminal size constants
 pub const MIN_TERMINAL_WIDTH: u16 = 10;
@@ -31,12 +36,6 @@ pub struct ColorProfile {
     pub config: ColorConfig,
 }
 
-impl ColorProfile {
-    pub fn new(name: String, description: String, config: ColorConfig) -> Self {
-        Self { name, description, config }
-    }
-}
-
 /// Embedded color profiles encoded as Base64 strings
 const EMBEDDED_PROFILES: &[(&str, &str)] = &[
     ("default", "eyJuYW1lIjoiRGVmYXVsdCIsImRlc2NyaXB0aW9uIjoiRGVmYXVsdCBjb2xvciBzY2hlbWUiLCJjb25maWciOnsiYmFja2dyb3VuZCI6IkJsYWNrIiwiYm9yZGVyIjoiV2hpdGUiLCJ0ZXh0IjoiV2hpdGUiLCJ1c2VyX25hbWUiOiJCcmlnaHRCbHVlIiwiYXNzaXN0YW50X25hbWUiOiJCcmlnaHRHcmVlbiIsImJvcmRlcl9zdHlsZSI6IkFzY2lpIn19"),
@@ -51,9 +50,10 @@ const EMBEDDED_PROFILES: &[(&str, &str)] = &[
 ];
 
 /// Available border styles
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, ValueEnum, Default)]
 pub enum BorderStyle {
     /// ASCII borders using +, -, | characters
+    #[default]
     Ascii,
     /// Rounded Unicode borders with curved corners
     Rounded,
@@ -73,29 +73,15 @@ impl BorderStyle {
         }
     }
 
-    pub fn name(&self) -> &'static str {
+    /// Line symbols in the same style, including the junctions tables need
+    pub fn to_ratatui_line_set(self) -> line::Set {
         match self {
-            BorderStyle::Ascii => "ASCII",
-            BorderStyle::Rounded => "Rounded",
-            BorderStyle::Thick => "Thick",
-            BorderStyle::Double => "Double",
+            BorderStyle::Ascii => line::NORMAL,
+            BorderStyle::Rounded => line::ROUNDED,
+            BorderStyle::Thick => line::THICK,
+            BorderStyle::Double => line::DOUBLE,
         }
     }
-
-    pub fn all() -> Vec<BorderStyle> {
-        vec![
-            BorderStyle::Ascii,
-            BorderStyle::Rounded,
-            BorderStyle::Thick,
-            BorderStyle::Double,
-        ]
-    }
-}
-
-impl Default for BorderStyle {
-    fn default() -> Self {
-        BorderStyle::Ascii
-    }
 }
 
 impl fmt::Display for BorderStyle {
@@ -110,7 +96,7 @@ impl fmt::Display for BorderStyle {
 }
 
 /// Available ANSI colors for user selection
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, Default)]
 pub enum AnsiColor {
     Black,
     Red,
@@ -119,6 +105,7 @@ pub enum AnsiColor {
     Blue,
     Magenta,
     Cyan,
+    #[default]
     White,
     BrightBlack,
     BrightRed,
@@ -224,12 +211,6 @@ impl AnsiColor {
     }
 }
 
-impl Default for AnsiColor {
-    fn default() -> Self {
-        AnsiColor::White
-    }
-}
-
 /// Color configuration for the application
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColorConfig {
@@ -256,11 +237,6 @@ impl Default for ColorConfig {
 }
 
 impl ColorConfig {
-    pub fn from_args(args: &Args) -> anyhow::Result<Self> {
-        let (result, _) = Self::from_args_and_saved(args);
-        result
-    }
-
     /// Create color configuration from args and saved config
     pub fn from_args_and_saved(args: &Args) -> (anyhow::Result<Self>, Option<String>) {
         // Start with saved config or defaults
@@ -331,12 +307,50 @@ pub fn get_config_path() -> PathBuf {
 
 /// Save color configuration to file
 pub fn save_color_config(config: &ColorConfig) -> anyhow::Result<()> {
-    let config_path = get_config_path();
-    let json = serde_json::to_string_pretty(config)?;
-    std::fs::write(&config_path, json)?;
+    save_color_config_to(config, &get_config_path())
+}
+
+/// Write the color fields into the config file at `path`, keeping any other
+/// sections (such as `api`) that are already there. A file that doesn't parse
+/// is an error and is left as it is.
+pub fn save_color_config_to(config: &ColorConfig, path: &std::path::Path) -> anyhow::Result<()> {
+    let mut root = crate::settings::read_json_for_update(path)?;
+
+    if let serde_json::Value::Object(colors) = serde_json::to_value(config)? {
+        root.extend(colors);
+    }
+
+    let json = serde_json::to_string_pretty(&root)?;
+    std::fs::write(path, json)?;
     Ok(())
 }
 
+/// Connection settings from the `api` section of the config file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApiConfig {
+    /// Host to send requests to instead of https://api.anthropic.com
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Value for the `anthropic-version` header
+    #[serde(default)]
+    pub anthropic_version: Option<String>,
+    /// Extra headers added to every request, e.g. for an auth gateway
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Parse a `KEY=VALUE` header argument
+fn parse_header_arg(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("missing header name in '{}'", s));
+    }
+    Ok((key.to_string(), value.trim().to_string()))
+}
+
 /// Load color configuration from file
 pub fn load_color_config() -> anyhow::Result<ColorConfig> {
     let config_path = get_config_path();
@@ -357,11 +371,6 @@ pub fn load_color_config() -> anyhow::Result<ColorConfig> {
     }
 }
 
-/// Load color configuration, falling back to defaults on error
-pub fn load_color_config_safe() -> ColorConfig {
-    load_color_config().unwrap_or_else(|_| ColorConfig::default())
-}
-
 /// Load color configuration, returning the result and whether it had an error
 pub fn load_color_config_with_error_info() -> (ColorConfig, Option<String>) {
     match load_color_config() {
@@ -396,17 +405,6 @@ pub fn get_profiles_path() -> PathBuf {
     path
 }
 
-/// Save a custom color profile
-pub fn save_color_profile(profile: &ColorProfile) -> anyhow::Result<()> {
-    let profiles_path = get_profiles_path();
-    let filename = format!("{}.json", profile.name.replace(' ', "_").to_lowercase());
-    let profile_path = profiles_path.join(filename);
-    
-    let json = serde_json::to_string_pretty(profile)?;
-    std::fs::write(&profile_path, json)?;
-    Ok(())
-}
-
 /// Load custom color profiles from disk
 pub fn load_custom_profiles() -> HashMap<String, ColorProfile> {
     let mut profiles = HashMap::new();
@@ -446,34 +444,115 @@ pub fn get_all_profiles() -> HashMap<String, ColorProfile> {
 }
 
 /// Get the default color configuration for testing
+#[cfg(test)]
 pub fn get_default_colors() -> ColorConfig {
     ColorConfig::default()
 }
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+// Subcommands work on files and need no API key
+#[command(subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Your Anthropic API key
-    #[arg(short, long)]
+    #[arg(short, long, required = true, default_value = "", hide_default_value = true)]
     #[arg(env = "ANTHROPIC_API_KEY")]
     pub api_key: String,
 
     /// Model to use (default: claude-3-5-sonnet-20241022)
-    #[arg(short, long, default_value = "claude-3-5-sonnet-20241022")]
+    #[arg(short, long, default_value = "claude-3-5-sonnet-20241022", env = "CLAUDECLI_MODEL")]
     pub model: String,
 
     /// Maximum tokens for response
-    #[arg(short = 't', long, default_value = "1024")]
+    #[arg(short = 't', long, default_value = "1024", env = "CLAUDECLI_MAX_TOKENS")]
     pub max_tokens: u32,
 
     /// Temperature (0.0 to 1.0)
-    #[arg(long, default_value = "0.7")]
+    #[arg(long, default_value = "0.7", env = "CLAUDECLI_TEMPERATURE")]
     pub temperature: f32,
 
-    /// Simulate API calls without actually sending requests
+    /// Start with a model preset from the settings; --model, --max-tokens and
+    /// --temperature still override it
+    #[arg(long, value_name = "NAME", env = "CLAUDECLI_PRESET")]
+    pub preset: Option<String>,
+
+    /// System prompt sent with every request
+    #[arg(long, conflicts_with = "system_file")]
+    pub system: Option<String>,
+
+    /// Read the system prompt from a file
     #[arg(long)]
+    pub system_file: Option<PathBuf>,
+
+    /// Send this prompt, print the reply and exit instead of starting the TUI.
+    /// Text piped on stdin is appended to it (or used on its own).
+    #[arg(short, long)]
+    pub prompt: Option<String>,
+
+    /// Use a line-oriented prompt in the normal screen instead of the full-screen TUI
+    #[arg(long, conflicts_with = "prompt", overrides_with = "no_plain")]
+    pub plain: bool,
+
+    /// Start the full-screen TUI even if the settings ask for plain mode
+    #[arg(long, overrides_with = "plain")]
+    pub no_plain: bool,
+
+    /// Simulate API calls without actually sending requests
+    #[arg(long, overrides_with = "no_simulate")]
     pub simulate: bool,
 
+    /// Send real requests even if the settings ask for simulate mode
+    #[arg(long, overrides_with = "simulate")]
+    pub no_simulate: bool,
+
+    /// Wait for the complete response instead of streaming it token by token
+    #[arg(long, overrides_with = "stream")]
+    pub no_stream: bool,
+
+    /// Stream the response even if the settings turn streaming off
+    #[arg(long, overrides_with = "no_stream")]
+    pub stream: bool,
+
+    /// How many times to retry rate-limited, overloaded or failed requests (0 disables)
+    #[arg(long, default_value = "5")]
+    pub max_retries: u32,
+
+    /// Seconds to wait before the first retry; doubled for each retry after that
+    #[arg(long, default_value = "2")]
+    pub retry_delay: f64,
+
+    /// API host to send requests to, e.g. a proxy or gateway (default: https://api.anthropic.com)
+    #[arg(long, env = "ANTHROPIC_BASE_URL")]
+    pub base_url: Option<String>,
+
+    /// Extra header sent with every request; can be repeated
+    #[arg(long = "header", value_name = "KEY=VALUE", value_parser = parse_header_arg)]
+    pub headers: Vec<(String, String)>,
+
+    /// Value of the anthropic-version header (default: 2023-06-01)
+    #[arg(long)]
+    pub anthropic_version: Option<String>,
+
+    /// Don't autosave the session or offer to restore the last one
+    #[arg(long, overrides_with = "autosave")]
+    pub no_autosave: bool,
+
+    /// Autosave the session even if the settings turn it off
+    #[arg(long, overrides_with = "no_autosave")]
+    pub autosave: bool,
+
+    /// Use the settings files of the project in the current directory (or
+    /// the nearest above it) without asking, and remember the choice
+    #[arg(long)]
+    pub trust_project: bool,
+
+    /// Start with this saved conversation loaded
+    #[arg(long, value_name = "FILE", conflicts_with = "prompt")]
+    pub open: Option<PathBuf>,
+
     /// Reset all colors to default values
     #[arg(long)]
     pub reset_colors: bool,
@@ -503,6 +582,137 @@ pub struct Args {
     pub border_style: BorderStyle,
 }
 
+#[derive(Subcommand)]
+pub enum Command {
+    /// Convert a saved conversation to Markdown, HTML or plain text
+    Export {
+        /// Saved conversation (JSON) to convert
+        file: PathBuf,
+
+        /// Document format
+        #[arg(short, long, value_enum, default_value = "md")]
+        format: crate::export::ExportFormat,
+
+        /// Write the document here instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Find messages in saved conversations (the session library and the current directory)
+    Search {
+        /// Text to look for, ignoring case
+        query: String,
+
+        /// Search the save files in this directory instead; can be repeated
+        #[arg(short, long = "dir", value_name = "DIR")]
+        dirs: Vec<PathBuf>,
+
+        /// Read every file again instead of using (and updating) the search index
+        #[arg(long)]
+        no_index: bool,
+    },
+}
+
+impl Args {
+    /// Take options that were left at their defaults - given neither on the
+    /// command line nor in the environment - from the settings files
+    pub fn apply_settings(&mut self, settings: &crate::settings::Settings, matches: &ArgMatches) {
+        let unset = |id: &str| !given(matches, id);
+        fn fill<T: Clone>(field: &mut T, setting: Option<T>, unset: bool) {
+            if let (true, Some(value)) = (unset, setting) {
+                *field = value;
+            }
+        }
+
+        fill(&mut self.model, settings.model.clone(), unset("model"));
+        fill(&mut self.max_tokens, settings.max_tokens, unset("max_tokens"));
+        fill(&mut self.temperature, settings.temperature, unset("temperature"));
+        // Each of these has a flag for either setting, so the command line can
+        // undo what the settings turned on or off
+        fill(&mut self.simulate, settings.simulate, unset("simulate") && unset("no_simulate"));
+        fill(&mut self.plain, settings.plain, unset("plain") && unset("no_plain") && self.prompt.is_none());
+        fill(&mut self.no_stream, settings.stream.map(|stream| !stream), unset("no_stream") && unset("stream"));
+        fill(&mut self.no_autosave, settings.autosave.map(|autosave| !autosave), unset("no_autosave") && unset("autosave"));
+        fill(&mut self.max_retries, settings.max_retries, unset("max_retries"));
+        fill(&mut self.retry_delay, settings.retry_delay, unset("retry_delay"));
+        // A system prompt given either way on the command line replaces both
+        if unset("system") && unset("system_file") {
+            self.system = settings.system.clone();
+            self.system_file = settings.system_file.clone();
+        }
+    }
+
+    /// Take the model, max tokens and temperature from the chosen preset -
+    /// `--preset` or else the settings' `preset` - unless they were given on
+    /// the command line or in the environment. If the model was, `preset` is
+    /// cleared so the model isn't shown as coming from the preset.
+    pub fn apply_preset(&mut self, settings: &crate::settings::Settings, matches: &ArgMatches) -> anyhow::Result<()> {
+        if self.preset.is_none() {
+            self.preset = settings.preset.clone();
+        }
+        let Some(name) = &self.preset else {
+            return Ok(());
+        };
+        let preset = settings.presets.get(name).ok_or_else(|| {
+            let names: Vec<&str> = settings.presets.keys().map(String::as_str).collect();
+            anyhow::anyhow!("Unknown preset '{}' (configured: {})", name, if names.is_empty() { "none".to_string() } else { names.join(", ") })
+        })?;
+
+        if !given(matches, "model") {
+            self.model = preset.model.clone();
+        }
+        let overridden = self.model != preset.model;
+        if let (false, Some(max_tokens)) = (given(matches, "max_tokens"), preset.max_tokens) {
+            self.max_tokens = max_tokens;
+        }
+        if let (false, Some(temperature)) = (given(matches, "temperature"), preset.temperature) {
+            self.temperature = temperature;
+        }
+        if overridden {
+            self.preset = None;
+        }
+        Ok(())
+    }
+
+    /// Apply command-line connection options on top of the saved `api` settings
+    pub fn api_config(&self, saved: ApiConfig) -> ApiConfig {
+        let mut config = saved;
+        if let Some(base_url) = &self.base_url {
+            config.base_url = Some(base_url.clone());
+        }
+        if let Some(version) = &self.anthropic_version {
+            config.anthropic_version = Some(version.clone());
+        }
+        config.headers.extend(self.headers.iter().cloned());
+        config
+    }
+
+    /// Retry policy from `--max-retries` and `--retry-delay`
+    pub fn retry_policy(&self) -> crate::backend::RetryPolicy {
+        crate::backend::RetryPolicy {
+            max_retries: self.max_retries,
+            base_delay: std::time::Duration::try_from_secs_f64(self.retry_delay).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Resolve the system prompt from `--system` or `--system-file`
+    pub fn system_prompt(&self) -> anyhow::Result<Option<String>> {
+        if let Some(path) = &self.system_file {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Failed to read system prompt file {}: {}", path.display(), e))?;
+            let prompt = contents.trim();
+            return Ok(if prompt.is_empty() { None } else { Some(prompt.to_string()) });
+        }
+        Ok(self.system.clone().filter(|s| !s.trim().is_empty()))
+    }
+}
+
+/// Whether the option was set on the command line or in the environment
+/// rather than left at its default
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable))
+}
+
 #[cfg(test)]
 mod command_line_override_tests {
     use super::*;
@@ -511,11 +721,30 @@ mod command_line_override_tests {
     fn test_background_color_black_override() {
         // Test that --background-color black properly overrides config file values
         let args = Args {
+            command: None,
             api_key: "dummy".to_string(),
             model: "claude-3-5-sonnet-20241022".to_string(),
             max_tokens: 1024,
             temperature: 0.7,
+            preset: None,
+            system: None,
+            system_file: None,
+            prompt: None,
+            plain: false,
+            no_plain: false,
             simulate: false,
+            no_simulate: false,
+            no_stream: false,
+            stream: false,
+            max_retries: 5,
+            retry_delay: 2.0,
+            base_url: None,
+            headers: Vec::new(),
+            anthropic_version: None,
+            no_autosave: false,
+            autosave: false,
+            trust_project: false,
+            open: None,
             reset_colors: false,
             background_color: Some("black".to_string()),
             border_color: None,
@@ -536,11 +765,30 @@ mod command_line_override_tests {
     fn test_all_default_colors_can_be_overridden() {
         // Test that all default colors can be explicitly specified and will override config
         let args = Args {
+            command: None,
             api_key: "dummy".to_string(),
             model: "claude-3-5-sonnet-20241022".to_string(),
             max_tokens: 1024,
             temperature: 0.7,
+            preset: None,
+            system: None,
+            system_file: None,
+            prompt: None,
+            plain: false,
+            no_plain: false,
             simulate: false,
+            no_simulate: false,
+            no_stream: false,
+            stream: false,
+            max_retries: 5,
+            retry_delay: 2.0,
+            base_url: None,
+            headers: Vec::new(),
+            anthropic_version: None,
+            no_autosave: false,
+            autosave: false,
+            trust_project: false,
+            open: None,
             reset_colors: false,
             background_color: Some("black".to_string()),
             border_color: Some("white".to_string()),
@@ -563,11 +811,30 @@ mod command_line_override_tests {
     fn test_no_override_when_not_specified() {
         // Test that when no color arguments are provided, we get defaults
         let args = Args {
+            command: None,
             api_key: "dummy".to_string(),
             model: "claude-3-5-sonnet-20241022".to_string(),
             max_tokens: 1024,
             temperature: 0.7,
+            preset: None,
+            system: None,
+            system_file: None,
+            prompt: None,
+            plain: false,
+            no_plain: false,
             simulate: false,
+            no_simulate: false,
+            no_stream: false,
+            stream: false,
+            max_retries: 5,
+            retry_delay: 2.0,
+            base_url: None,
+            headers: Vec::new(),
+            anthropic_version: None,
+            no_autosave: false,
+            autosave: false,
+            trust_project: false,
+            open: None,
             reset_colors: false,
             background_color: None,
             border_color: None,
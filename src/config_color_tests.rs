@@ -40,7 +40,7 @@ mod color_tests {
         use crate::config::Args;
         use clap::Parser;
         
-        let args = Args::parse_from(&[
+        let args = Args::parse_from([
             "claudecli",
             "--api-key", "test-key",
             "--background-color", "blue",
@@ -50,7 +50,7 @@ mod color_tests {
             "--assistant-name-color", "bright-cyan"
         ]);
         
-        let config = ColorConfig::from_args(&args).unwrap();
+        let config = ColorConfig::from_args_and_saved(&args).0.unwrap();
         assert_eq!(config.background, AnsiColor::Blue);
         assert_eq!(config.border, AnsiColor::BrightWhite);
         assert_eq!(config.text, AnsiColor::Green);
@@ -85,33 +85,6 @@ mod color_tests {
         assert_eq!(matrix_profile.config.text, AnsiColor::BrightGreen);
     }
 
-    #[test]
-    fn test_color_profile_creation() {
-        let config = ColorConfig {
-            background: AnsiColor::Black,
-            border: AnsiColor::Red,
-            text: AnsiColor::White,
-            user_name: AnsiColor::BrightBlue,
-            assistant_name: AnsiColor::BrightGreen,
-            border_style: crate::config::BorderStyle::Rounded,
-        };
-        
-        let profile = crate::config::ColorProfile::new(
-            "Test Profile".to_string(),
-            "A test color profile".to_string(),
-            config.clone(),
-        );
-        
-        assert_eq!(profile.name, "Test Profile");
-        assert_eq!(profile.description, "A test color profile");
-        assert_eq!(profile.config.background, config.background);
-        assert_eq!(profile.config.border, config.border);
-        assert_eq!(profile.config.text, config.text);
-        assert_eq!(profile.config.user_name, config.user_name);
-        assert_eq!(profile.config.assistant_name, config.assistant_name);
-        assert_eq!(profile.config.border_style, config.border_style);
-    }
-
     #[test]
     fn test_get_all_profiles() {
         let all_profiles = crate::config::get_all_profiles();
@@ -0,0 +1,153 @@
+//! Unit tests for command line argument handling
+//! Tests options that are resolved from Args and the config file before the TUI starts
+
+use clap::Parser;
+use tempfile::TempDir;
+
+use crate::config::{save_color_config_to, ApiConfig, Args, ColorConfig};
+use crate::settings::load_settings_from;
+
+#[cfg(test)]
+mod system_prompt_args_tests {
+    use super::*;
+
+    #[test]
+    fn test_system_prompt_from_flag() {
+        let args = Args::parse_from(["claudecli", "--api-key", "k", "--system", "Be brief."]);
+        assert_eq!(args.system_prompt().unwrap().as_deref(), Some("Be brief."));
+    }
+
+    #[test]
+    fn test_system_prompt_from_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("system.txt");
+        std::fs::write(&path, "You review Rust code.\nBe direct.\n").unwrap();
+
+        let args = Args::parse_from(["claudecli", "--api-key", "k", "--system-file", path.to_str().unwrap()]);
+        assert_eq!(args.system_prompt().unwrap().as_deref(), Some("You review Rust code.\nBe direct."));
+    }
+
+    #[test]
+    fn test_missing_system_file_is_an_error() {
+        let args = Args::parse_from(["claudecli", "--api-key", "k", "--system-file", "/nonexistent/system.txt"]);
+        assert!(args.system_prompt().is_err());
+    }
+
+    #[test]
+    fn test_system_and_system_file_conflict() {
+        let result = Args::try_parse_from(["claudecli", "--api-key", "k", "--system", "a", "--system-file", "b"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_no_system_prompt_by_default() {
+        let args = Args::parse_from(["claudecli", "--api-key", "k"]);
+        assert!(args.system_prompt().unwrap().is_none());
+    }
+}
+
+#[cfg(test)]
+mod api_config_tests {
+    use super::*;
+
+    #[test]
+    fn test_header_flags_are_parsed() {
+        let args = Args::parse_from([
+            "claudecli", "--api-key", "k",
+            "--header", "X-Gateway=abc",
+            "--header", "X-Trace = a=b",
+        ]);
+        assert_eq!(args.headers, vec![
+            ("X-Gateway".to_string(), "abc".to_string()),
+            ("X-Trace".to_string(), "a=b".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_malformed_header_flag_is_rejected() {
+        assert!(Args::try_parse_from(["claudecli", "--api-key", "k", "--header", "NoEquals"]).is_err());
+        assert!(Args::try_parse_from(["claudecli", "--api-key", "k", "--header", "=value"]).is_err());
+    }
+
+    #[test]
+    fn test_flags_override_saved_settings() {
+        let saved = ApiConfig {
+            base_url: Some("https://saved.example".to_string()),
+            anthropic_version: Some("2023-01-01".to_string()),
+            headers: [
+                ("X-Keep".to_string(), "saved".to_string()),
+                ("X-Replace".to_string(), "saved".to_string()),
+            ].into_iter().collect(),
+        };
+        let args = Args::parse_from([
+            "claudecli", "--api-key", "k",
+            "--base-url", "http://localhost:8080",
+            "--header", "X-Replace=flag",
+        ]);
+
+        let config = args.api_config(saved);
+        assert_eq!(config.base_url.as_deref(), Some("http://localhost:8080"));
+        assert_eq!(config.anthropic_version.as_deref(), Some("2023-01-01"));
+        assert_eq!(config.headers["X-Keep"], "saved");
+        assert_eq!(config.headers["X-Replace"], "flag");
+    }
+
+    #[test]
+    fn test_api_section_is_loaded_from_config_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("config.json");
+        std::fs::write(&path, r#"{
+            "background": "Black", "border": "White", "text": "White",
+            "user_name": "BrightBlue", "assistant_name": "BrightGreen",
+            "api": { "base_url": "https://gateway.internal", "headers": { "X-Auth": "t" } }
+        }"#).unwrap();
+
+        let config = load_settings_from(std::slice::from_ref(&path)).unwrap().api;
+        assert_eq!(config.base_url.as_deref(), Some("https://gateway.internal"));
+        assert_eq!(config.headers["X-Auth"], "t");
+        assert_eq!(config.anthropic_version, None);
+    }
+
+    #[test]
+    fn test_missing_config_file_gives_defaults() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let config = load_settings_from(&[temp_dir.path().join("config.json")]).unwrap().api;
+        assert_eq!(config, ApiConfig::default());
+    }
+
+    #[test]
+    fn test_invalid_api_section_is_an_error() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("config.json");
+        std::fs::write(&path, r#"{ "api": { "headers": ["not", "a", "map"] } }"#).unwrap();
+        assert!(load_settings_from(std::slice::from_ref(&path)).is_err());
+    }
+
+    #[test]
+    fn test_saving_colors_keeps_api_section() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("config.json");
+        std::fs::write(&path, r#"{ "api": { "base_url": "https://gateway.internal" } }"#).unwrap();
+
+        save_color_config_to(&ColorConfig::default(), &path).unwrap();
+
+        let config = load_settings_from(std::slice::from_ref(&path)).unwrap().api;
+        assert_eq!(config.base_url.as_deref(), Some("https://gateway.internal"));
+        let colors: ColorConfig = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(colors.border_style, ColorConfig::default().border_style);
+    }
+
+    /// A hand-edited file with a mistake in it isn't replaced by just the colors
+    #[test]
+    fn test_saving_colors_keeps_broken_json() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("config.json");
+        let broken = "{ \"api\": { \"base_url\": \"https://gateway.internal\" }, }";
+        std::fs::write(&path, broken).unwrap();
+
+        let error = save_color_config_to(&ColorConfig::default(), &path).unwrap_err().to_string();
+
+        assert!(error.contains("config.json"), "{}", error);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), broken);
+    }
+}
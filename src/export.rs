@@ -0,0 +1,343 @@
+// src/export.rs
+//! Conversations as documents to paste into PRs and wikis: Markdown, HTML or
+//! plain text, with the model, time and token counts of the save. Only the
+//! branch that was on screen is exported, and message text is kept as written
+//! so code fences keep their language tags.
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use clap::ValueEnum;
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use crate::api::Message;
+use crate::client::ConversationClient;
+use crate::handlers::file_ops::{load_conversation, SavedConversation};
+use crate::library::{default_title, local_time};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    #[value(name = "md", alias = "markdown")]
+    Markdown,
+    #[value(name = "html")]
+    Html,
+    #[value(name = "txt", alias = "text")]
+    Text,
+}
+
+impl ExportFormat {
+    /// The format named by `/export`, e.g. "md"
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::from_str(name.trim(), true).ok()
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Text => "txt",
+        }
+    }
+}
+
+/// Render a saved conversation as a document in `format`
+pub fn export_conversation(conversation: &SavedConversation, format: ExportFormat) -> String {
+    let branch = conversation.branch();
+    let title = conversation.title.clone().unwrap_or_else(|| default_title(&branch));
+    let document = Document { conversation, title, branch };
+    match format {
+        ExportFormat::Markdown => document.markdown(),
+        ExportFormat::Html => document.html(),
+        ExportFormat::Text => document.text(),
+    }
+}
+
+/// Convert a saved conversation file. Writes to `output` if given and returns
+/// the document either way.
+pub fn export_file(input: &Path, format: ExportFormat, output: Option<&Path>) -> Result<String> {
+    let conversation = load_conversation(&input.to_path_buf())
+        .map_err(|e| anyhow::anyhow!("Failed to load {}: {}", input.display(), e))?;
+    let document = export_conversation(&conversation, format);
+    if let Some(output) = output {
+        std::fs::write(output, &document)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", output.display(), e))?;
+    }
+    Ok(document)
+}
+
+/// Export the open conversation into `directory`, named after its title (or
+/// the time, if it has none), with a suffix if a file already has that name.
+/// Returns the path written.
+pub fn export_to_directory(client: &ConversationClient, format: ExportFormat, directory: &Path) -> Result<PathBuf> {
+    let conversation = SavedConversation::new(client);
+    let stem = crate::title::default_filename(client)
+        .strip_suffix(".json")
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("conversation-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    let mut path = directory.join(format!("{}.{}", stem, format.extension()));
+    let mut suffix = 2;
+    while path.exists() {
+        path = directory.join(format!("{}-{}.{}", stem, suffix, format.extension()));
+        suffix += 1;
+    }
+    std::fs::write(&path, export_conversation(&conversation, format))?;
+    Ok(path)
+}
+
+/// Heading for a message's role, e.g. "Assistant"
+fn role_heading(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn usage_note(message: &Message) -> Option<String> {
+    message
+        .usage
+        .as_ref()
+        .map(|usage| format!("{} input tokens, {} output tokens", usage.input_tokens, usage.output_tokens))
+}
+
+struct Document<'a> {
+    conversation: &'a SavedConversation,
+    title: String,
+    branch: Vec<Message>,
+}
+
+impl Document<'_> {
+    /// Label and value of each line of metadata
+    fn metadata(&self) -> Vec<(&'static str, String)> {
+        let conversation = self.conversation;
+        vec![
+            ("Model", conversation.model.clone()),
+            ("Saved", local_time(&conversation.timestamp)),
+            (
+                "Tokens",
+                format!(
+                    "{} input, {} output, {} total",
+                    conversation.total_input_tokens,
+                    conversation.total_output_tokens,
+                    conversation.total_input_tokens + conversation.total_output_tokens
+                ),
+            ),
+        ]
+    }
+
+    fn markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title);
+        for (label, value) in self.metadata() {
+            let _ = writeln!(out, "- **{}:** {}", label, value);
+        }
+        if let Some(prompt) = &self.conversation.system_prompt {
+            let _ = write!(out, "\n## System prompt\n\n{}\n", prompt.trim_end());
+        }
+        for message in &self.branch {
+            let _ = write!(out, "\n## {}\n\n{}\n", role_heading(&message.role), message.content.trim_end());
+            if let Some(note) = usage_note(message) {
+                let _ = write!(out, "\n*{}*\n", note);
+            }
+        }
+        out
+    }
+
+    fn text(&self) -> String {
+        let mut out = format!("{}\n{}\n\n", self.title, "=".repeat(self.title.chars().count()));
+        for (label, value) in self.metadata() {
+            let _ = writeln!(out, "{:<7} {}", format!("{}:", label), value);
+        }
+        if let Some(prompt) = &self.conversation.system_prompt {
+            let _ = write!(out, "\n[System prompt]\n{}\n", prompt.trim_end());
+        }
+        for message in &self.branch {
+            let _ = write!(out, "\n[{}]", role_heading(&message.role));
+            if let Some(note) = usage_note(message) {
+                let _ = write!(out, " ({})", note);
+            }
+            let _ = writeln!(out, "\n{}", message.content.trim_end());
+        }
+        out
+    }
+
+    fn html(&self) -> String {
+        let mut out = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        let _ = writeln!(out, "<title>{}</title>", escape_html(&self.title));
+        out.push_str(HTML_STYLE);
+        out.push_str("</head>\n<body>\n");
+        let _ = writeln!(out, "<h1>{}</h1>\n<dl>", escape_html(&self.title));
+        for (label, value) in self.metadata() {
+            let _ = writeln!(out, "<dt>{}</dt><dd>{}</dd>", label, escape_html(&value));
+        }
+        out.push_str("</dl>\n");
+        if let Some(prompt) = &self.conversation.system_prompt {
+            let _ = writeln!(
+                out,
+                "<section class=\"system\">\n<h2>System prompt</h2>\n{}</section>",
+                markdown_to_html(prompt)
+            );
+        }
+        for message in &self.branch {
+            let _ = writeln!(
+                out,
+                "<section class=\"{}\">\n<h2>{}</h2>",
+                escape_html(&message.role),
+                escape_html(&role_heading(&message.role))
+            );
+            out.push_str(&markdown_to_html(&message.content));
+            if let Some(note) = usage_note(message) {
+                let _ = writeln!(out, "<p class=\"usage\">{}</p>", note);
+            }
+            out.push_str("</section>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+const HTML_STYLE: &str = "<style>
+body { font-family: sans-serif; max-width: 50em; margin: 2em auto; line-height: 1.5; }
+dt { font-weight: bold; float: left; clear: left; width: 5em; }
+dd { margin-left: 6em; }
+section { border-top: 1px solid #ccc; margin-top: 1.5em; }
+pre { background: #f4f4f4; padding: 0.75em; overflow-x: auto; }
+code { font-family: monospace; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; }
+.usage { color: #777; font-size: 0.85em; }
+</style>
+";
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Whether a link target can be emitted as an `href`: http, https and mailto
+/// links, and relative ones. Other schemes such as `javascript:` and `data:`
+/// could run code when the exported page is opened and clicked.
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters inside a scheme
+    let url: String = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(end) if url[end..].starts_with(':') => {
+            matches!(url[..end].to_ascii_lowercase().as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+/// Message text as HTML. Raw HTML in a message is shown as text rather than
+/// passed through, and links with an unsafe target as their text alone.
+pub fn markdown_to_html(content: &str) -> String {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES;
+    let mut out = String::new();
+    let mut in_table_head = false;
+    // Whether each open link or image was written as an <a>
+    let mut links = Vec::new();
+    for event in Parser::new_ext(content, options) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => out.push_str("<p>"),
+                Tag::Heading { level, .. } => {
+                    let _ = write!(out, "<h{}>", heading_number(level));
+                }
+                Tag::BlockQuote(_) => out.push_str("<blockquote>\n"),
+                Tag::CodeBlock(kind) => {
+                    let language = match kind {
+                        CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+                        CodeBlockKind::Indented => String::new(),
+                    };
+                    if language.is_empty() {
+                        out.push_str("<pre><code>");
+                    } else {
+                        let _ = write!(out, "<pre><code class=\"language-{}\">", escape_html(&language));
+                    }
+                }
+                Tag::List(Some(1)) => out.push_str("<ol>\n"),
+                Tag::List(Some(start)) => {
+                    let _ = writeln!(out, "<ol start=\"{}\">", start);
+                }
+                Tag::List(None) => out.push_str("<ul>\n"),
+                Tag::Item => out.push_str("<li>"),
+                Tag::Table(_) => out.push_str("<table>\n"),
+                Tag::TableHead => {
+                    in_table_head = true;
+                    out.push_str("<thead><tr>");
+                }
+                Tag::TableRow => out.push_str("<tr>"),
+                Tag::TableCell => out.push_str(if in_table_head { "<th>" } else { "<td>" }),
+                Tag::Emphasis => out.push_str("<em>"),
+                Tag::Strong => out.push_str("<strong>"),
+                Tag::Strikethrough => out.push_str("<del>"),
+                Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                    let safe = is_safe_url(&dest_url);
+                    if safe {
+                        let _ = write!(out, "<a href=\"{}\">", escape_html(&dest_url));
+                    }
+                    links.push(safe);
+                }
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph => out.push_str("</p>\n"),
+                TagEnd::Heading(level) => {
+                    let _ = writeln!(out, "</h{}>", heading_number(level));
+                }
+                TagEnd::BlockQuote(_) => out.push_str("</blockquote>\n"),
+                TagEnd::CodeBlock => out.push_str("</code></pre>\n"),
+                TagEnd::List(true) => out.push_str("</ol>\n"),
+                TagEnd::List(false) => out.push_str("</ul>\n"),
+                TagEnd::Item => out.push_str("</li>\n"),
+                TagEnd::Table => out.push_str("</tbody></table>\n"),
+                TagEnd::TableHead => {
+                    in_table_head = false;
+                    out.push_str("</tr></thead><tbody>\n");
+                }
+                TagEnd::TableRow => out.push_str("</tr>\n"),
+                TagEnd::TableCell => out.push_str(if in_table_head { "</th>" } else { "</td>" }),
+                TagEnd::Emphasis => out.push_str("</em>"),
+                TagEnd::Strong => out.push_str("</strong>"),
+                TagEnd::Strikethrough => out.push_str("</del>"),
+                TagEnd::Link | TagEnd::Image => {
+                    out.push_str(if links.pop().unwrap_or(false) { "</a>" } else { "" });
+                }
+                _ => {}
+            },
+            Event::Text(text) | Event::InlineMath(text) | Event::DisplayMath(text) => {
+                out.push_str(&escape_html(&text))
+            }
+            Event::Html(html) | Event::InlineHtml(html) => out.push_str(&escape_html(&html)),
+            Event::Code(code) => {
+                let _ = write!(out, "<code>{}</code>", escape_html(&code));
+            }
+            Event::SoftBreak => out.push('\n'),
+            Event::HardBreak => out.push_str("<br>\n"),
+            Event::Rule => out.push_str("<hr>\n"),
+            Event::TaskListMarker(checked) => {
+                out.push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            Event::FootnoteReference(name) => {
+                let _ = write!(out, "[^{}]", escape_html(&name));
+            }
+        }
+    }
+    out
+}
+
+/// HTML heading level for a heading in a message, two below the message's own
+fn heading_number(level: HeadingLevel) -> usize {
+    match level {
+        HeadingLevel::H1 => 3,
+        HeadingLevel::H2 => 4,
+        HeadingLevel::H3 => 5,
+        _ => 6,
+    }
+}
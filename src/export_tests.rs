@@ -0,0 +1,205 @@
+//! Unit tests for exporting conversations
+//! Tests the Markdown, HTML and plain text documents, converting saved files
+//! and the `export` subcommand
+
+use clap::Parser;
+use tempfile::TempDir;
+
+use crate::api::{Message, Usage};
+use crate::client::ConversationClient;
+use crate::config::{Args, Command};
+use crate::export::*;
+use crate::handlers::file_ops::{save_conversation, SavedConversation};
+use crate::test_support::message;
+
+const REPLY: &str = "Use `read_to_string`:\n\n```rust\nlet text = std::fs::read_to_string(\"a.txt\")?;\n```";
+
+/// A titled two-message conversation with a system prompt and token counts
+fn sample_client() -> ConversationClient {
+    let mut client = ConversationClient::new("key".to_string(), "test-model".to_string(), 100, 0.7);
+    client.title = Some("Reading files".to_string());
+    client.system_prompt = Some("Be terse.".to_string());
+    client.messages.push(message("user", "How do I read a file?"));
+    client.messages.push(Message {
+        usage: Some(Usage { input_tokens: 12, output_tokens: 34 }),
+        ..message("assistant", REPLY)
+    });
+    client.total_input_tokens = 12;
+    client.total_output_tokens = 34;
+    client
+}
+
+#[cfg(test)]
+mod format_tests {
+    use super::*;
+
+    #[test]
+    fn test_format_names() {
+        assert_eq!(ExportFormat::from_name("md"), Some(ExportFormat::Markdown));
+        assert_eq!(ExportFormat::from_name(" HTML"), Some(ExportFormat::Html));
+        assert_eq!(ExportFormat::from_name("text"), Some(ExportFormat::Text));
+        assert_eq!(ExportFormat::from_name("pdf"), None);
+        assert_eq!(ExportFormat::from_name(""), None);
+        assert_eq!(ExportFormat::Text.extension(), "txt");
+    }
+
+    #[test]
+    fn test_markdown_document() {
+        let document = export_conversation(&SavedConversation::new(&sample_client()), ExportFormat::Markdown);
+
+        assert!(document.starts_with("# Reading files\n"));
+        assert!(document.contains("- **Model:** test-model\n"));
+        assert!(document.contains("- **Tokens:** 12 input, 34 output, 46 total\n"));
+        assert!(document.contains("## System prompt\n\nBe terse.\n"));
+        assert!(document.contains("## User\n\nHow do I read a file?\n"));
+        // The reply is kept as written, fence language and all
+        assert!(document.contains(&format!("## Assistant\n\n{}\n", REPLY)));
+        assert!(document.contains("*12 input tokens, 34 output tokens*"));
+    }
+
+    #[test]
+    fn test_text_document() {
+        let document = export_conversation(&SavedConversation::new(&sample_client()), ExportFormat::Text);
+
+        assert!(document.starts_with("Reading files\n=============\n"));
+        assert!(document.contains("Model:  test-model\n"));
+        assert!(document.contains("[User]\nHow do I read a file?\n"));
+        assert!(document.contains("[Assistant] (12 input tokens, 34 output tokens)\n"));
+        assert!(document.contains("```rust\n"));
+    }
+
+    #[test]
+    fn test_html_document() {
+        let mut client = sample_client();
+        client.messages[0].content = "Is <T> & \"U\" valid?".to_string();
+        let document = export_conversation(&SavedConversation::new(&client), ExportFormat::Html);
+
+        assert!(document.starts_with("<!DOCTYPE html>"));
+        assert!(document.contains("<title>Reading files</title>"));
+        assert!(document.contains("<h2>Assistant</h2>"));
+        assert!(document.contains("Is &lt;T&gt; &amp; &quot;U&quot; valid?"));
+        assert!(document.contains("<pre><code class=\"language-rust\">let text"));
+        assert!(document.contains("<code>read_to_string</code>"));
+    }
+
+    #[test]
+    fn test_markdown_to_html() {
+        let html = markdown_to_html("# Top\n\n- one\n- **two**\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n<script>");
+
+        // Headings sit below the message's own h2
+        assert!(html.contains("<h3>Top</h3>"));
+        assert!(html.contains("<ul>\n<li>one</li>\n<li><strong>two</strong></li>\n</ul>"));
+        assert!(html.contains("<thead><tr><th>a</th><th>b</th></tr></thead>"));
+        assert!(html.contains("<td>1</td>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+    }
+
+    /// Only web, mail and relative links become live; the rest keep just their text
+    #[test]
+    fn test_unsafe_links_are_plain_text() {
+        let html = markdown_to_html(
+            "[a](https://example.com) [b](mailto:me@example.com) [c](docs/x.md#top) \
+             [d](javascript:alert(1)) [e](JavaScript&#58;alert(1)) [f](data:text/html,x) \
+             [![g](javascript:alert(2))](https://example.com/img)",
+        );
+
+        assert!(html.contains("<a href=\"https://example.com\">a</a>"));
+        assert!(html.contains("<a href=\"mailto:me@example.com\">b</a>"));
+        assert!(html.contains("<a href=\"docs/x.md#top\">c</a>"));
+        assert!(html.contains(" d "));
+        assert!(html.contains(" e "));
+        assert!(html.contains(" f "));
+        assert!(html.contains("<a href=\"https://example.com/img\">g</a>"));
+        assert!(!html.to_lowercase().contains("javascript"), "{}", html);
+        assert!(!html.contains("data:"));
+    }
+
+    #[test]
+    fn test_untitled_conversation_uses_first_prompt() {
+        let mut client = sample_client();
+        client.title = None;
+        let document = export_conversation(&SavedConversation::new(&client), ExportFormat::Markdown);
+        assert!(document.starts_with("# How do I read a file?\n"));
+    }
+
+    /// Branches that weren't on screen stay out of the document
+    #[test]
+    fn test_only_current_branch_is_exported() {
+        let mut client = sample_client();
+        client.record_branch();
+        client.truncate_messages(1);
+        client.messages.push(message("assistant", "Open it with File::open."));
+
+        let document = export_conversation(&SavedConversation::new(&client), ExportFormat::Markdown);
+        assert!(document.contains("File::open"));
+        assert!(!document.contains("read_to_string"));
+    }
+}
+
+#[cfg(test)]
+mod file_tests {
+    use super::*;
+
+    #[test]
+    fn test_export_file_writes_output() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let input = temp_dir.path().join("chat.json");
+        let output = temp_dir.path().join("chat.md");
+        save_conversation(&sample_client(), &input).unwrap();
+
+        let document = export_file(&input, ExportFormat::Markdown, Some(&output)).unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), document);
+
+        let error = export_file(&temp_dir.path().join("missing.json"), ExportFormat::Text, None).unwrap_err();
+        assert!(error.to_string().contains("missing.json"));
+    }
+
+    #[test]
+    fn test_export_to_directory_names_file_after_title() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = export_to_directory(&sample_client(), ExportFormat::Html, temp_dir.path()).unwrap();
+
+        assert_eq!(path, temp_dir.path().join("reading-files.html"));
+        assert!(std::fs::read_to_string(&path).unwrap().contains("<h1>Reading files</h1>"));
+
+        let mut client = sample_client();
+        client.title = None;
+        let path = export_to_directory(&client, ExportFormat::Text, temp_dir.path()).unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("conversation-") && name.ends_with(".txt"));
+    }
+
+    /// A second conversation with the same title doesn't replace the first export
+    #[test]
+    fn test_export_to_directory_keeps_existing_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let first = export_to_directory(&sample_client(), ExportFormat::Markdown, temp_dir.path()).unwrap();
+        std::fs::write(&first, "earlier export").unwrap();
+
+        let second = export_to_directory(&sample_client(), ExportFormat::Markdown, temp_dir.path()).unwrap();
+        let third = export_to_directory(&sample_client(), ExportFormat::Markdown, temp_dir.path()).unwrap();
+
+        assert_eq!(second, temp_dir.path().join("reading-files-2.md"));
+        assert_eq!(third, temp_dir.path().join("reading-files-3.md"));
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "earlier export");
+    }
+
+    /// The subcommand works without an API key
+    #[test]
+    fn test_export_subcommand_parses() {
+        let args = Args::try_parse_from(["claudecli", "export", "chat.json", "--format", "html", "-o", "chat.html"])
+            .expect("export should not need an API key");
+        match args.command {
+            Some(Command::Export { file, format, output }) => {
+                assert_eq!(file.to_str(), Some("chat.json"));
+                assert_eq!(format, ExportFormat::Html);
+                assert_eq!(output.as_ref().and_then(|path| path.to_str()), Some("chat.html"));
+            }
+            _ => panic!("Expected the export subcommand"),
+        }
+
+        let args = Args::try_parse_from(["claudecli", "export", "chat.json"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Export { format: ExportFormat::Markdown, .. })));
+    }
+}
@@ -0,0 +1,230 @@
+// src/find.rs
+//! Searching every saved conversation at once: the session library and the
+//! save files in the saves directory. What each file holds is cached in an
+//! index in the data directory, so only files that changed since the last
+//! search are read again.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use crate::handlers::file_ops::load_conversation;
+use crate::library::{default_title, get_library_directory, local_time};
+
+/// Characters of context shown either side of a hit
+const SNIPPET_CONTEXT: usize = 40;
+
+pub fn get_index_path() -> PathBuf {
+    let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("claudecli");
+    path.push("search-index.json");
+    path
+}
+
+/// Where saved conversations are looked for when no directories are given:
+/// the library and the saves directory
+pub fn default_search_dirs(saves: &Path) -> Vec<PathBuf> {
+    vec![get_library_directory(), saves.to_path_buf()]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedMessage {
+    pub id: Option<u64>,
+    pub role: String,
+    pub content: String,
+}
+
+/// The searchable part of a save file, with what it looked like on disk
+/// when it was read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedConversation {
+    pub path: PathBuf,
+    pub title: String,
+    pub timestamp: String,
+    /// Every message on every branch
+    pub messages: Vec<IndexedMessage>,
+    modified: u128,
+    size: u64,
+}
+
+impl IndexedConversation {
+    fn read(path: &Path, modified: u128, size: u64) -> Option<Self> {
+        let conversation = load_conversation(&path.to_path_buf()).ok()?;
+        let title = conversation.title.clone().unwrap_or_else(|| default_title(&conversation.branch()));
+        Some(Self {
+            path: path.to_path_buf(),
+            title,
+            timestamp: conversation.timestamp,
+            messages: conversation
+                .messages
+                .into_iter()
+                .map(|message| IndexedMessage { id: message.id, role: message.role, content: message.content })
+                .collect(),
+            modified,
+            size,
+        })
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SearchIndex {
+    conversations: Vec<IndexedConversation>,
+}
+
+/// Modification time (in nanoseconds) and size, to tell whether a file changed
+fn file_stamp(path: &Path) -> Option<(u128, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    Some((modified, metadata.len()))
+}
+
+/// File types `load_conversation` reads: save files and the importable formats
+const CONVERSATION_EXTENSIONS: [&str; 3] = ["json", "jsonl", "md"];
+
+/// Every conversation file in `dirs`, newest first. Files the index at
+/// `index` already holds unchanged are taken from it; the index is then
+/// updated with what was found, keeping its entries for other directories.
+/// Unreadable files and other JSON or Markdown are skipped.
+pub fn scan(dirs: &[PathBuf], index: Option<&Path>) -> Vec<IndexedConversation> {
+    let mut cached: HashMap<PathBuf, IndexedConversation> = index
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<SearchIndex>(&json).ok())
+        .map(|index| index.conversations.into_iter().map(|c| (c.path.clone(), c)).collect())
+        .unwrap_or_default();
+
+    let mut seen = Vec::new();
+    let mut conversations = Vec::new();
+    for dir in dirs {
+        // The saves directory may well be the library
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+        if seen.contains(&dir) {
+            continue;
+        }
+        seen.push(dir.clone());
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .and_then(|extension| extension.to_str())
+                        .is_some_and(|extension| CONVERSATION_EXTENSIONS.contains(&extension))
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            let Some((modified, size)) = file_stamp(&path) else {
+                continue;
+            };
+            let conversation = match cached.remove(&path) {
+                Some(entry) if entry.modified == modified && entry.size == size => Some(entry),
+                _ => IndexedConversation::read(&path, modified, size),
+            };
+            conversations.extend(conversation);
+        }
+    }
+    conversations.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+    if let Some(index) = index {
+        // What's left of the cache belongs to directories not searched this
+        // time, or to files since deleted from the ones that were
+        let others = cached
+            .into_values()
+            .filter(|entry| !entry.path.parent().is_some_and(|parent| seen.iter().any(|dir| dir == parent)));
+        let entries: Vec<IndexedConversation> = conversations.iter().cloned().chain(others).collect();
+        // The index only saves time; a search works the same without it
+        let _ = write_index(index, &entries);
+    }
+    conversations
+}
+
+fn write_index(path: &Path, conversations: &[IndexedConversation]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let index = SearchIndex { conversations: conversations.to_vec() };
+    fs::write(path, serde_json::to_string(&index)?)?;
+    Ok(())
+}
+
+/// A message containing the query
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub path: PathBuf,
+    pub title: String,
+    pub timestamp: String,
+    pub message_id: Option<u64>,
+    pub role: String,
+    /// The start of the match with some text either side, on one line
+    pub snippet: String,
+}
+
+impl SearchHit {
+    /// When the conversation was last saved, in local time
+    pub fn local_time(&self) -> String {
+        local_time(&self.timestamp)
+    }
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Character position of the first occurrence of `query` (already folded)
+/// in `text`, ignoring case
+fn find_folded(text: &[char], query: &[char]) -> Option<usize> {
+    if query.is_empty() || query.len() > text.len() {
+        return None;
+    }
+    (0..=text.len() - query.len()).find(|&start| {
+        text[start..start + query.len()].iter().map(|&c| fold(c)).eq(query.iter().copied())
+    })
+}
+
+/// The hit at `start..end` with up to `SNIPPET_CONTEXT` characters around
+/// it, whitespace collapsed so it fits on one line
+fn snippet(text: &[char], start: usize, end: usize) -> String {
+    let from = start.saturating_sub(SNIPPET_CONTEXT);
+    let to = (end + SNIPPET_CONTEXT).min(text.len());
+    let middle: String = text[from..to].iter().collect();
+    let mut snippet = middle.split_whitespace().collect::<Vec<_>>().join(" ");
+    if from > 0 {
+        snippet.insert(0, '…');
+    }
+    if to < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Messages containing `query`, ignoring case, newest conversation first
+/// and in the order they were written within each
+pub fn search(conversations: &[IndexedConversation], query: &str) -> Vec<SearchHit> {
+    let query: Vec<char> = query.trim().chars().map(fold).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut hits = Vec::new();
+    for conversation in conversations {
+        for message in &conversation.messages {
+            let text: Vec<char> = message.content.chars().collect();
+            if let Some(start) = find_folded(&text, &query) {
+                hits.push(SearchHit {
+                    path: conversation.path.clone(),
+                    title: conversation.title.clone(),
+                    timestamp: conversation.timestamp.clone(),
+                    message_id: message.id,
+                    role: message.role.clone(),
+                    snippet: snippet(&text, start, start + query.len()),
+                });
+            }
+        }
+    }
+    hits
+}
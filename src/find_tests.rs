@@ -0,0 +1,260 @@
+//! Unit tests for searching saved conversations
+//! Tests scanning directories, reusing the search index, matching messages
+//! and opening a hit from the find dialog
+
+use clap::Parser;
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{backend::TestBackend, Terminal};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use crate::api::Message;
+use crate::app::AppState;
+use crate::config::{get_default_colors, Args, Command};
+use crate::find::*;
+use crate::handlers::file_ops::{SavedConversation, SAVE_FORMAT_VERSION};
+use crate::test_support::press;
+use crate::ui::{layout::create_main_layout, render::draw_ui};
+
+fn node(id: u64, parent: Option<u64>, role: &str, content: &str) -> Message {
+    Message {
+        role: role.to_string(),
+        content: content.to_string(),
+        id: Some(id),
+        parent,
+        ..Default::default()
+    }
+}
+
+/// A question with two replies, the first of them on screen
+fn branched_conversation(title: &str, timestamp: &str) -> SavedConversation {
+    SavedConversation {
+        version: SAVE_FORMAT_VERSION.to_string(),
+        timestamp: timestamp.to_string(),
+        model: "test_model".to_string(),
+        total_input_tokens: 0,
+        total_output_tokens: 0,
+        system_prompt: None,
+        messages: vec![
+            node(0, None, "user", "How do I stop the lockfile changing?"),
+            node(1, Some(0), "assistant", "Run cargo update less often."),
+            node(2, Some(0), "assistant", "Commit Cargo.lock and build with --locked in CI."),
+        ],
+        current: Some(1),
+        title: Some(title.to_string()),
+        library_id: None,
+    }
+}
+
+fn write(dir: &Path, name: &str, conversation: &SavedConversation) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, serde_json::to_string(conversation).unwrap()).unwrap();
+    path
+}
+
+#[cfg(test)]
+mod scan_tests {
+    use super::*;
+
+    /// Save files from every directory, newest first; other files are skipped
+    #[test]
+    fn test_scan_directories() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let (first, second) = (temp_dir.path().join("a"), temp_dir.path().join("b"));
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+        write(&first, "old.json", &branched_conversation("Old", "2024-01-01T00:00:00+00:00"));
+        write(&second, "new.json", &branched_conversation("New", "2025-01-01T00:00:00+00:00"));
+        std::fs::write(second.join("package.json"), r#"{"name": "web"}"#).unwrap();
+        std::fs::write(second.join("notes.txt"), "lockfile").unwrap();
+        std::fs::write(second.join("README.md"), "Just a readme").unwrap();
+
+        // The same directory twice is only read once
+        let conversations = scan(&[first.clone(), second, first], None);
+        let titles: Vec<&str> = conversations.iter().map(|c| c.title.as_str()).collect();
+        assert_eq!(titles, vec!["New", "Old"]);
+        assert_eq!(conversations[0].messages.len(), 3);
+    }
+
+    /// JSONL and Markdown transcripts can be loaded, so they're searched too
+    #[test]
+    fn test_scan_imported_formats() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        std::fs::write(
+            temp_dir.path().join("chat.jsonl"),
+            "{\"role\": \"user\", \"content\": \"Pin the lockfile\"}\n",
+        )
+        .unwrap();
+        std::fs::write(temp_dir.path().join("chat.md"), "# Exported\n\n## User\n\nHi\n\n## Assistant\n\nHello\n").unwrap();
+
+        let conversations = scan(&[temp_dir.path().to_path_buf()], None);
+        let mut titles: Vec<&str> = conversations.iter().map(|c| c.title.as_str()).collect();
+        titles.sort();
+        assert_eq!(titles, vec!["Exported", "Pin the lockfile"]);
+    }
+
+    /// Searching one directory keeps the index entries for the others
+    #[test]
+    fn test_index_keeps_other_directories() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let (first, second) = (temp_dir.path().join("a"), temp_dir.path().join("b"));
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+        let index = temp_dir.path().join("search-index.json");
+        write(&first, "one.json", &branched_conversation("One", "2024-01-01T00:00:00+00:00"));
+        write(&second, "two.json", &branched_conversation("Two", "2025-01-01T00:00:00+00:00"));
+
+        scan(&[first.clone(), second.clone()], Some(&index));
+        assert_eq!(scan(std::slice::from_ref(&second), Some(&index)).len(), 1);
+
+        let cached = std::fs::read_to_string(&index).unwrap();
+        assert!(cached.contains("\"One\"") && cached.contains("\"Two\""), "{}", cached);
+    }
+
+    /// Unchanged files come from the index; changed ones are read again
+    #[test]
+    fn test_index_is_reused_and_refreshed() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let saves = temp_dir.path().join("saves");
+        std::fs::create_dir_all(&saves).unwrap();
+        let index = temp_dir.path().join("index").join("search-index.json");
+        let path = write(&saves, "chat.json", &branched_conversation("Chat", "2025-01-01T00:00:00+00:00"));
+        let dirs = [saves.clone()];
+
+        assert_eq!(scan(&dirs, Some(&index))[0].title, "Chat");
+        assert!(index.exists());
+
+        // Doctor the index: an unchanged file is taken from it as it is
+        let doctored = std::fs::read_to_string(&index).unwrap().replace("\"Chat\"", "\"From the index\"");
+        std::fs::write(&index, doctored).unwrap();
+        assert_eq!(scan(&dirs, Some(&index))[0].title, "From the index");
+
+        write(&saves, "chat.json", &branched_conversation("Renamed chat", "2025-01-01T00:00:00+00:00"));
+        assert_eq!(scan(&dirs, Some(&index))[0].title, "Renamed chat");
+
+        std::fs::remove_file(path).unwrap();
+        assert!(scan(&dirs, Some(&index)).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    /// Messages on every branch are searched, ignoring case
+    #[test]
+    fn test_search_all_branches() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = write(temp_dir.path(), "chat.json", &branched_conversation("Lockfiles", "2025-01-01T00:00:00+00:00"));
+        let conversations = scan(&[temp_dir.path().to_path_buf()], None);
+
+        let hits = search(&conversations, "CARGO");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[1].message_id, Some(2));
+        assert_eq!(hits[1].role, "assistant");
+        assert_eq!(hits[1].title, "Lockfiles");
+        assert_eq!(hits[1].path, path);
+
+        assert!(search(&conversations, "  ").is_empty());
+        assert!(search(&conversations, "nowhere").is_empty());
+    }
+
+    /// The snippet shows the text around the match on one line
+    #[test]
+    fn test_snippet_context() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let mut conversation = branched_conversation("Long", "2025-01-01T00:00:00+00:00");
+        let long = format!("{}\nthe needle\n{}", "a".repeat(60), "b".repeat(60));
+        conversation.messages[1].content = long;
+        write(temp_dir.path(), "chat.json", &conversation);
+        let conversations = scan(&[temp_dir.path().to_path_buf()], None);
+
+        let hits = search(&conversations, "needle");
+        assert_eq!(hits.len(), 1);
+        let snippet = &hits[0].snippet;
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains(" the needle "));
+        assert!(!snippet.contains('\n'));
+    }
+}
+
+#[cfg(test)]
+mod command_tests {
+    use super::*;
+
+    /// The subcommand works without an API key
+    #[test]
+    fn test_search_subcommand_parses() {
+        let args = Args::try_parse_from(["claudecli", "search", "lock file", "-d", "a", "--dir", "b", "--no-index"])
+            .expect("search should not need an API key");
+        match args.command {
+            Some(Command::Search { query, dirs, no_index }) => {
+                assert_eq!(query, "lock file");
+                assert_eq!(dirs, vec![PathBuf::from("a"), PathBuf::from("b")]);
+                assert!(no_index);
+            }
+            _ => panic!("Expected the search subcommand"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod dialog_tests {
+    use super::*;
+
+    /// Opening a hit on another branch puts that branch on screen and
+    /// searches the conversation from the matching message
+    #[tokio::test]
+    async fn test_open_hit_on_other_branch() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        write(temp_dir.path(), "chat.json", &branched_conversation("Lockfiles", "2025-01-01T00:00:00+00:00"));
+        let mut app = AppState::new(
+            "test_key".to_string(),
+            "test_model".to_string(),
+            1000,
+            0.7,
+            true,
+            get_default_colors(),
+        ).expect("Failed to create AppState");
+        app.find_conversations = scan(&[temp_dir.path().to_path_buf()], None);
+        app.show_find_dialog = true;
+
+        for c in "--locked".chars() {
+            press(&mut app, KeyCode::Char(c), KeyModifiers::NONE).await;
+        }
+        assert_eq!(app.find_hits.len(), 1);
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+
+        assert!(!app.show_find_dialog);
+        assert_eq!(app.client.title.as_deref(), Some("Lockfiles"));
+        assert_eq!(app.client.messages[1].id, Some(2));
+        let search = app.search.as_ref().expect("The conversation should be searched");
+        assert_eq!((search.query.as_str(), search.start_at), ("--locked", Some(1)));
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|f| {
+            let layout = create_main_layout(f.size());
+            draw_ui(f, &mut app, &layout);
+        }).unwrap();
+        let search = app.search.as_ref().unwrap();
+        assert_eq!((search.match_count, search.current, search.start_at), (1, 0, None));
+    }
+
+    #[tokio::test]
+    async fn test_escape_closes() {
+        let mut app = AppState::new(
+            "test_key".to_string(),
+            "test_model".to_string(),
+            1000,
+            0.7,
+            true,
+            get_default_colors(),
+        ).expect("Failed to create AppState");
+        app.show_find_dialog = true;
+        press(&mut app, KeyCode::Char('x'), KeyModifiers::NONE).await;
+        assert_eq!(app.find_query, "x");
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE).await;
+        assert!(!app.show_find_dialog);
+        assert!(!app.show_exit_dialog);
+    }
+}
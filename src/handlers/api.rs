@@ -1,98 +1,112 @@
 // src/handlers/api.rs
-use anyhow::{Context, Result};
-use tokio::time::Duration;
-use crate::api::{ApiRequest, ApiResponse, ErrorResponse, Message};
-use reqwest::Client;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use crate::api::{ApiRequest, Message, Usage};
+use crate::app::{AppState, Regeneration, RetryStatus};
+use crate::backend::{ApiUpdate, ChatBackend, RequestSender, RequestUpdate};
 
-pub async fn send_message_to_api(
-    user_input: String,
-    messages: Vec<Message>,
-    api_key: String,
-    model: String,
-    max_tokens: u32,
-    temperature: f32,
-    simulate_mode: bool,
-) -> Result<(String, u32, u32, Vec<Message>)> {
-    if simulate_mode {
-        // Simulate API delay
-        tokio::time::sleep(Duration::from_millis(500)).await;
-        
-        // Generate mock response
-        let mock_response = format!(
-            "This is a simulated response to your message: \"{}\". \
-            In simulate mode, no actual API calls are made. \
-            Your message had {} characters.",
-            user_input.trim(),
-            user_input.len()
-        );
-        
-        // Don't add user message - it's already in the messages list
-        // Just add the assistant response
-        let mut updated_messages = messages; // Use the messages as-is
-        updated_messages.push(Message {
-            role: "assistant".to_string(),
-            content: mock_response.clone(),
-        });
-        
-        // Simulate token counts
-        let mock_input_tokens = user_input.len() as u32 / 4;
-        let mock_output_tokens = mock_response.len() as u32 / 4;
-        
-        Ok((mock_response, mock_input_tokens, mock_output_tokens, updated_messages))
-    } else {
-        // Real API - messages already includes the user message
-        let request = ApiRequest {
-            model: model.clone(),
-            max_tokens,
-            temperature,
-            messages: messages.clone(), // Use messages as-is
+/// Run a request on its own task so the UI keeps drawing while it's in flight.
+/// Text arrives as `ApiUpdate::Delta`, followed by exactly one `Done` or `Error`,
+/// each tagged with `request_id`.
+pub fn spawn_api_request(
+    backend: Arc<dyn ChatBackend>,
+    request: ApiRequest,
+    request_id: u64,
+    tx: RequestSender,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let (updates, mut rx) = mpsc::channel::<ApiUpdate>(64);
+        let send = async move {
+            let last = match backend.send(request, Some(&updates)).await {
+                Ok(response) => ApiUpdate::Done {
+                    input_tokens: response.input_tokens,
+                    output_tokens: response.output_tokens,
+                },
+                Err(e) => ApiUpdate::Error(format!("API Error: {}", e)),
+            };
+            updates.send(last).await.ok();
         };
+        let forward = async {
+            while let Some(update) = rx.recv().await {
+                tx.send(RequestUpdate { request: request_id, update }).await.ok();
+            }
+        };
+        tokio::join!(send, forward);
+    })
+}
 
-        let client_http = Client::new();
-        let response = client_http
-            .post("https://api.anthropic.com/v1/messages")
-            .header("Content-Type", "application/json")
-            .header("x-api-key", &api_key)
-            .header("anthropic-version", "2023-06-01")
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send request to API")?;
-
-        let status = response.status();
-        let response_text = response.text().await?;
-
-        if !status.is_success() {
-            let error_response: ErrorResponse = serde_json::from_str(&response_text)
-                .context("Failed to parse error response")?;
-            anyhow::bail!(
-                "API Error ({}): {}",
-                error_response.error.error_type,
-                error_response.error.message
-            );
-        }
-
-        let api_response: ApiResponse = serde_json::from_str(&response_text)
-            .context("Failed to parse API response")?;
-
-        let total_input_tokens = api_response.usage.input_tokens;
-        let total_output_tokens = api_response.usage.output_tokens;
+/// Send the conversation as the app's new in-flight request. Its number
+/// replaces the previous one, so anything still queued from an earlier
+/// request is ignored.
+pub fn start_request(app: &mut AppState, tx: &RequestSender) {
+    app.request_id += 1;
+    let request = app.client.build_request();
+    app.pending_request = Some(spawn_api_request(app.client.backend.clone(), request, app.request_id, tx.clone()));
+}
 
-        let assistant_response = api_response
-            .content
-            .iter()
-            .filter(|block| block.content_type == "text")
-            .map(|block| block.text.as_str())
-            .collect::<Vec<_>>()
-            .join("");
+/// Whether an update belongs to the request the app is waiting on
+pub fn is_current(app: &AppState, update: &RequestUpdate) -> bool {
+    app.waiting && update.request == app.request_id
+}
 
-        // Add only the assistant response
-        let mut updated_messages = messages;
-        updated_messages.push(Message {
+/// Append streamed text to the reply being built at the end of `messages`,
+/// starting a new assistant message if the reply hasn't begun yet
+pub fn apply_delta(messages: &mut Vec<Message>, text: &str) {
+    match messages.last_mut() {
+        Some(last) if last.role == "assistant" => last.content.push_str(text),
+        _ => messages.push(Message {
             role: "assistant".to_string(),
-            content: assistant_response.clone(),
-        });
+            content: text.to_string(),
+            ..Default::default()
+        }),
+    }
+}
 
-        Ok((assistant_response, total_input_tokens, total_output_tokens, updated_messages))
+/// Apply an update from the in-flight request to the app state. A failure
+/// opens the error dialog; the plain REPL prints `error_message` instead.
+pub fn handle_api_update(app: &mut AppState, update: ApiUpdate) {
+    match update {
+        ApiUpdate::Delta(text) => {
+            app.retry_status = None;
+            // Grow the reply in place as it streams in
+            apply_delta(&mut app.client.messages, &text);
+        }
+        ApiUpdate::Retrying { attempt, max_retries, delay } => {
+            app.retry_status = Some(RetryStatus {
+                attempt,
+                max_retries,
+                resume_at: std::time::Instant::now() + delay,
+            });
+        }
+        ApiUpdate::Done { input_tokens, output_tokens } => {
+            app.retry_status = None;
+            app.waiting = false;
+            app.pending_request = None;
+            app.status = "Ready".to_string();
+            app.client.total_input_tokens += input_tokens;
+            app.client.total_output_tokens += output_tokens;
+            // Remember what this reply cost so editing an earlier message can take it back off
+            if let Some(last) = app.client.messages.last_mut().filter(|m| m.role == "assistant") {
+                last.usage = Some(Usage { input_tokens, output_tokens });
+            }
+            app.client.record_branch();
+            if let Some(Regeneration { replaced: Some(id) }) = app.regeneration.take() {
+                if !app.ui.keep_alternate_replies {
+                    app.client.tree.remove_leaf(id);
+                }
+            }
+        }
+        ApiUpdate::Error(error_msg) => {
+            app.regeneration = None;
+            app.retry_status = None;
+            app.waiting = false;
+            app.pending_request = None;
+            app.status = "Ready".to_string();
+            app.client.record_branch();
+            // Show the actual error message
+            app.show_error_dialog = true;
+            app.error_message = error_msg;
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,150 @@
+//! Unit tests for the API handler
+//! Tests request building, reply assembly and the spawned request task
+
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+use crate::api::Message;
+use crate::app::AppState;
+use crate::backend::{ApiUpdate, RequestUpdate, SimulatedBackend};
+use crate::config::get_default_colors;
+use crate::client::ConversationClient;
+use crate::handlers::api::*;
+
+#[cfg(test)]
+mod reply_tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_delta_starts_then_extends_reply() {
+        let mut messages = vec![Message { role: "user".to_string(), content: "Hi".to_string(), ..Default::default() }];
+
+        apply_delta(&mut messages, "Hel");
+        apply_delta(&mut messages, "lo");
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].role, "assistant");
+        assert_eq!(messages[1].content, "Hello");
+    }
+}
+
+#[cfg(test)]
+mod request_tests {
+    use crate::client::ConversationClient;
+
+    #[test]
+    fn test_system_prompt_serialized_only_when_set() {
+        let mut client = ConversationClient::new("key".to_string(), "m".to_string(), 100, 0.5);
+
+        client.stream = false;
+        let json = serde_json::to_value(client.build_request()).unwrap();
+        assert!(json.get("system").is_none());
+        assert!(json.get("stream").is_none());
+
+        client.system_prompt = Some("You are terse.".to_string());
+        client.stream = true;
+        let json = serde_json::to_value(client.build_request()).unwrap();
+        assert_eq!(json["system"], "You are terse.");
+        assert_eq!(json["stream"], true);
+    }
+
+    /// Project context follows the system prompt in the request, but isn't
+    /// part of the prompt itself
+    #[test]
+    fn test_context_sent_after_system_prompt() {
+        let mut client = ConversationClient::new("key".to_string(), "m".to_string(), 100, 0.5);
+        client.context = Some("<context file=\"README.md\">\nHi\n</context>".to_string());
+        assert_eq!(client.build_request().system, client.context);
+
+        client.system_prompt = Some("Be brief.".to_string());
+        assert_eq!(
+            client.build_request().system.as_deref(),
+            Some("Be brief.\n\n<context file=\"README.md\">\nHi\n</context>")
+        );
+        assert_eq!(client.system_prompt.as_deref(), Some("Be brief."));
+    }
+
+    /// Token usage recorded on replies stays out of the request body
+    #[test]
+    fn test_message_usage_not_sent() {
+        use crate::api::{Message, Usage};
+
+        let mut client = ConversationClient::new("key".to_string(), "m".to_string(), 100, 0.5);
+        client.messages.push(Message {
+            role: "assistant".to_string(),
+            content: "Hello".to_string(),
+            usage: Some(Usage { input_tokens: 10, output_tokens: 5 }),
+            ..Default::default()
+        });
+
+        let json = serde_json::to_value(client.build_request()).unwrap();
+        assert_eq!(json["messages"], serde_json::json!([{ "role": "assistant", "content": "Hello" }]));
+    }
+}
+
+#[cfg(test)]
+mod spawn_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spawned_request_ends_with_done() {
+        let mut client = ConversationClient::new("key".to_string(), "m".to_string(), 100, 0.5)
+            .with_backend(Arc::new(SimulatedBackend { delay: Duration::ZERO, word_delay: Duration::ZERO }));
+        client.messages.push(Message { role: "user".to_string(), content: "Hi there".to_string(), ..Default::default() });
+
+        let (tx, mut rx) = mpsc::channel(256);
+        spawn_api_request(client.backend.clone(), client.build_request(), 3, tx)
+            .await
+            .unwrap();
+
+        let mut updates = Vec::new();
+        while let Some(RequestUpdate { request, update }) = rx.recv().await {
+            assert_eq!(request, 3);
+            updates.push(update);
+        }
+
+        for update in &updates {
+            if let ApiUpdate::Delta(text) = update {
+                apply_delta(&mut client.messages, text);
+            }
+        }
+        assert!(matches!(updates.last(), Some(ApiUpdate::Done { .. })));
+        assert_eq!(client.messages.len(), 2);
+        assert!(client.messages[1].content.contains("Hi there"));
+    }
+}
+
+#[cfg(test)]
+mod stale_update_tests {
+    use super::*;
+
+    /// Updates an aborted request queued before it stopped don't land in the next reply
+    #[tokio::test]
+    async fn test_updates_from_cancelled_request_are_ignored() {
+        let mut app = AppState::new(
+            "test_key".to_string(),
+            "test_model".to_string(),
+            100,
+            0.5,
+            true,
+            get_default_colors(),
+        ).expect("Failed to create AppState");
+        app.client.backend = Arc::new(SimulatedBackend { delay: Duration::from_secs(30), word_delay: Duration::ZERO });
+        let (tx, _rx) = mpsc::channel(16);
+
+        app.waiting = true;
+        start_request(&mut app, &tx);
+        let stale = RequestUpdate { request: app.request_id, update: ApiUpdate::Delta("old".to_string()) };
+        app.cancel_request();
+
+        app.waiting = true;
+        start_request(&mut app, &tx);
+        assert!(!is_current(&app, &stale));
+        let current = RequestUpdate { request: app.request_id, update: ApiUpdate::Delta("new".to_string()) };
+        assert!(is_current(&app, &current));
+
+        app.cancel_request();
+        assert!(!is_current(&app, &current));
+    }
+}
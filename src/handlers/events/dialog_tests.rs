@@ -2,7 +2,7 @@
 //! Tests the dialog navigation, state management, and user interactions
 
 use crossterm::event::KeyCode;
-use std::path::PathBuf;
+use std::path::Path;
 use tempfile::TempDir;
 use std::fs;
 
@@ -28,10 +28,10 @@ fn create_test_app_state() -> (AppState, TempDir) {
 }
 
 /// Helper function to create test files in a directory
-fn create_test_files(dir: &PathBuf, files: &[&str]) {
+fn create_test_files(dir: &Path, files: &[&str]) {
     for file in files {
-        if file.ends_with('/') {
-            let dir_path = dir.join(&file[..file.len()-1]);
+        if let Some(dirname) = file.strip_suffix('/') {
+            let dir_path = dir.join(dirname);
             fs::create_dir_all(&dir_path).expect("Failed to create test directory");
         } else {
             let file_path = dir.join(file);
@@ -224,7 +224,7 @@ mod save_dialog_tests {
         app.show_save_dialog = true;
         
         // Create test files for navigation
-        create_test_files(&temp_dir.path().to_path_buf(), &["file1.txt", "file2.txt", "file3.txt"]);
+        create_test_files(temp_dir.path(), &["file1.txt", "file2.txt", "file3.txt"]);
         load_directory_contents(&mut app.available_files, &app.current_directory, true);
         
         // Start at first item
@@ -287,6 +287,25 @@ mod save_dialog_tests {
         assert!(app.available_files.contains(&"../".to_string())); // Parent directory
         assert!(app.available_files.contains(&"[ Create New Directory ]".to_string())); // Create dir option
     }
+
+    /// Test that a titled conversation is saved under a name made from its title
+    /// Expected: The dialog opens with the name filled in and Enter saves straight away
+    #[test]
+    fn test_save_dialog_suggests_title_filename() {
+        let (mut app, temp_dir) = create_test_app_state();
+        app.client.title = Some("Reading files in Rust".to_string());
+
+        open_save_dialog(&mut app);
+        assert_eq!(app.save_filename, "reading-files-in-rust.json");
+        assert_eq!(app.dialog_cursor_pos, app.save_filename.len());
+        assert_eq!(app.file_list_state.selected(), None);
+
+        app.current_directory = temp_dir.path().to_path_buf();
+        handle_save_dialog(&mut app, KeyCode::Enter);
+
+        assert!(!app.show_save_dialog);
+        assert!(temp_dir.path().join("reading-files-in-rust.json").exists());
+    }
 }
 
 #[cfg(test)]
@@ -337,7 +356,7 @@ mod load_dialog_tests {
         app.show_load_dialog = true;
         
         // Create test files for navigation testing
-        create_test_files(&temp_dir.path().to_path_buf(), &["file1.txt", "file2.txt"]);
+        create_test_files(temp_dir.path(), &["file1.txt", "file2.txt"]);
         load_directory_contents(&mut app.available_files, &app.current_directory, false);
         
         // Start at first item
@@ -530,6 +549,113 @@ mod create_dir_dialog_tests {
     }
 }
 
+#[cfg(test)]
+mod settings_dialog_tests {
+    use super::*;
+
+    #[test]
+    fn test_open_settings_dialog() {
+        let (mut app, _temp_dir) = create_test_app_state();
+        app.settings_selection = 2;
+
+        open_settings_dialog(&mut app);
+
+        assert!(app.show_settings_dialog);
+        assert_eq!(app.settings_selection, 0);
+    }
+
+    /// Up and Down wrap around the options; Esc closes without changing any
+    #[test]
+    fn test_settings_dialog_navigation() {
+        let (mut app, _temp_dir) = create_test_app_state();
+        open_settings_dialog(&mut app);
+        let ui = app.ui;
+
+        handle_settings_dialog(&mut app, KeyCode::Up);
+        assert_eq!(app.settings_selection, crate::settings::UI_OPTIONS.len() - 1);
+        handle_settings_dialog(&mut app, KeyCode::Down);
+        assert_eq!(app.settings_selection, 0);
+        handle_settings_dialog(&mut app, KeyCode::Down);
+        assert_eq!(app.settings_selection, 1);
+
+        handle_settings_dialog(&mut app, KeyCode::Esc);
+        assert!(!app.show_settings_dialog);
+        assert_eq!(app.ui, ui);
+    }
+
+    /// An option the project sets changes for the session without being saved
+    #[test]
+    fn test_toggle_option_set_by_project() {
+        let (mut app, temp_dir) = create_test_app_state();
+        let project = temp_dir.path().join(".claudecli.toml");
+        app.ui_from_project.insert("shift_enter_sends".to_string(), project.clone());
+        open_settings_dialog(&mut app);
+
+        handle_settings_dialog(&mut app, KeyCode::Enter);
+
+        assert!(app.ui.shift_enter_sends);
+        assert!(!app.show_error_dialog);
+        assert!(app.status.contains("this session only"), "{}", app.status);
+        assert!(app.status.contains(&project.display().to_string()));
+    }
+}
+
+#[cfg(test)]
+mod model_dialog_tests {
+    use super::*;
+    use crate::settings::ModelPreset;
+
+    fn app_with_presets() -> (AppState, TempDir) {
+        let (mut app, temp_dir) = create_test_app_state();
+        for (name, model) in [("deep", "opus"), ("fast", "haiku")] {
+            app.presets.insert(name.to_string(), ModelPreset { model: model.to_string(), max_tokens: None, temperature: Some(0.1) });
+        }
+        (app, temp_dir)
+    }
+
+    /// The picker opens on the preset in use
+    #[test]
+    fn test_open_model_dialog() {
+        let (mut app, _temp_dir) = app_with_presets();
+        app.active_preset = Some("fast".to_string());
+
+        open_model_dialog(&mut app);
+
+        assert!(app.show_model_dialog);
+        assert_eq!(app.model_selection, 1);
+    }
+
+    #[test]
+    fn test_pick_preset() {
+        let (mut app, _temp_dir) = app_with_presets();
+        open_model_dialog(&mut app);
+
+        handle_model_dialog(&mut app, KeyCode::Up);
+        assert_eq!(app.model_selection, 1);
+        handle_model_dialog(&mut app, KeyCode::Enter);
+
+        assert!(!app.show_model_dialog);
+        assert_eq!(app.client.model, "haiku");
+        assert_eq!(app.client.max_tokens, 1000);
+        assert_eq!(app.client.temperature, 0.1);
+        assert_eq!(app.active_preset.as_deref(), Some("fast"));
+        assert!(app.status.contains("fast"), "{}", app.status);
+    }
+
+    /// With no presets Enter just closes the picker
+    #[test]
+    fn test_empty_model_dialog() {
+        let (mut app, _temp_dir) = create_test_app_state();
+        open_model_dialog(&mut app);
+
+        handle_model_dialog(&mut app, KeyCode::Down);
+        handle_model_dialog(&mut app, KeyCode::Enter);
+
+        assert!(!app.show_model_dialog);
+        assert_eq!(app.client.model, "test_model");
+    }
+}
+
 #[cfg(test)]
 mod property_based_tests {
     use super::*;
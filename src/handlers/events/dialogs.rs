@@ -1,8 +1,340 @@
 use crossterm::event::KeyCode;
-use crate::app::AppState;
+use crate::app::{AppState, ChatSearch};
+use crate::find::{default_search_dirs, get_index_path, scan, search};
 use crate::handlers::file_ops::{load_directory_contents, save_conversation, load_conversation};
+use crate::library::{filter_sessions, load_session_file, SessionSummary};
+use crate::settings::{save_ui_option, UI_OPTIONS};
+use crate::title::default_filename;
 use anyhow::Result;
 
+/// Restore or discard the session the last run left behind
+pub fn handle_restore_dialog(app: &mut AppState, code: KeyCode) {
+    let restore = match code {
+        KeyCode::Up | KeyCode::Left => {
+            app.restore_selected = 0;
+            return;
+        }
+        KeyCode::Down | KeyCode::Right => {
+            app.restore_selected = 1;
+            return;
+        }
+        KeyCode::Enter => app.restore_selected == 0,
+        KeyCode::Char('y') | KeyCode::Char('Y') => true,
+        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => false,
+        _ => return,
+    };
+
+    app.show_restore_dialog = false;
+    app.restore_selected = 0;
+    let Some(conversation) = app.restore_candidate.take() else {
+        return;
+    };
+    if restore {
+        app.apply_saved_conversation(conversation);
+        app.status = "Previous session restored".to_string();
+    } else if let Err(e) = app.autosave.discard() {
+        app.status = format!("Could not remove the previous session: {}", e);
+    }
+}
+
+/// Open the session library, listing what's in it now
+pub fn open_library_dialog(app: &mut AppState) {
+    let Some(library) = app.autosave.library() else {
+        app.status = "The session library is off while autosave is (--no-autosave)".to_string();
+        return;
+    };
+    app.library_sessions = library.list();
+    app.library_filter.clear();
+    app.library_matches = (0..app.library_sessions.len()).collect();
+    app.library_selection = 0;
+    app.library_rename = None;
+    app.library_confirm_delete = false;
+    app.show_library_dialog = true;
+}
+
+/// Typing filters the sessions; Enter resumes the highlighted one, Delete
+/// removes it after confirming and F2 renames it
+pub fn handle_library_dialog(app: &mut AppState, code: KeyCode) {
+    if app.library_confirm_delete {
+        app.library_confirm_delete = false;
+        if matches!(code, KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter) {
+            delete_library_session(app);
+        }
+        return;
+    }
+    if let Some(title) = &mut app.library_rename {
+        match code {
+            KeyCode::Enter => rename_library_session(app),
+            KeyCode::Esc => app.library_rename = None,
+            KeyCode::Backspace => {
+                title.pop();
+            }
+            KeyCode::Char(c) => title.push(c),
+            _ => {}
+        }
+        return;
+    }
+
+    let count = app.library_matches.len();
+    match code {
+        KeyCode::Esc => {
+            app.show_library_dialog = false;
+        }
+        KeyCode::Enter => resume_library_session(app),
+        KeyCode::Up if count > 0 => {
+            app.library_selection = (app.library_selection + count - 1) % count;
+        }
+        KeyCode::Down if count > 0 => {
+            app.library_selection = (app.library_selection + 1) % count;
+        }
+        KeyCode::Delete if count > 0 => {
+            app.library_confirm_delete = true;
+        }
+        KeyCode::F(2) => {
+            if let Some(session) = selected_library_session(app) {
+                app.library_rename = Some(session.title.clone());
+            }
+        }
+        KeyCode::Backspace => {
+            app.library_filter.pop();
+            refilter_library(app);
+        }
+        KeyCode::Char(c) => {
+            app.library_filter.push(c);
+            refilter_library(app);
+        }
+        _ => {}
+    }
+}
+
+fn selected_library_session(app: &AppState) -> Option<&SessionSummary> {
+    app.library_matches
+        .get(app.library_selection)
+        .and_then(|&position| app.library_sessions.get(position))
+}
+
+fn refilter_library(app: &mut AppState) {
+    app.library_matches = filter_sessions(&app.library_sessions, &app.library_filter);
+    app.library_selection = 0;
+}
+
+fn resume_library_session(app: &mut AppState) {
+    if app.waiting {
+        app.status = "Still waiting for the previous response (Esc to cancel)".to_string();
+        return;
+    }
+    let (Some(session), Some(library)) = (selected_library_session(app), app.autosave.library()) else {
+        return;
+    };
+    let id = session.id.clone();
+    let title = session.title.clone();
+    match library.load(&id) {
+        Ok(mut conversation) => {
+            conversation.library_id = Some(id);
+            app.apply_saved_conversation(conversation);
+            app.status = format!("Resumed \"{}\"", title);
+            app.show_library_dialog = false;
+        }
+        Err(e) => app.status = format!("Load failed: {}", e),
+    }
+}
+
+fn delete_library_session(app: &mut AppState) {
+    let (Some(session), Some(library)) = (selected_library_session(app), app.autosave.library()) else {
+        return;
+    };
+    let (id, title) = (session.id.clone(), session.title.clone());
+    match library.delete(&id) {
+        Ok(()) => {
+            app.status = format!("Deleted \"{}\"", title);
+            app.autosave.forget_entry(&id);
+            app.library_sessions.retain(|session| session.id != id);
+            app.library_matches = filter_sessions(&app.library_sessions, &app.library_filter);
+            app.library_selection = app.library_selection.min(app.library_matches.len().saturating_sub(1));
+        }
+        Err(e) => app.status = e.to_string(),
+    }
+}
+
+fn rename_library_session(app: &mut AppState) {
+    let Some(title) = app.library_rename.take() else {
+        return;
+    };
+    let (Some(session), Some(library)) = (selected_library_session(app), app.autosave.library()) else {
+        return;
+    };
+    let id = session.id.clone();
+    match library.rename(&id, &title) {
+        Ok(()) => {
+            // The open conversation would write its old title back otherwise
+            if app.autosave.entry() == Some(id.as_str()) {
+                let title = title.trim();
+                app.client.title = if title.is_empty() { None } else { Some(title.to_string()) };
+            }
+            if let Some(library) = app.autosave.library() {
+                app.library_sessions = library.list();
+            }
+            refilter_library(app);
+            if let Some(position) = app.library_matches.iter().position(|&m| app.library_sessions[m].id == id) {
+                app.library_selection = position;
+            }
+            app.status = "Session renamed".to_string();
+        }
+        Err(e) => app.status = format!("Rename failed: {}", e),
+    }
+}
+
+/// Open the find dialog, reading every saved conversation so typing can
+/// search them all
+pub fn open_find_dialog(app: &mut AppState, query: &str) {
+    app.find_conversations = scan(&default_search_dirs(&app.saves_directory), Some(&get_index_path()));
+    app.find_query = query.to_string();
+    app.find_hits = search(&app.find_conversations, &app.find_query);
+    app.find_selection = 0;
+    app.show_find_dialog = true;
+}
+
+/// Typing searches the saved conversations; Enter opens the highlighted hit
+pub fn handle_find_dialog(app: &mut AppState, code: KeyCode) {
+    let count = app.find_hits.len();
+    match code {
+        KeyCode::Esc => {
+            app.show_find_dialog = false;
+            app.find_conversations.clear();
+        }
+        KeyCode::Enter => open_find_hit(app),
+        KeyCode::Up if count > 0 => {
+            app.find_selection = (app.find_selection + count - 1) % count;
+        }
+        KeyCode::Down if count > 0 => {
+            app.find_selection = (app.find_selection + 1) % count;
+        }
+        KeyCode::Backspace => {
+            app.find_query.pop();
+            research(app);
+        }
+        KeyCode::Char(c) => {
+            app.find_query.push(c);
+            research(app);
+        }
+        _ => {}
+    }
+}
+
+fn research(app: &mut AppState) {
+    app.find_hits = search(&app.find_conversations, &app.find_query);
+    app.find_selection = 0;
+}
+
+/// Load the conversation the highlighted hit is in, on the branch holding
+/// the message, and search it for the query starting at that message
+fn open_find_hit(app: &mut AppState) {
+    if app.waiting {
+        app.status = "Still waiting for the previous response (Esc to cancel)".to_string();
+        return;
+    }
+    let Some(hit) = app.find_hits.get(app.find_selection).cloned() else {
+        return;
+    };
+    match load_session_file(&hit.path) {
+        Ok(conversation) => {
+            app.apply_saved_conversation(conversation);
+            if let Some(id) = hit.message_id {
+                app.client.switch_branch(id);
+            }
+            let index = app.client.messages.iter().position(|message| message.id.is_some() && message.id == hit.message_id);
+            app.search = Some(ChatSearch {
+                query: app.find_query.trim().to_string(),
+                start_at: index,
+                jump: true,
+                ..Default::default()
+            });
+            // Stay at the hit rather than following the new messages to the bottom
+            app.auto_scroll = false;
+            app.last_message_count = app.client.messages.len();
+            app.show_find_dialog = false;
+            app.find_conversations.clear();
+        }
+        Err(e) => app.status = format!("Load failed: {}", e),
+    }
+}
+
+/// Open the model picker on the preset in use
+pub fn open_model_dialog(app: &mut AppState) {
+    app.model_selection = app.active_preset
+        .as_ref()
+        .and_then(|active| app.presets.keys().position(|name| name == active))
+        .unwrap_or(0);
+    app.show_model_dialog = true;
+}
+
+/// Up/Down pick a preset; Enter switches to it
+pub fn handle_model_dialog(app: &mut AppState, code: KeyCode) {
+    let count = app.presets.len();
+    match code {
+        KeyCode::Esc => app.show_model_dialog = false,
+        KeyCode::Up if count > 0 => app.model_selection = (app.model_selection + count - 1) % count,
+        KeyCode::Down if count > 0 => app.model_selection = (app.model_selection + 1) % count,
+        KeyCode::Enter => {
+            if let Some(name) = app.presets.keys().nth(app.model_selection).cloned() {
+                app.status = app.switch_model(&name);
+            }
+            app.show_model_dialog = false;
+        }
+        _ => {}
+    }
+}
+
+pub fn open_help_dialog(app: &mut AppState) {
+    app.help_scroll_offset = 0;
+    app.show_help_dialog = true;
+}
+
+/// Up/Down scroll the key binding list; anything else closes it
+pub fn handle_help_dialog(app: &mut AppState, code: KeyCode) {
+    match code {
+        KeyCode::Up => app.help_scroll_offset = app.help_scroll_offset.saturating_sub(1),
+        KeyCode::Down => app.help_scroll_offset = app.help_scroll_offset.saturating_add(1),
+        KeyCode::PageUp => app.help_scroll_offset = app.help_scroll_offset.saturating_sub(10),
+        KeyCode::PageDown => app.help_scroll_offset = app.help_scroll_offset.saturating_add(10),
+        _ => app.show_help_dialog = false,
+    }
+}
+
+pub fn open_settings_dialog(app: &mut AppState) {
+    app.settings_selection = 0;
+    app.show_settings_dialog = true;
+}
+
+/// Up/Down pick an option; Enter or Space flips it and saves it to the
+/// user's settings. An option a project file sets only changes for this
+/// session, since the project's value would win again next time.
+pub fn handle_settings_dialog(app: &mut AppState, code: KeyCode) {
+    let count = UI_OPTIONS.len();
+    match code {
+        KeyCode::Esc => app.show_settings_dialog = false,
+        KeyCode::Up => app.settings_selection = (app.settings_selection + count - 1) % count,
+        KeyCode::Down => app.settings_selection = (app.settings_selection + 1) % count,
+        KeyCode::Enter | KeyCode::Char(' ') => {
+            let index = app.settings_selection;
+            let (key, _) = UI_OPTIONS[index];
+            app.ui.toggle(index);
+            if let Some(project) = app.ui_from_project.get(key) {
+                app.status = format!("Changed for this session only; {} sets this option", project.display());
+                return;
+            }
+            match save_ui_option(key, app.ui.values()[index]) {
+                Ok(path) => app.status = format!("Settings saved to {}", path.display()),
+                Err(e) => {
+                    app.show_error_dialog = true;
+                    app.error_message = format!("Failed to save settings: {}", e);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
 pub fn handle_exit_dialog(app: &mut AppState, code: KeyCode) -> Result<bool> {
     match code {
         KeyCode::Enter => {
@@ -66,58 +398,129 @@ pub fn handle_create_dir_dialog(app: &mut AppState, code: KeyCode) {
             app.show_create_dir_dialog = false;
             app.new_dir_name.clear();
         }
-        KeyCode::Backspace => {
-            if !app.new_dir_name.is_empty() {
-                let mut chars: Vec<char> = app.new_dir_name.chars().collect();
-                chars.pop();
-                app.new_dir_name = chars.into_iter().collect();
+        KeyCode::Backspace if !app.new_dir_name.is_empty() => {
+            let mut chars: Vec<char> = app.new_dir_name.chars().collect();
+            chars.pop();
+            app.new_dir_name = chars.into_iter().collect();
+        }
+        // Only allow valid directory name characters
+        KeyCode::Char(c) if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' => {
+            app.new_dir_name.push(c);
+        }
+        _ => {}
+    }
+}
+
+pub fn handle_system_dialog(app: &mut AppState, code: KeyCode) {
+    match code {
+        KeyCode::Enter => {
+            let prompt = app.system_prompt_input.trim().to_string();
+            if prompt.is_empty() {
+                app.client.system_prompt = None;
+                app.status = "System prompt cleared".to_string();
+            } else {
+                app.client.system_prompt = Some(prompt);
+                app.status = "System prompt updated".to_string();
             }
+            app.show_system_dialog = false;
+            app.system_prompt_input.clear();
+            app.dialog_cursor_pos = 0;
+        }
+        KeyCode::Esc => {
+            app.show_system_dialog = false;
+            app.system_prompt_input.clear();
+            app.dialog_cursor_pos = 0;
+        }
+        KeyCode::Backspace if app.dialog_cursor_pos > 0 => {
+            let mut chars: Vec<char> = app.system_prompt_input.chars().collect();
+            chars.remove(app.dialog_cursor_pos - 1);
+            app.system_prompt_input = chars.into_iter().collect();
+            app.dialog_cursor_pos -= 1;
+        }
+        KeyCode::Delete if app.dialog_cursor_pos < app.system_prompt_input.chars().count() => {
+            let mut chars: Vec<char> = app.system_prompt_input.chars().collect();
+            chars.remove(app.dialog_cursor_pos);
+            app.system_prompt_input = chars.into_iter().collect();
+        }
+        KeyCode::Left if app.dialog_cursor_pos > 0 => {
+            app.dialog_cursor_pos -= 1;
+        }
+        KeyCode::Right if app.dialog_cursor_pos < app.system_prompt_input.chars().count() => {
+            app.dialog_cursor_pos += 1;
+        }
+        KeyCode::Home => {
+            app.dialog_cursor_pos = 0;
+        }
+        KeyCode::End => {
+            app.dialog_cursor_pos = app.system_prompt_input.chars().count();
         }
         KeyCode::Char(c) => {
-            // Only allow valid directory name characters
-            if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' {
-                app.new_dir_name.push(c);
-            }
+            let mut chars: Vec<char> = app.system_prompt_input.chars().collect();
+            chars.insert(app.dialog_cursor_pos, c);
+            app.system_prompt_input = chars.into_iter().collect();
+            app.dialog_cursor_pos += 1;
         }
         _ => {}
     }
 }
 
+/// Open the save dialog in the saves directory, suggesting a filename made
+/// from the conversation's title
+pub fn open_save_dialog(app: &mut AppState) {
+    app.show_save_dialog = true;
+    app.save_filename = default_filename(&app.client);
+    app.dialog_cursor_pos = app.save_filename.chars().count();
+    app.current_directory = app.saves_directory.clone();
+    load_directory_contents(&mut app.available_files, &app.current_directory, true);
+    // With a name ready, Enter saves rather than opening the first folder
+    app.file_list_state.select(if app.save_filename.is_empty() { Some(0) } else { None });
+}
+
+/// Enter opens the highlighted folder, or saves under the typed name. Typing
+/// moves the focus from the list back to the name.
 pub fn handle_save_dialog(app: &mut AppState, code: KeyCode) {
     match code {
         KeyCode::Enter => {
-            if !app.save_filename.is_empty() {
-                let mut filepath = app.current_directory.clone();
-                filepath.push(&app.save_filename);
-                match save_conversation(&app.client, &filepath) {
-                    Ok(_) => app.status = format!("Conversation saved to {}", filepath.display()),
-                    Err(e) => app.status = format!("Save failed: {}", e),
+            let selected = app.file_list_state
+                .selected()
+                .and_then(|selected| app.available_files.get(selected))
+                .cloned();
+            match selected {
+                Some(entry) if entry == "[ Create New Directory ]" => {
+                    app.show_create_dir_dialog = true;
+                    app.new_dir_name.clear();
                 }
-                app.show_save_dialog = false;
-                app.save_filename.clear();
-                app.dialog_cursor_pos = 0;
-            } else if let Some(selected) = app.file_list_state.selected() {
-                if selected < app.available_files.len() {
-                    let filename = &app.available_files[selected];
-                    if filename == "../" {
+                Some(entry) if entry.ends_with('/') => {
+                    if entry == "../" {
                         if let Some(parent) = app.current_directory.parent() {
                             app.current_directory = parent.to_path_buf();
-                            load_directory_contents(&mut app.available_files, &app.current_directory, true);
-                            app.file_list_state.select(Some(0));
                         }
-                    } else if filename == "[ Create New Directory ]" {
-                        app.show_create_dir_dialog = true;
-                        app.new_dir_name.clear();
-                    } else if filename.ends_with('/') {
-                        let dirname = &filename[..filename.len()-1];
-                        app.current_directory.push(dirname);
-                        load_directory_contents(&mut app.available_files, &app.current_directory, true);
-                        app.file_list_state.select(Some(0));
-                    } else if !filename.starts_with('(') {
-                        app.save_filename = filename.clone();
-                        app.dialog_cursor_pos = app.save_filename.len();
+                    } else {
+                        app.current_directory.push(&entry[..entry.len() - 1]);
                     }
+                    load_directory_contents(&mut app.available_files, &app.current_directory, true);
+                    app.file_list_state.select(if app.save_filename.is_empty() { Some(0) } else { None });
                 }
+                _ if !app.save_filename.is_empty() => {
+                    let mut filepath = app.current_directory.clone();
+                    filepath.push(&app.save_filename);
+                    match save_conversation(&app.client, &filepath) {
+                        Ok(_) => {
+                            app.status = format!("Conversation saved to {}", filepath.display());
+                            // Nothing left to recover from the session file
+                            app.autosave.mark_saved(&app.client).ok();
+                        }
+                        Err(e) => app.status = format!("Save failed: {}", e),
+                    }
+                    app.show_save_dialog = false;
+                    app.save_filename.clear();
+                    app.dialog_cursor_pos = 0;
+                }
+                Some(filename) if !filename.starts_with('(') => {
+                    app.dialog_cursor_pos = filename.chars().count();
+                    app.save_filename = filename;
+                }
+                _ => {}
             }
         }
         KeyCode::Esc => {
@@ -149,19 +552,19 @@ pub fn handle_save_dialog(app: &mut AppState, code: KeyCode) {
                 app.file_list_state.select(Some(0));
             }
         }
-        KeyCode::Backspace => {
-            if !app.save_filename.is_empty() && app.dialog_cursor_pos > 0 {
-                let mut chars: Vec<char> = app.save_filename.chars().collect();
-                chars.remove(app.dialog_cursor_pos - 1);
-                app.save_filename = chars.into_iter().collect();
-                app.dialog_cursor_pos -= 1;
-            }
+        KeyCode::Backspace if !app.save_filename.is_empty() && app.dialog_cursor_pos > 0 => {
+            let mut chars: Vec<char> = app.save_filename.chars().collect();
+            chars.remove(app.dialog_cursor_pos - 1);
+            app.save_filename = chars.into_iter().collect();
+            app.dialog_cursor_pos -= 1;
+            app.file_list_state.select(None);
         }
         KeyCode::Char(c) => {
             let mut chars: Vec<char> = app.save_filename.chars().collect();
             chars.insert(app.dialog_cursor_pos, c);
             app.save_filename = chars.into_iter().collect();
             app.dialog_cursor_pos += 1;
+            app.file_list_state.select(None);
         }
         _ => {}
     }
@@ -189,14 +592,9 @@ pub fn handle_load_dialog(app: &mut AppState, code: KeyCode) {
                         filepath.push(filename);
                         match load_conversation(&filepath) {
                             Ok(conversation) => {
-                                app.client.messages = conversation.messages;
-                                app.client.total_input_tokens = conversation.total_input_tokens;
-                                app.client.total_output_tokens = conversation.total_output_tokens;
+                                app.apply_saved_conversation(conversation);
                                 app.status = format!("Conversation loaded from {}", filepath.display());
-                                app.auto_scroll = true;
                                 app.show_load_dialog = false;
-                                // Clear the highlight cache since we have new messages
-                                app.clear_highlight_cache();
                             }
                             Err(e) => app.status = format!("Load failed: {}", e),
                         }
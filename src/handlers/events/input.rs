@@ -1,36 +1,66 @@
 use crossterm::event::KeyModifiers;
 use crate::app::AppState;
 use crate::api::Message;
-use crate::config::SHIFT_ENTER_SENDS;
+use crate::backend::RequestSender;
+use crate::export::{export_to_directory, ExportFormat};
+use super::dialogs::{open_find_dialog, open_library_dialog, open_help_dialog, open_model_dialog, open_save_dialog, open_settings_dialog};
+use super::regenerate::regenerate_reply;
+use super::search::start_search;
+use super::selection::start_message_selection;
 use crate::handlers::{
-    api::send_message_to_api,
-    file_ops::{get_saves_directory, load_directory_contents},
+    api::start_request,
+    file_ops::load_directory_contents,
 };
-use tokio::sync::mpsc;
 use anyhow::Result;
 
 pub async fn handle_enter_key(
     app: &mut AppState,
     modifiers: KeyModifiers,
-    tx: &mpsc::Sender<Result<(String, u32, u32, Vec<Message>), String>>,
+    tx: &RequestSender,
 ) -> Result<()> {
     // Check for commands first
     if app.input == "/save" {
-        app.show_save_dialog = true;
-        app.save_filename.clear();
-        app.dialog_cursor_pos = 0;
-        app.current_directory = get_saves_directory();
-        load_directory_contents(&mut app.available_files, &app.current_directory, true);
-        app.file_list_state.select(Some(0));
+        open_save_dialog(app);
         app.input.clear();
         app.cursor_position = 0;
     } else if app.input == "/load" {
         app.show_load_dialog = true;
-        app.current_directory = get_saves_directory();
+        app.current_directory = app.saves_directory.clone();
         load_directory_contents(&mut app.available_files, &app.current_directory, false);
         app.file_list_state.select(Some(0));
         app.input.clear();
         app.cursor_position = 0;
+    } else if app.input == "/" {
+        // A lone slash opens the search bar
+        app.input.clear();
+        app.cursor_position = 0;
+        start_search(app);
+    } else if app.input == "/library" || app.input == "/sessions" {
+        app.input.clear();
+        app.cursor_position = 0;
+        open_library_dialog(app);
+    } else if app.input == "/find" || app.input.starts_with("/find ") {
+        let query = app.input.strip_prefix("/find").unwrap_or_default().trim().to_string();
+        app.input.clear();
+        app.cursor_position = 0;
+        open_find_dialog(app, &query);
+    } else if app.input == "/model" || app.input.starts_with("/model ") {
+        let name = app.input.strip_prefix("/model").unwrap_or_default().trim().to_string();
+        app.input.clear();
+        app.cursor_position = 0;
+        if name.is_empty() {
+            open_model_dialog(app);
+        } else {
+            app.status = app.switch_model(&name);
+        }
+    } else if app.input == "/keys" || app.input == "/help" {
+        app.input.clear();
+        app.cursor_position = 0;
+        open_help_dialog(app);
+    } else if app.input == "/settings" {
+        app.input.clear();
+        app.cursor_position = 0;
+        open_settings_dialog(app);
     } else if app.input == "/colors" || app.input == "/color" {
         app.show_color_dialog = true;
         app.color_dialog_selection = 0;
@@ -43,8 +73,34 @@ pub async fn handle_enter_key(
         app.profile_dialog_scroll_offset = 0;
         app.input.clear();
         app.cursor_position = 0;
+    } else if app.input == "/edit" {
+        app.input.clear();
+        app.cursor_position = 0;
+        start_message_selection(app);
+    } else if app.input == "/retry" {
+        app.input.clear();
+        app.cursor_position = 0;
+        regenerate_reply(app, tx);
+    } else if app.input == "/export" || app.input.starts_with("/export ") {
+        export_conversation(app);
+        app.input.clear();
+        app.cursor_position = 0;
+    } else if app.input == "/system" {
+        open_system_dialog(app);
+        app.input.clear();
+        app.cursor_position = 0;
+    } else if let Some(prompt) = app.input.strip_prefix("/system ") {
+        let prompt = prompt.trim().to_string();
+        app.status = if prompt.is_empty() {
+            "System prompt cleared".to_string()
+        } else {
+            "System prompt updated".to_string()
+        };
+        app.client.system_prompt = if prompt.is_empty() { None } else { Some(prompt) };
+        app.input.clear();
+        app.cursor_position = 0;
     } else if modifiers.contains(KeyModifiers::SHIFT) || modifiers.contains(KeyModifiers::ALT) {
-        if SHIFT_ENTER_SENDS && !app.input.is_empty() {
+        if app.ui.shift_enter_sends && !app.input.is_empty() {
             send_message(app, tx).await?;
         } else {
             // Shift/Alt+Enter adds newline
@@ -56,8 +112,8 @@ pub async fn handle_enter_key(
     } else if modifiers.contains(KeyModifiers::CONTROL) && !app.input.is_empty() {
         send_message(app, tx).await?;
     } else {
-        // Regular Enter behavior depends on the feature flag
-        if SHIFT_ENTER_SENDS {
+        // Regular Enter behavior depends on the setting
+        if app.ui.shift_enter_sends {
             // Regular Enter inserts a newline
             let mut chars: Vec<char> = app.input.chars().collect();
             chars.insert(app.cursor_position, '\n');
@@ -74,10 +130,17 @@ pub async fn handle_enter_key(
     Ok(())
 }
 
-async fn send_message(
+/// Send the contents of the input box as the next user message
+pub async fn send_message(
     app: &mut AppState,
-    tx: &mpsc::Sender<Result<(String, u32, u32, Vec<Message>), String>>,
+    tx: &RequestSender,
 ) -> Result<()> {
+    if app.waiting {
+        // Only one request at a time - the reply to the previous one is still arriving
+        app.status = "Still waiting for the previous response (Esc to cancel)".to_string();
+        return Ok(());
+    }
+
     app.waiting = true;
     app.status = "Sending to Claude...".to_string();
     app.progress_i = 0;
@@ -88,47 +151,51 @@ async fn send_message(
     app.history_index = None;
     app.input_draft = None;
 
+    // An edited message replaces the original and everything that followed it,
+    // which stays in the tree as another branch
+    if let Some(index) = app.editing_message.take() {
+        app.client.truncate_messages(index);
+        if let Some(stashed) = app.input_before_edit.take() {
+            app.cursor_position = stashed.chars().count();
+            app.input = stashed;
+        }
+    }
+
     // Add to rustyline history
     app.rl.add_history_entry(&user_input).ok();
 
     // Add user message
     app.client.messages.push(Message {
         role: "user".to_string(),
-        content: user_input.clone(),
+        content: user_input,
+        ..Default::default()
     });
+    app.client.record_branch();
 
-    // Spawn API call with channel
-    let api_key = app.client.api_key.clone();
-    let model = app.client.model.clone();
-    let max_tokens = app.client.max_tokens;
-    let temperature = app.client.temperature;
-    let messages = app.client.messages.clone();
-    let simulate = app.simulate_mode;
-    let tx_clone = tx.clone();
-
-    tokio::spawn(async move {
-        match send_message_to_api(
-            user_input,
-            messages,
-            api_key,
-            model,
-            max_tokens,
-            temperature,
-            simulate,
-        ).await {
-            Ok((response, input_tokens, output_tokens, updated_messages)) => {
-                tx_clone.send(Ok((response, input_tokens, output_tokens, updated_messages))).await.ok();
-            }
-            Err(e) => {
-                let error_msg = format!("API Error: {}", e);
-                tx_clone.send(Err(error_msg)).await.ok();
-            }
-        }
-    });
+    start_request(app, tx);
 
     Ok(())
 }
 
+/// Export the conversation in the format named after `/export`, next to saved conversations
+fn export_conversation(app: &mut AppState) {
+    let name = app.input.strip_prefix("/export").unwrap_or_default();
+    app.status = match ExportFormat::from_name(name) {
+        Some(format) => match export_to_directory(&app.client, format, &app.saves_directory) {
+            Ok(path) => format!("Exported to {}", path.display()),
+            Err(e) => format!("Export failed: {}", e),
+        },
+        None => "Usage: /export md|html|txt".to_string(),
+    };
+}
+
+/// Open the system prompt dialog, pre-filled with the current prompt
+pub fn open_system_dialog(app: &mut AppState) {
+    app.show_system_dialog = true;
+    app.system_prompt_input = app.client.system_prompt.clone().unwrap_or_default();
+    app.dialog_cursor_pos = app.system_prompt_input.chars().count();
+}
+
 pub fn handle_backspace(app: &mut AppState) {
     if app.cursor_position > 0 {
         let mut chars: Vec<char> = app.input.chars().collect();
@@ -154,17 +221,12 @@ pub fn handle_char_input(app: &mut AppState, c: char) {
         app.input.insert(app.cursor_position, c);
         app.cursor_position += 1;
     } else if app.input == "/save" && c == ' ' {
-        app.show_save_dialog = true;
-        app.save_filename.clear();
-        app.dialog_cursor_pos = 0;
-        app.current_directory = get_saves_directory();
-        load_directory_contents(&mut app.available_files, &app.current_directory, true);
-        app.file_list_state.select(Some(0));
+        open_save_dialog(app);
         app.input.clear();
         app.cursor_position = 0;
     } else if app.input == "/load" && c == ' ' {
         app.show_load_dialog = true;
-        app.current_directory = get_saves_directory();
+        app.current_directory = app.saves_directory.clone();
         load_directory_contents(&mut app.available_files, &app.current_directory, false);
         app.file_list_state.select(Some(0));
         app.input.clear();
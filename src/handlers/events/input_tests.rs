@@ -0,0 +1,227 @@
+//! Unit tests for message input handling
+//! Tests sending guards and cancelling an in-flight request
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tokio::sync::mpsc;
+
+use crate::app::AppState;
+use crate::config::get_default_colors;
+use crate::handlers::events::handle_key_event;
+use crate::test_support::message;
+
+/// Helper function to create a test AppState in simulate mode
+fn create_test_app_state() -> AppState {
+    AppState::new(
+        "test_key".to_string(),
+        "test_model".to_string(),
+        1000,
+        0.7,
+        true,
+        get_default_colors(),
+    ).expect("Failed to create AppState")
+}
+
+#[cfg(test)]
+mod cancel_request_tests {
+    use super::*;
+
+    /// Cancelling before any reply arrives removes the prompt and restores it for editing
+    #[test]
+    fn test_cancel_without_reply_restores_prompt() {
+        let mut app = create_test_app_state();
+        app.client.messages.push(message("user", "Explain monads"));
+        app.waiting = true;
+
+        assert!(app.cancel_request());
+
+        assert!(!app.waiting);
+        assert!(app.client.messages.is_empty());
+        assert_eq!(app.input, "Explain monads");
+        assert_eq!(app.cursor_position, "Explain monads".chars().count());
+    }
+
+    /// Text typed while waiting is not overwritten by the restored prompt
+    #[test]
+    fn test_cancel_keeps_text_typed_while_waiting() {
+        let mut app = create_test_app_state();
+        app.client.messages.push(message("user", "first"));
+        app.input = "second".to_string();
+        app.waiting = true;
+
+        app.cancel_request();
+
+        assert!(app.client.messages.is_empty());
+        assert_eq!(app.input, "second");
+    }
+
+    /// A partially streamed reply is kept and marked as cancelled
+    #[test]
+    fn test_cancel_with_partial_reply_marks_it() {
+        let mut app = create_test_app_state();
+        app.client.messages.push(message("user", "Hi"));
+        app.client.messages.push(message("assistant", "Hello th"));
+        app.waiting = true;
+
+        app.cancel_request();
+
+        assert_eq!(app.client.messages.len(), 2);
+        assert!(app.client.messages[1].content.starts_with("Hello th"));
+        assert!(app.client.messages[1].content.ends_with("[response cancelled]"));
+    }
+
+    /// Nothing happens when no request is pending
+    #[test]
+    fn test_cancel_when_idle_is_noop() {
+        let mut app = create_test_app_state();
+        app.client.messages.push(message("user", "Hi"));
+
+        assert!(!app.cancel_request());
+        assert_eq!(app.client.messages.len(), 1);
+    }
+
+    /// Esc while waiting aborts the spawned task rather than opening the exit dialog
+    #[tokio::test]
+    async fn test_escape_while_waiting_aborts_task() {
+        let mut app = create_test_app_state();
+        let (tx, _rx) = mpsc::channel(16);
+
+        app.input = "Hello".to_string();
+        app.cursor_position = 5;
+        handle_key_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &tx, (80, 24))
+            .await
+            .unwrap();
+        assert!(app.waiting);
+        assert!(app.pending_request.is_some());
+
+        handle_key_event(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE), &tx, (80, 24))
+            .await
+            .unwrap();
+
+        assert!(!app.waiting);
+        assert!(app.pending_request.is_none());
+        assert!(!app.show_exit_dialog);
+        assert_eq!(app.input, "Hello");
+    }
+
+    /// A second message cannot be sent while the first is still in flight
+    #[tokio::test]
+    async fn test_cannot_send_while_waiting() {
+        let mut app = create_test_app_state();
+        let (tx, _rx) = mpsc::channel(16);
+
+        app.input = "one".to_string();
+        handle_key_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &tx, (80, 24))
+            .await
+            .unwrap();
+        app.input = "two".to_string();
+        handle_key_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &tx, (80, 24))
+            .await
+            .unwrap();
+
+        assert_eq!(app.client.messages.len(), 1);
+        assert_eq!(app.input, "two");
+        app.cancel_request();
+    }
+}
+
+#[cfg(test)]
+mod system_command_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_system_command_with_text_sets_prompt() {
+        let mut app = create_test_app_state();
+        let (tx, _rx) = mpsc::channel(16);
+
+        app.input = "/system Reply in haiku.".to_string();
+        handle_key_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &tx, (80, 24))
+            .await
+            .unwrap();
+
+        assert_eq!(app.client.system_prompt.as_deref(), Some("Reply in haiku."));
+        assert!(app.client.messages.is_empty(), "Commands are not sent as messages");
+        assert!(app.input.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_system_dialog_edits_current_prompt() {
+        let mut app = create_test_app_state();
+        let (tx, _rx) = mpsc::channel(16);
+        app.client.system_prompt = Some("Be kind".to_string());
+
+        app.input = "/system".to_string();
+        handle_key_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &tx, (80, 24))
+            .await
+            .unwrap();
+        assert!(app.show_system_dialog);
+        assert_eq!(app.system_prompt_input, "Be kind");
+
+        for code in [KeyCode::Char('.'), KeyCode::Enter] {
+            handle_key_event(&mut app, KeyEvent::new(code, KeyModifiers::NONE), &tx, (80, 24))
+                .await
+                .unwrap();
+        }
+
+        assert!(!app.show_system_dialog);
+        assert_eq!(app.client.system_prompt.as_deref(), Some("Be kind."));
+    }
+
+    #[tokio::test]
+    async fn test_system_dialog_empty_clears_prompt() {
+        let mut app = create_test_app_state();
+        let (tx, _rx) = mpsc::channel(16);
+        app.client.system_prompt = Some("ab".to_string());
+
+        app.input = "/system".to_string();
+        for code in [KeyCode::Enter, KeyCode::Backspace, KeyCode::Backspace, KeyCode::Enter] {
+            handle_key_event(&mut app, KeyEvent::new(code, KeyModifiers::NONE), &tx, (80, 24))
+                .await
+                .unwrap();
+        }
+
+        assert!(app.client.system_prompt.is_none());
+    }
+}
+
+#[cfg(test)]
+mod enter_key_tests {
+    use super::*;
+
+    /// Enter follows the shift_enter_sends option as it is now, not as it was at startup
+    #[tokio::test]
+    async fn test_enter_reads_live_setting() {
+        let mut app = create_test_app_state();
+        let (tx, _rx) = mpsc::channel(16);
+
+        app.ui.shift_enter_sends = true;
+        app.input = "line".to_string();
+        app.cursor_position = 4;
+        handle_key_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &tx, (80, 24))
+            .await
+            .unwrap();
+        assert_eq!(app.input, "line\n");
+        assert!(app.client.messages.is_empty());
+
+        app.ui.shift_enter_sends = false;
+        handle_key_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &tx, (80, 24))
+            .await
+            .unwrap();
+        assert_eq!(app.client.messages.len(), 1);
+        app.cancel_request();
+    }
+
+    #[tokio::test]
+    async fn test_settings_command_opens_dialog() {
+        let mut app = create_test_app_state();
+        let (tx, _rx) = mpsc::channel(16);
+
+        app.input = "/settings".to_string();
+        handle_key_event(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &tx, (80, 24))
+            .await
+            .unwrap();
+
+        assert!(app.show_settings_dialog);
+        assert!(app.input.is_empty());
+        assert!(app.client.messages.is_empty());
+    }
+}
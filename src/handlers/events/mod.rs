@@ -1,14 +1,20 @@
 use crossterm::event::{KeyCode, KeyEvent};
 use crate::app::AppState;
-use crate::api::Message;
-use tokio::sync::mpsc;
+use crate::backend::RequestSender;
+use crate::keymap::Action;
 use anyhow::Result;
 
 mod dialogs;
 mod input;
 mod navigation;
+mod regenerate;
+mod search;
+mod selection;
 mod shortcuts;
 
+pub use input::send_message;
+pub use regenerate::regenerate_reply;
+
 // Test modules - kept separate from main code
 #[cfg(test)]
 mod dialog_tests;
@@ -17,13 +23,21 @@ mod input_tests;
 #[cfg(test)]
 mod navigation_tests;
 #[cfg(test)]
+mod regenerate_tests;
+#[cfg(test)]
+mod search_tests;
+#[cfg(test)]
+mod selection_tests;
+#[cfg(test)]
 mod shortcuts_tests;
 #[cfg(test)]
 mod integration_tests;
 
-use dialogs::{handle_exit_dialog, handle_create_dir_dialog, handle_save_dialog, handle_load_dialog, handle_color_dialog, handle_profile_dialog};
+use dialogs::{handle_restore_dialog, handle_library_dialog, handle_find_dialog, handle_settings_dialog, handle_model_dialog, handle_help_dialog, handle_exit_dialog, handle_create_dir_dialog, handle_system_dialog, handle_save_dialog, handle_load_dialog, handle_color_dialog, handle_profile_dialog};
 use input::{handle_enter_key, handle_backspace, handle_delete, handle_char_input};
 use navigation::{handle_up_key, handle_down_key, handle_page_up, handle_page_down};
+use search::handle_search_key;
+use selection::{handle_message_selection, cancel_edit};
 use shortcuts::handle_keyboard_shortcuts;
 
 // Re-export dialog scroll functions for use in UI module
@@ -32,10 +46,11 @@ pub use dialogs::{update_color_dialog_scroll_with_height, update_color_dialog_se
 pub async fn handle_key_event(
     app: &mut AppState,
     key_event: KeyEvent,
-    tx: &mpsc::Sender<Result<(String, u32, u32, Vec<Message>), String>>,
+    tx: &RequestSender,
     terminal_size: (u16, u16),
 ) -> Result<bool> {
     let KeyEvent { code, modifiers, .. } = key_event;
+    let shortcut = app.keymap.action(code, modifiers);
     
     match code {
         // Handle error dialog dismissal first
@@ -47,6 +62,10 @@ pub async fn handle_key_event(
         _ if app.show_error_dialog => {
             // Ignore all other input when error dialog is shown
         }
+        // Offer to restore the last session before anything else
+        _ if app.show_restore_dialog => {
+            handle_restore_dialog(app, code);
+        }
         // Handle exit dialog 
         _ if app.show_exit_dialog => {
             return handle_exit_dialog(app, code);
@@ -55,6 +74,10 @@ pub async fn handle_key_event(
         _ if app.show_create_dir_dialog => {
             handle_create_dir_dialog(app, code);
         }
+        // Handle system prompt dialog
+        _ if app.show_system_dialog => {
+            handle_system_dialog(app, code);
+        }
         // Handle save dialog
         _ if app.show_save_dialog => {
             handle_save_dialog(app, code);
@@ -63,6 +86,26 @@ pub async fn handle_key_event(
         _ if app.show_load_dialog => {
             handle_load_dialog(app, code);
         }
+        // Browsing the session library
+        _ if app.show_library_dialog => {
+            handle_library_dialog(app, code);
+        }
+        // Searching the saved conversations
+        _ if app.show_find_dialog => {
+            handle_find_dialog(app, code);
+        }
+        // Reading the key bindings
+        _ if app.show_help_dialog => {
+            handle_help_dialog(app, code);
+        }
+        // Picking a model preset
+        _ if app.show_model_dialog => {
+            handle_model_dialog(app, code);
+        }
+        // Changing the UI options
+        _ if app.show_settings_dialog => {
+            handle_settings_dialog(app, code);
+        }
         // Handle color dialog
         _ if app.show_color_dialog => {
             handle_color_dialog(app, code);
@@ -71,6 +114,30 @@ pub async fn handle_key_event(
         _ if app.show_profile_dialog => {
             handle_profile_dialog(app, code);
         }
+        // Searching the conversation
+        _ if app.search.is_some() => {
+            handle_search_key(app, code, modifiers, terminal_size);
+        }
+        // Picking a message to edit
+        _ if app.message_selection.is_some() => {
+            handle_message_selection(app, code);
+        }
+        // Escape while a request is in flight cancels it instead of offering to exit
+        KeyCode::Esc if app.waiting => {
+            app.cancel_request();
+        }
+        // Escape while editing an earlier message discards the edit
+        KeyCode::Esc if app.editing_message.is_some() => {
+            cancel_edit(app);
+        }
+        // Regenerating needs the channel, so it can't live with the other shortcuts
+        _ if shortcut == Some(Action::Regenerate) => {
+            regenerate_reply(app, tx);
+        }
+        // Shortcuts from the keymap take precedence over editing keys
+        _ if shortcut.is_some() => {
+            handle_keyboard_shortcuts(app, code, modifiers, terminal_size);
+        }
         // Handle main interface - Escape shows exit dialog ONLY when no other dialogs are open
         KeyCode::Esc => {
             // Show exit confirmation dialog only when in main interface
@@ -87,15 +154,11 @@ pub async fn handle_key_event(
         KeyCode::Delete => {
             handle_delete(app);
         }
-        KeyCode::Left => {
-            if app.cursor_position > 0 { 
-                app.cursor_position -= 1; 
-            }
+        KeyCode::Left if app.cursor_position > 0 => {
+            app.cursor_position -= 1;
         }
-        KeyCode::Right => {
-            if app.cursor_position < app.input.chars().count() { 
-                app.cursor_position += 1; 
-            }
+        KeyCode::Right if app.cursor_position < app.input.chars().count() => {
+            app.cursor_position += 1;
         }
         KeyCode::Up => {
             handle_up_key(app, terminal_size);
@@ -116,21 +179,10 @@ pub async fn handle_key_event(
             handle_page_down(app, terminal_size);
         }
         KeyCode::Char(c) => {
-            // Check for keyboard shortcuts first (with modifiers)
-            if handle_keyboard_shortcuts(app, code, modifiers, terminal_size) {
-                // Shortcut was handled, don't process as regular character input
-            } else {
-                // No shortcut matched, process as regular character input
-                handle_char_input(app, c);
-            }
-        }
-        // Handle keyboard shortcuts and modified keys for non-character codes
-        _ => {
-            if handle_keyboard_shortcuts(app, code, modifiers, terminal_size) {
-                // Shortcut was handled
-            }
-            // Handle all other KeyCode variants
+            handle_char_input(app, c);
         }
+        // Other keys do nothing unless the keymap binds them
+        _ => {}
     }
     
     Ok(false) // Continue running
@@ -17,7 +17,8 @@ pub fn handle_chat_scroll_down(app: &mut AppState, terminal_size: (u16, u16)) {
     // Calculate max scroll
     let mut chat_spans = Vec::new();
     for msg in &app.client.messages {
-        chat_spans.extend(format_message_for_tui_cached(&msg.role, &msg.content, &mut app.highlight_cache, app.colors.user_name, app.colors.assistant_name));
+        // Same width as the chat pane, so the rendered lines come from the cache
+        chat_spans.extend(format_message_for_tui_cached(&msg.role, &msg.content, &mut app.highlight_cache, app.colors.user_name, app.colors.assistant_name, terminal_size.0.saturating_sub(2), app.colors.border_style));
     }
     
     if !chat_spans.is_empty() {
@@ -29,7 +30,7 @@ pub fn handle_chat_scroll_down(app: &mut AppState, terminal_size: (u16, u16)) {
             if line_width == 0 {
                 total_visual_lines += 1;
             } else {
-                let wrapped_lines = ((line_width as u16 + chat_width - 1) / chat_width).max(1);
+                let wrapped_lines = (line_width as u16).div_ceil(chat_width).max(1);
                 total_visual_lines += wrapped_lines;
             }
         }
@@ -95,7 +96,8 @@ pub fn handle_page_down(app: &mut AppState, terminal_size: (u16, u16)) {
     // Calculate max scroll based on content
     let mut chat_spans = Vec::new();
     for msg in &app.client.messages {
-        chat_spans.extend(format_message_for_tui_cached(&msg.role, &msg.content, &mut app.highlight_cache, app.colors.user_name, app.colors.assistant_name));
+        // Same width as the chat pane, so the rendered lines come from the cache
+        chat_spans.extend(format_message_for_tui_cached(&msg.role, &msg.content, &mut app.highlight_cache, app.colors.user_name, app.colors.assistant_name, terminal_size.0.saturating_sub(2), app.colors.border_style));
     }
     
     if !chat_spans.is_empty() {
@@ -107,7 +109,7 @@ pub fn handle_page_down(app: &mut AppState, terminal_size: (u16, u16)) {
             if line_width == 0 {
                 total_visual_lines += 1;
             } else {
-                let wrapped_lines = ((line_width as u16 + chat_width - 1) / chat_width).max(1);
+                let wrapped_lines = (line_width as u16).div_ceil(chat_width).max(1);
                 total_visual_lines += wrapped_lines;
             }
         }
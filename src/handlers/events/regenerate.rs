@@ -0,0 +1,69 @@
+use crate::app::{AppState, Regeneration};
+use crate::backend::RequestSender;
+use crate::handlers::api::start_request;
+
+/// Ask for another answer to the last prompt. The reply on screen is dropped,
+/// and kept in the conversation tree as an alternate unless the
+/// `keep_alternate_replies` setting is off, in which case it is forgotten once
+/// the new reply is complete. Cancelling puts it back. With no reply (e.g.
+/// after an error) the prompt is simply sent again.
+pub fn regenerate_reply(app: &mut AppState, tx: &RequestSender) {
+    if app.waiting {
+        app.status = "Still waiting for the previous response (Esc to cancel)".to_string();
+        return;
+    }
+
+    app.client.record_branch();
+    let replaced = match app.client.messages.last().map(|m| (m.role.as_str(), m.id)) {
+        Some(("assistant", id)) => {
+            let last = app.client.messages.len() - 1;
+            app.client.truncate_messages(last);
+            id
+        }
+        Some(("user", _)) => None,
+        _ => {
+            app.status = "Nothing to regenerate".to_string();
+            return;
+        }
+    };
+    app.regeneration = Some(Regeneration { replaced });
+
+    app.waiting = true;
+    app.status = "Regenerating response...".to_string();
+    app.progress_i = 0;
+
+    start_request(app, tx);
+}
+
+/// Replace the last reply with the next answer to the same prompt, wrapping around
+pub fn cycle_reply_version(app: &mut AppState) {
+    if app.waiting {
+        app.status = "Still waiting for the previous response (Esc to cancel)".to_string();
+        return;
+    }
+
+    app.client.record_branch();
+    let messages = &app.client.messages;
+    let (prompt, reply) = match messages.last() {
+        Some(last) if last.role == "assistant" => (messages.len().checked_sub(2).map(|i| &messages[i]), last.id),
+        // A regeneration that failed left no reply; bring back the newest one
+        Some(last) => (Some(last), None),
+        None => (None, None),
+    };
+    let replies = app.client.tree.children(prompt.and_then(|m| m.id));
+
+    let next = match reply {
+        Some(id) if replies.len() > 1 => {
+            let position = replies.iter().position(|&r| r == id).unwrap_or(0);
+            (position + 1) % replies.len()
+        }
+        None if !replies.is_empty() => replies.len() - 1,
+        _ => {
+            app.status = "No other responses to switch to (Ctrl+R to regenerate)".to_string();
+            return;
+        }
+    };
+
+    app.client.switch_branch(replies[next]);
+    app.status = format!("Showing response {} of {}", next + 1, replies.len());
+}
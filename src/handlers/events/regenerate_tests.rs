@@ -0,0 +1,219 @@
+//! Unit tests for regenerating the last reply
+//! Tests /retry, Ctrl+R and cycling through the kept answers with Ctrl+N
+
+use std::sync::Arc;
+use crossterm::event::{KeyCode, KeyModifiers};
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+use crate::app::AppState;
+use crate::api::{Message, Usage};
+use crate::backend::{ApiUpdate, RequestUpdate, SimulatedBackend};
+use crate::config::get_default_colors;
+use crate::handlers::api::{handle_api_update, is_current};
+use crate::handlers::events::regenerate_reply;
+use crate::test_support::press_sending_to;
+
+/// App whose simulated backend answers immediately, holding one exchange
+fn app_with_reply() -> AppState {
+    let mut app = AppState::new(
+        "test_key".to_string(),
+        "test_model".to_string(),
+        1000,
+        0.7,
+        true,
+        get_default_colors(),
+    ).expect("Failed to create AppState");
+    app.client.backend = Arc::new(SimulatedBackend { delay: Duration::ZERO, word_delay: Duration::ZERO });
+    app.client.messages = vec![
+        Message { role: "user".to_string(), content: "Name a colour".to_string(), ..Default::default() },
+        Message {
+            role: "assistant".to_string(),
+            content: "Blue".to_string(),
+            usage: Some(Usage { input_tokens: 7, output_tokens: 1 }),
+            ..Default::default()
+        },
+    ];
+    app.client.total_input_tokens = 7;
+    app.client.total_output_tokens = 1;
+    app
+}
+
+/// Apply updates until the request in flight finishes
+async fn finish_reply(app: &mut AppState, rx: &mut mpsc::Receiver<RequestUpdate>) {
+    while app.waiting {
+        let update = rx.recv().await.expect("Request should finish");
+        if is_current(app, &update) {
+            handle_api_update(app, update.update);
+        }
+    }
+}
+
+#[cfg(test)]
+mod regenerate_reply_tests {
+    use super::*;
+
+    /// The old reply is replaced by a new one for the same history, and its tokens with it
+    #[tokio::test]
+    async fn test_retry_command_replaces_reply() {
+        let mut app = app_with_reply();
+        let (tx, mut rx) = mpsc::channel(256);
+
+        app.input = "/retry".to_string();
+        press_sending_to(&mut app, &tx, KeyCode::Enter, KeyModifiers::NONE).await;
+        assert!(app.waiting);
+        assert_eq!(app.client.messages.len(), 1);
+        assert_eq!(app.client.total_tokens(), 0);
+        finish_reply(&mut app, &mut rx).await;
+
+        assert!(app.input.is_empty());
+        assert_eq!(app.client.messages.len(), 2);
+        assert!(app.client.messages[1].content.contains("Name a colour"));
+        assert_eq!(app.client.messages[1].usage.map(|u| u.input_tokens), Some(app.client.total_input_tokens));
+        // The old answer is kept as an alternative to the new one
+        let replies = app.client.tree.children(app.client.messages[0].id);
+        assert_eq!(replies.len(), 2);
+        assert_eq!(app.client.tree.get(replies[0]).unwrap().content, "Blue");
+        assert_eq!(app.client.messages[1].id, Some(replies[1]));
+    }
+
+    /// With `keep_alternate_replies` off the old answer is discarded
+    #[tokio::test]
+    async fn test_retry_without_keeping_alternates() {
+        let mut app = app_with_reply();
+        app.ui.keep_alternate_replies = false;
+        let (tx, mut rx) = mpsc::channel(256);
+
+        regenerate_reply(&mut app, &tx);
+        finish_reply(&mut app, &mut rx).await;
+
+        let replies = app.client.tree.children(app.client.messages[0].id);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(app.client.messages[1].id, Some(replies[0]));
+    }
+
+    /// A prompt left without a reply after an error is sent again
+    #[tokio::test]
+    async fn test_retry_resends_unanswered_prompt() {
+        let mut app = app_with_reply();
+        app.client.messages.pop();
+        let (tx, mut rx) = mpsc::channel(256);
+
+        press_sending_to(&mut app, &tx, KeyCode::Char('r'), KeyModifiers::CONTROL).await;
+        finish_reply(&mut app, &mut rx).await;
+
+        assert_eq!(app.client.messages.len(), 2);
+        assert_eq!(app.client.tree.children(app.client.messages[0].id).len(), 1);
+    }
+
+    /// Cancelling puts the old reply back, tokens and all, and leaves the prompt
+    /// in the conversation, whether or not alternates are kept
+    #[tokio::test]
+    async fn test_cancel_restores_previous_reply() {
+        for keep in [true, false] {
+            let mut app = app_with_reply();
+            app.ui.keep_alternate_replies = keep;
+            let (tx, _rx) = mpsc::channel(256);
+            let before: Vec<(String, String)> = app.client.messages
+                .iter()
+                .map(|m| (m.role.clone(), m.content.clone()))
+                .collect();
+
+            regenerate_reply(&mut app, &tx);
+            handle_api_update(&mut app, ApiUpdate::Delta("Gre".to_string()));
+            assert!(app.cancel_request());
+
+            let after: Vec<(String, String)> = app.client.messages
+                .iter()
+                .map(|m| (m.role.clone(), m.content.clone()))
+                .collect();
+            assert_eq!(after, before);
+            assert!(app.input.is_empty());
+            assert_eq!(app.client.total_tokens(), 8);
+            assert_eq!(app.client.tree.children(app.client.messages[0].id).len(), 1);
+        }
+    }
+
+    /// Cancelling a resend after an error keeps the prompt where it was
+    #[tokio::test]
+    async fn test_cancel_resend_keeps_prompt() {
+        let mut app = app_with_reply();
+        app.client.messages.pop();
+        let (tx, _rx) = mpsc::channel(256);
+
+        regenerate_reply(&mut app, &tx);
+        assert!(app.cancel_request());
+
+        assert_eq!(app.client.messages.len(), 1);
+        assert_eq!(app.client.messages[0].content, "Name a colour");
+        assert!(app.input.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nothing_to_regenerate() {
+        let mut app = app_with_reply();
+        app.client.messages.clear();
+        let (tx, _rx) = mpsc::channel(256);
+
+        regenerate_reply(&mut app, &tx);
+
+        assert!(!app.waiting);
+        assert_eq!(app.status, "Nothing to regenerate");
+    }
+}
+
+#[cfg(test)]
+mod cycle_tests {
+    use super::*;
+
+    /// Ctrl+N steps through every version, swapping the token totals to match
+    #[tokio::test]
+    async fn test_cycle_through_versions() {
+        let mut app = app_with_reply();
+        let (tx, mut rx) = mpsc::channel(256);
+
+        regenerate_reply(&mut app, &tx);
+        finish_reply(&mut app, &mut rx).await;
+        let second = app.client.messages[1].clone();
+
+        press_sending_to(&mut app, &tx, KeyCode::Char('n'), KeyModifiers::CONTROL).await;
+        assert_eq!(app.client.messages.len(), 2);
+        assert_eq!(app.client.messages[1].content, "Blue");
+        assert_eq!(app.client.total_input_tokens, 7);
+        assert_eq!(app.status, "Showing response 1 of 2");
+
+        press_sending_to(&mut app, &tx, KeyCode::Char('n'), KeyModifiers::CONTROL).await;
+        assert_eq!(app.client.messages[1].content, second.content);
+        assert_eq!(app.status, "Showing response 2 of 2");
+    }
+
+    /// With a single version there is nothing to switch to
+    #[tokio::test]
+    async fn test_cycle_without_alternates() {
+        let mut app = app_with_reply();
+        let (tx, _rx) = mpsc::channel(256);
+
+        press_sending_to(&mut app, &tx, KeyCode::Char('n'), KeyModifiers::CONTROL).await;
+
+        assert_eq!(app.client.messages[1].content, "Blue");
+        assert!(app.status.starts_with("No other responses"));
+    }
+
+    /// After a new message, Ctrl+N switches answers to that message instead
+    #[tokio::test]
+    async fn test_cycle_follows_latest_prompt() {
+        let mut app = app_with_reply();
+        let (tx, mut rx) = mpsc::channel(256);
+
+        regenerate_reply(&mut app, &tx);
+        finish_reply(&mut app, &mut rx).await;
+        app.input = "Another".to_string();
+        press_sending_to(&mut app, &tx, KeyCode::Enter, KeyModifiers::NONE).await;
+        finish_reply(&mut app, &mut rx).await;
+
+        assert_eq!(app.client.messages.len(), 4);
+        press_sending_to(&mut app, &tx, KeyCode::Char('n'), KeyModifiers::CONTROL).await;
+        assert!(app.status.starts_with("No other responses"));
+        assert_eq!(app.client.messages.len(), 4);
+    }
+}
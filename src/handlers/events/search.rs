@@ -0,0 +1,72 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+use crate::app::{AppState, ChatSearch};
+use super::shortcuts::handle_keyboard_shortcuts;
+
+/// Open the search bar, or go back to typing the query if it's already open
+pub fn start_search(app: &mut AppState) {
+    app.message_selection = None;
+    let search = app.search.get_or_insert_with(ChatSearch::default);
+    search.editing = true;
+    search.jump = true;
+    app.auto_scroll = false;
+}
+
+pub fn close_search(app: &mut AppState) {
+    app.search = None;
+    app.status = "Ready".to_string();
+}
+
+/// Move to the next hit, or the previous one, wrapping around at either end
+fn step(search: &mut ChatSearch, forward: bool) {
+    if search.match_count == 0 {
+        return;
+    }
+    search.current = if forward {
+        (search.current + 1) % search.match_count
+    } else {
+        (search.current + search.match_count - 1) % search.match_count
+    };
+    search.jump = true;
+}
+
+/// While typing, keys edit the query and Enter moves on to stepping through
+/// hits. After that, n/N step, `/` edits the query again, and other
+/// shortcuts (scrolling, dialogs) still work.
+pub fn handle_search_key(app: &mut AppState, code: KeyCode, modifiers: KeyModifiers, terminal_size: (u16, u16)) {
+    let Some(search) = app.search.as_mut() else {
+        return;
+    };
+
+    if search.editing {
+        match code {
+            KeyCode::Esc => close_search(app),
+            KeyCode::Enter if search.query.is_empty() => close_search(app),
+            KeyCode::Enter => search.editing = false,
+            KeyCode::Backspace => {
+                search.query.pop();
+                search.current = 0;
+                search.jump = true;
+            }
+            KeyCode::Char(c) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                search.query.push(c);
+                search.current = 0;
+                search.jump = true;
+            }
+            KeyCode::Down => step(search, true),
+            KeyCode::Up => step(search, false),
+            _ => {}
+        }
+        return;
+    }
+
+    match code {
+        KeyCode::Esc => close_search(app),
+        KeyCode::Char('n') | KeyCode::Enter if !modifiers.contains(KeyModifiers::CONTROL) => step(search, true),
+        KeyCode::Char('N') => step(search, false),
+        KeyCode::Char('/') => start_search(app),
+        // Everything else goes to the keymap, including the search shortcut
+        _ => {
+            handle_keyboard_shortcuts(app, code, modifiers, terminal_size);
+        }
+    }
+}
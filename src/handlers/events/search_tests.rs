@@ -0,0 +1,162 @@
+//! Unit tests for searching the conversation
+//! Tests opening the search bar, typing a query, stepping through hits and
+//! the hit count worked out when the chat is drawn
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use ratatui::{backend::TestBackend, Terminal};
+
+use crate::app::AppState;
+use crate::config::get_default_colors;
+use crate::test_support::{message, press};
+use crate::ui::{layout::create_main_layout, render::draw_ui};
+
+/// A conversation long enough to scroll, mentioning "lockfile" three times
+fn app_with_conversation() -> AppState {
+    let mut app = AppState::new(
+        "test_key".to_string(),
+        "test_model".to_string(),
+        1000,
+        0.7,
+        true,
+        get_default_colors(),
+    ).expect("Failed to create AppState");
+    app.client.messages.push(message("user", "Why does the lockfile change?"));
+    for i in 0..30 {
+        app.client.messages.push(message("assistant", &format!("Filler line {}", i)));
+    }
+    app.client.messages.push(message("user", "The lockfile bug again"));
+    app.client.messages.push(message("assistant", "Pin the LOCKFILE version."));
+    app
+}
+
+async fn type_text(app: &mut AppState, text: &str) {
+    for c in text.chars() {
+        press(app, KeyCode::Char(c), KeyModifiers::NONE).await;
+    }
+}
+
+/// Draw the app the way the main loop does
+fn draw(app: &mut AppState) {
+    let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+    terminal
+        .draw(|f| {
+            let layout = create_main_layout(f.size());
+            draw_ui(f, app, &layout);
+        })
+        .unwrap();
+}
+
+#[cfg(test)]
+mod search_bar_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ctrl_f_opens_search_and_typing_edits_query() {
+        let mut app = app_with_conversation();
+        press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL).await;
+        assert!(app.search.as_ref().is_some_and(|search| search.editing));
+        assert!(!app.auto_scroll);
+
+        type_text(&mut app, "lockfilx").await;
+        press(&mut app, KeyCode::Backspace, KeyModifiers::NONE).await;
+        type_text(&mut app, "e").await;
+
+        assert_eq!(app.search.as_ref().unwrap().query, "lockfile");
+        // Nothing was typed into the message input
+        assert!(app.input.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_lone_slash_opens_search() {
+        let mut app = app_with_conversation();
+        type_text(&mut app, "/").await;
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+
+        assert!(app.search.is_some());
+        assert!(app.input.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_slash_in_selection_mode_opens_search() {
+        let mut app = app_with_conversation();
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        press(&mut app, KeyCode::Char('/'), KeyModifiers::NONE).await;
+
+        assert!(app.search.is_some());
+        assert_eq!(app.message_selection, None);
+    }
+
+    #[tokio::test]
+    async fn test_escape_closes_search() {
+        let mut app = app_with_conversation();
+        press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL).await;
+        type_text(&mut app, "lock").await;
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE).await;
+
+        assert!(app.search.is_none());
+        assert!(!app.show_exit_dialog);
+    }
+}
+
+#[cfg(test)]
+mod match_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_draw_counts_matches_and_jumps_to_them() {
+        let mut app = app_with_conversation();
+        press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL).await;
+        type_text(&mut app, "lockfile").await;
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+        draw(&mut app);
+
+        let search = app.search.as_ref().unwrap();
+        assert_eq!(search.match_count, 3);
+        assert!(search.status().starts_with("Match 1 of 3"));
+        // The first hit is at the top of the conversation
+        assert_eq!(app.chat_scroll_offset, 0);
+
+        press(&mut app, KeyCode::Char('n'), KeyModifiers::NONE).await;
+        draw(&mut app);
+        assert!(app.search.as_ref().unwrap().status().starts_with("Match 2 of 3"));
+        let second_offset = app.chat_scroll_offset;
+        assert!(second_offset > 0);
+
+        // N steps back, and wraps around from the first to the last
+        press(&mut app, KeyCode::Char('N'), KeyModifiers::SHIFT).await;
+        press(&mut app, KeyCode::Char('N'), KeyModifiers::SHIFT).await;
+        draw(&mut app);
+        assert!(app.search.as_ref().unwrap().status().starts_with("Match 3 of 3"));
+        assert!(app.chat_scroll_offset >= second_offset);
+    }
+
+    #[tokio::test]
+    async fn test_no_matches() {
+        let mut app = app_with_conversation();
+        press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL).await;
+        type_text(&mut app, "zebra").await;
+        draw(&mut app);
+
+        let search = app.search.as_mut().unwrap();
+        assert_eq!(search.match_count, 0);
+        assert!(search.status().starts_with("No matches for \"zebra\""));
+
+        // Stepping with nothing to step through does nothing
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+        press(&mut app, KeyCode::Char('n'), KeyModifiers::NONE).await;
+        assert_eq!(app.search.as_ref().unwrap().current, 0);
+    }
+
+    #[tokio::test]
+    async fn test_slash_goes_back_to_editing_query() {
+        let mut app = app_with_conversation();
+        press(&mut app, KeyCode::Char('f'), KeyModifiers::CONTROL).await;
+        type_text(&mut app, "lock").await;
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+        assert!(!app.search.as_ref().unwrap().editing);
+
+        press(&mut app, KeyCode::Char('/'), KeyModifiers::NONE).await;
+        type_text(&mut app, "file").await;
+        assert_eq!(app.search.as_ref().unwrap().query, "lockfile");
+    }
+}
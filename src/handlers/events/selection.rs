@@ -0,0 +1,142 @@
+use crossterm::event::KeyCode;
+use crate::app::AppState;
+use super::search::start_search;
+
+/// Enter selection mode with the most recent user message highlighted
+pub fn start_message_selection(app: &mut AppState) {
+    if app.waiting {
+        app.status = "Wait for the reply to finish (Esc to cancel) before editing".to_string();
+        return;
+    }
+
+    match app.client.messages.iter().rposition(|message| message.role == "user") {
+        Some(index) => {
+            app.message_selection = Some(index);
+            app.auto_scroll = false;
+            // Branch ids are needed to show and switch between alternatives
+            app.client.record_branch();
+            app.status = "Select a message: Up/Down to move, Enter to edit, f to fork, Left/Right to switch branch, / to search, Esc to cancel".to_string();
+        }
+        None => {
+            app.status = "No messages to edit".to_string();
+        }
+    }
+}
+
+pub fn handle_message_selection(app: &mut AppState, code: KeyCode) {
+    let Some(selected) = app.message_selection else {
+        return;
+    };
+    let last = app.client.messages.len().saturating_sub(1);
+
+    match code {
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.message_selection = Some(selected.saturating_sub(1));
+        }
+        KeyCode::Down | KeyCode::Char('j') => {
+            app.message_selection = Some((selected + 1).min(last));
+        }
+        KeyCode::Home => {
+            app.message_selection = Some(0);
+        }
+        KeyCode::End => {
+            app.message_selection = Some(last);
+        }
+        KeyCode::Left | KeyCode::Char('h') => {
+            switch_sibling(app, selected, false);
+        }
+        KeyCode::Right | KeyCode::Char('l') => {
+            switch_sibling(app, selected, true);
+        }
+        KeyCode::Char('f') => {
+            fork_after(app, selected);
+        }
+        KeyCode::Char('/') => {
+            start_search(app);
+        }
+        KeyCode::Enter if app.client.messages.get(selected).is_some_and(|m| m.role == "user") => {
+            begin_edit(app, selected);
+        }
+        KeyCode::Enter => {
+            app.status = "Only your own messages can be edited; press f to fork after this one".to_string();
+        }
+        KeyCode::Esc => {
+            app.message_selection = None;
+            app.status = "Ready".to_string();
+        }
+        _ => {}
+    }
+}
+
+/// Show the previous or next alternative to the message at `index`, along
+/// with the most recent branch that continues from it
+fn switch_sibling(app: &mut AppState, index: usize, forward: bool) {
+    let Some(id) = app.client.messages.get(index).and_then(|m| m.id) else {
+        return;
+    };
+    let siblings = app.client.tree.siblings(id);
+    if siblings.len() < 2 {
+        app.status = "No other branches from here".to_string();
+        return;
+    }
+    // The message being edited may not be on the new branch
+    if app.editing_message.is_some() {
+        cancel_edit(app);
+    }
+
+    let position = siblings.iter().position(|&s| s == id).unwrap_or(0);
+    let next = if forward {
+        (position + 1) % siblings.len()
+    } else {
+        (position + siblings.len() - 1) % siblings.len()
+    };
+    app.client.switch_branch(siblings[next]);
+    app.status = format!("Branch {} of {}", next + 1, siblings.len());
+}
+
+/// End the branch on screen at `index`, so the next message sent starts a new
+/// branch from there. What followed is kept and can be switched back to.
+fn fork_after(app: &mut AppState, index: usize) {
+    if app.editing_message.is_some() {
+        cancel_edit(app);
+    }
+    app.client.truncate_messages(index + 1);
+    app.message_selection = None;
+    app.auto_scroll = true;
+    app.status = "Forked - the next message starts a new branch from here".to_string();
+}
+
+/// Load the message at `index` into the input box. Whatever was being typed is
+/// put aside and comes back once the edit is sent or discarded.
+fn begin_edit(app: &mut AppState, index: usize) {
+    let Some(message) = app.client.messages.get(index) else {
+        app.message_selection = None;
+        return;
+    };
+    let content = message.content.clone();
+    let dropped = app.client.messages.len() - index - 1;
+
+    // Re-selecting while already editing keeps the original stash
+    if app.editing_message.is_none() {
+        app.input_before_edit = Some(std::mem::take(&mut app.input));
+    }
+    app.cursor_position = content.chars().count();
+    app.input = content;
+    app.history_index = None;
+    app.input_draft = None;
+    app.message_selection = None;
+    app.editing_message = Some(index);
+    app.status = match dropped {
+        0 => "Editing message - sending replaces it (Esc to discard the edit)".to_string(),
+        1 => "Editing message - sending replaces it and drops the reply after it (Esc to discard the edit)".to_string(),
+        n => format!("Editing message - sending replaces it and drops the {} messages after it (Esc to discard the edit)", n),
+    };
+}
+
+/// Leave edit mode without sending, restoring what was in the input box before
+pub fn cancel_edit(app: &mut AppState) {
+    app.editing_message = None;
+    app.input = app.input_before_edit.take().unwrap_or_default();
+    app.cursor_position = app.input.chars().count();
+    app.status = "Edit discarded".to_string();
+}
@@ -0,0 +1,219 @@
+//! Unit tests for editing an earlier message
+//! Tests selection mode, loading a message for editing and resending it
+
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::app::AppState;
+use crate::config::get_default_colors;
+use crate::test_support::{message, press, reply};
+
+/// Helper function to create a test AppState in simulate mode
+fn create_test_app_state() -> AppState {
+    AppState::new(
+        "test_key".to_string(),
+        "test_model".to_string(),
+        1000,
+        0.7,
+        true,
+        get_default_colors(),
+    ).expect("Failed to create AppState")
+}
+
+/// Two exchanges, with the tokens of each reply recorded on it
+fn app_with_conversation() -> AppState {
+    let mut app = create_test_app_state();
+    app.client.messages = vec![
+        message("user", "first question"),
+        reply("first answer", 10, 20),
+        message("user", "second qeustion"),
+        reply("second answer", 40, 30),
+    ];
+    app.client.total_input_tokens = 50;
+    app.client.total_output_tokens = 50;
+    app
+}
+
+#[cfg(test)]
+mod select_mode_tests {
+    use super::*;
+
+    /// Ctrl+E starts on the latest user message; Up/Down step through every message
+    #[tokio::test]
+    async fn test_selection_moves_between_messages() {
+        let mut app = app_with_conversation();
+
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        assert_eq!(app.message_selection, Some(2));
+
+        press(&mut app, KeyCode::Up, KeyModifiers::NONE).await;
+        assert_eq!(app.message_selection, Some(1));
+        press(&mut app, KeyCode::Up, KeyModifiers::NONE).await;
+        press(&mut app, KeyCode::Up, KeyModifiers::NONE).await;
+        assert_eq!(app.message_selection, Some(0));
+
+        press(&mut app, KeyCode::Down, KeyModifiers::NONE).await;
+        press(&mut app, KeyCode::Down, KeyModifiers::NONE).await;
+        assert_eq!(app.message_selection, Some(2));
+        press(&mut app, KeyCode::End, KeyModifiers::NONE).await;
+        press(&mut app, KeyCode::Down, KeyModifiers::NONE).await;
+        assert_eq!(app.message_selection, Some(3));
+
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE).await;
+        assert_eq!(app.message_selection, None);
+        assert!(!app.show_exit_dialog);
+    }
+
+    /// There is nothing to select in an empty conversation or while waiting
+    #[tokio::test]
+    async fn test_selection_needs_an_idle_conversation() {
+        let mut app = create_test_app_state();
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        assert_eq!(app.message_selection, None);
+        assert_eq!(app.status, "No messages to edit");
+
+        let mut app = app_with_conversation();
+        app.waiting = true;
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        assert_eq!(app.message_selection, None);
+    }
+
+    /// The /edit command opens selection mode too
+    #[tokio::test]
+    async fn test_edit_command() {
+        let mut app = app_with_conversation();
+        app.input = "/edit".to_string();
+        app.cursor_position = 5;
+
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+
+        assert_eq!(app.message_selection, Some(2));
+        assert!(app.input.is_empty());
+        assert_eq!(app.client.messages.len(), 4);
+    }
+}
+
+#[cfg(test)]
+mod edit_tests {
+    use super::*;
+
+    /// Enter loads the selected message, keeping the draft for later
+    #[tokio::test]
+    async fn test_enter_loads_message_for_editing() {
+        let mut app = app_with_conversation();
+        app.input = "half-typed".to_string();
+
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+
+        assert_eq!(app.message_selection, None);
+        assert_eq!(app.editing_message, Some(2));
+        assert_eq!(app.input, "second qeustion");
+        assert_eq!(app.cursor_position, "second qeustion".chars().count());
+        assert_eq!(app.input_before_edit.as_deref(), Some("half-typed"));
+    }
+
+    /// Esc throws the edit away and brings the draft back, leaving the conversation alone
+    #[tokio::test]
+    async fn test_escape_discards_edit() {
+        let mut app = app_with_conversation();
+        app.input = "half-typed".to_string();
+
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE).await;
+
+        assert_eq!(app.editing_message, None);
+        assert_eq!(app.input, "half-typed");
+        assert!(!app.show_exit_dialog);
+        assert_eq!(app.client.messages.len(), 4);
+    }
+
+    /// Resending replaces the original and drops everything after it, along with its tokens
+    #[tokio::test]
+    async fn test_resend_truncates_and_recomputes_tokens() {
+        let mut app = app_with_conversation();
+
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+        app.input = "second question".to_string();
+        app.cursor_position = app.input.chars().count();
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+        app.cancel_request();
+
+        // The cancelled resend comes back to the input box; the old tail is gone
+        assert_eq!(app.editing_message, None);
+        assert_eq!(app.client.messages.len(), 2);
+        assert_eq!(app.client.messages[1].content, "first answer");
+        assert_eq!(app.input, "second question");
+        assert_eq!(app.client.total_input_tokens, 10);
+        assert_eq!(app.client.total_output_tokens, 20);
+    }
+
+    /// Editing the first message starts the conversation over
+    #[test]
+    fn test_truncate_from_start_clears_totals() {
+        let mut app = app_with_conversation();
+
+        app.client.truncate_messages(0);
+
+        assert!(app.client.messages.is_empty());
+        assert_eq!(app.client.total_tokens(), 0);
+    }
+}
+
+#[cfg(test)]
+mod branch_tests {
+    use super::*;
+
+    /// f ends the branch at the selected message; what followed stays in the tree
+    #[tokio::test]
+    async fn test_fork_after_reply() {
+        let mut app = app_with_conversation();
+
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        press(&mut app, KeyCode::Up, KeyModifiers::NONE).await;
+        press(&mut app, KeyCode::Char('f'), KeyModifiers::NONE).await;
+
+        assert_eq!(app.message_selection, None);
+        assert_eq!(app.client.messages.len(), 2);
+        assert_eq!(app.client.tree.nodes().len(), 4);
+        assert_eq!(app.client.total_input_tokens, 10);
+    }
+
+    /// Replies can be forked from but not edited
+    #[tokio::test]
+    async fn test_enter_on_reply_does_not_edit() {
+        let mut app = app_with_conversation();
+
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        press(&mut app, KeyCode::Down, KeyModifiers::NONE).await;
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+
+        assert_eq!(app.editing_message, None);
+        assert_eq!(app.message_selection, Some(3));
+        assert!(app.input.is_empty());
+    }
+
+    /// Left/Right on a message with alternatives swaps in the other branch
+    #[tokio::test]
+    async fn test_switch_branch_at_selected_message() {
+        let mut app = app_with_conversation();
+        app.client.record_branch();
+        app.client.truncate_messages(2);
+        app.client.messages.push(message("user", "second question"));
+        app.client.record_branch();
+
+        press(&mut app, KeyCode::Char('e'), KeyModifiers::CONTROL).await;
+        press(&mut app, KeyCode::Left, KeyModifiers::NONE).await;
+
+        assert_eq!(app.status, "Branch 1 of 2");
+        assert_eq!(app.client.messages.len(), 4);
+        assert_eq!(app.client.messages[2].content, "second qeustion");
+        assert_eq!(app.client.total_output_tokens, 50);
+
+        press(&mut app, KeyCode::Right, KeyModifiers::NONE).await;
+        assert_eq!(app.client.messages.len(), 3);
+        assert_eq!(app.client.messages[2].content, "second question");
+        assert_eq!(app.message_selection, Some(2));
+    }
+}
@@ -0,0 +1,112 @@
+//! Unit tests for keyboard shortcuts
+//! Tests that key presses in the main interface run the action the keymap
+//! binds to them, including remapped keys and the key binding help
+
+use std::collections::BTreeMap;
+use crossterm::event::{KeyCode, KeyModifiers};
+
+use crate::app::AppState;
+use crate::config::get_default_colors;
+use crate::keymap::{Action, KeyChord, Keymap};
+use crate::test_support::press;
+
+fn create_test_app_state() -> AppState {
+    AppState::new(
+        "test_key".to_string(),
+        "test_model".to_string(),
+        1000,
+        0.7,
+        true,
+        get_default_colors(),
+    ).expect("Failed to create AppState")
+}
+
+#[cfg(test)]
+mod default_binding_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dialog_shortcuts() {
+        let mut app = create_test_app_state();
+        press(&mut app, KeyCode::F(3), KeyModifiers::NONE).await;
+        assert!(app.show_color_dialog);
+
+        let mut app = create_test_app_state();
+        press(&mut app, KeyCode::Char('P'), KeyModifiers::CONTROL | KeyModifiers::SHIFT).await;
+        assert!(app.show_profile_dialog);
+
+        let mut app = create_test_app_state();
+        press(&mut app, KeyCode::Char('q'), KeyModifiers::CONTROL).await;
+        assert!(app.show_exit_dialog);
+    }
+
+    /// Modified arrows scroll the chat rather than moving through history
+    #[tokio::test]
+    async fn test_modified_arrows_scroll() {
+        let mut app = create_test_app_state();
+        app.chat_scroll_offset = 3;
+        app.input = "draft".to_string();
+
+        press(&mut app, KeyCode::Up, KeyModifiers::CONTROL).await;
+
+        assert_eq!(app.chat_scroll_offset, 2);
+        assert!(!app.auto_scroll);
+        assert_eq!(app.input, "draft");
+    }
+
+    /// Unbound chords still type their character
+    #[tokio::test]
+    async fn test_unbound_chord_types() {
+        let mut app = create_test_app_state();
+        press(&mut app, KeyCode::Char('x'), KeyModifiers::ALT).await;
+        assert_eq!(app.input, "x");
+    }
+}
+
+#[cfg(test)]
+mod remap_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_remapped_keys() {
+        let mut app = create_test_app_state();
+        app.keymap = Keymap::with_overrides(&BTreeMap::from([
+            (Action::Colors, vec![KeyChord::parse("f9").unwrap()]),
+            (Action::ScrollUp, vec![KeyChord::parse("alt+k").unwrap()]),
+        ]));
+        app.chat_scroll_offset = 3;
+
+        press(&mut app, KeyCode::F(3), KeyModifiers::NONE).await;
+        assert!(!app.show_color_dialog);
+        press(&mut app, KeyCode::F(9), KeyModifiers::NONE).await;
+        assert!(app.show_color_dialog);
+        app.show_color_dialog = false;
+
+        press(&mut app, KeyCode::Char('k'), KeyModifiers::ALT).await;
+        assert_eq!(app.chat_scroll_offset, 2);
+        press(&mut app, KeyCode::Char('k'), KeyModifiers::CONTROL).await;
+        assert_eq!(app.chat_scroll_offset, 2);
+        assert_eq!(app.input, "k");
+    }
+
+    /// The help overlay opens from its binding or /keys and closes on any other key
+    #[tokio::test]
+    async fn test_help_overlay() {
+        let mut app = create_test_app_state();
+        press(&mut app, KeyCode::F(7), KeyModifiers::NONE).await;
+        assert!(app.show_help_dialog);
+
+        press(&mut app, KeyCode::Down, KeyModifiers::NONE).await;
+        assert!(app.show_help_dialog);
+        assert_eq!(app.help_scroll_offset, 1);
+
+        press(&mut app, KeyCode::Esc, KeyModifiers::NONE).await;
+        assert!(!app.show_help_dialog);
+        assert!(!app.show_exit_dialog);
+
+        app.input = "/keys".to_string();
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+        assert!(app.show_help_dialog);
+        assert_eq!(app.help_scroll_offset, 0);
+    }
+}
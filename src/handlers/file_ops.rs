@@ -4,6 +4,12 @@ use std::fs;
 use std::path::PathBuf;
 use crate::client::ConversationClient;
 use crate::api::Message;
+use crate::import::{detect_format, import_conversation, ImportFormat};
+use crate::tree::ConversationTree;
+
+/// Version written to new files. 1.0 files hold a single flat list of
+/// messages; 2.0 files hold the whole conversation tree.
+pub const SAVE_FORMAT_VERSION: &str = "2.0";
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SavedConversation {
@@ -12,24 +18,69 @@ pub struct SavedConversation {
     pub model: String,
     pub total_input_tokens: u32,
     pub total_output_tokens: u32,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Every message on every branch, each pointing at its parent
     pub messages: Vec<Message>,
+    /// Last message of the branch that was on screen
+    #[serde(default)]
+    pub current: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Session library entry the conversation is kept in. Only the autosaved
+    /// session file records it, so a restored session keeps updating its entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library_id: Option<String>,
 }
 
 impl SavedConversation {
     pub fn new(client: &ConversationClient) -> Self {
+        // The branch on screen may have changed since it was last recorded
+        let mut tree = client.tree.clone();
+        let mut branch = client.messages.clone();
+        tree.record(&mut branch);
+
         Self {
-            version: "1.0".to_string(),
+            version: SAVE_FORMAT_VERSION.to_string(),
             timestamp: chrono::Utc::now().to_rfc3339(),
             model: client.model.clone(),
             total_input_tokens: client.total_input_tokens,
             total_output_tokens: client.total_output_tokens,
-            messages: client.messages.clone(),
+            system_prompt: client.system_prompt.clone(),
+            messages: tree.nodes().to_vec(),
+            current: branch.last().and_then(|message| message.id),
+            title: client.title.clone(),
+            library_id: None,
+        }
+    }
+
+    /// The branch that was on screen when the conversation was saved
+    pub fn branch(&self) -> Vec<Message> {
+        let tree = ConversationTree::from_messages(self.messages.clone());
+        match self.current {
+            Some(current) => tree.branch_through(current),
+            None => tree.latest_branch(),
         }
     }
 
     pub fn validate(&self) -> bool {
         // Validate the conversation file format - empty messages are OK
-        self.version == "1.0"
+        self.version == SAVE_FORMAT_VERSION
+    }
+
+    /// Bring a file written by an older version up to the current format.
+    /// A 1.0 conversation becomes a tree with a single branch.
+    pub fn migrate(&mut self) {
+        if self.version == "1.0" {
+            let mut parent = None;
+            for (id, message) in (0u64..).zip(self.messages.iter_mut()) {
+                message.id = Some(id);
+                message.parent = parent;
+                parent = Some(id);
+            }
+            self.current = parent;
+            self.version = SAVE_FORMAT_VERSION.to_string();
+        }
     }
 }
 
@@ -90,11 +141,21 @@ pub fn save_conversation(client: &ConversationClient, filepath: &PathBuf) -> Res
     Ok(())
 }
 
+/// Load a save file, or import a conversation from one of the formats
+/// `import` understands
 pub fn load_conversation(filepath: &PathBuf) -> Result<SavedConversation> {
     let json = fs::read_to_string(filepath)?;
-    let conversation: SavedConversation = serde_json::from_str(&json)?;
+    let format = detect_format(filepath, &json);
+    if format != ImportFormat::Saved {
+        return import_conversation(format, &json);
+    }
+    let mut conversation: SavedConversation = serde_json::from_str(&json)?;
+    conversation.migrate();
     if !conversation.validate() {
-        return Err(anyhow::anyhow!("Invalid conversation file format"));
+        return Err(anyhow::anyhow!(
+            "Unsupported conversation file version {}",
+            conversation.version
+        ));
     }
     Ok(conversation)
 }
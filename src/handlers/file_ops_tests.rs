@@ -0,0 +1,126 @@
+//! Unit tests for conversation save and load
+//! Tests that conversation state survives a round trip through the JSON file
+
+use tempfile::TempDir;
+
+use crate::api::Message;
+use crate::client::ConversationClient;
+use crate::handlers::file_ops::{save_conversation, load_conversation};
+
+/// Helper to build a client with a short conversation
+fn create_test_client() -> ConversationClient {
+    let mut client = ConversationClient::new("key".to_string(), "test_model".to_string(), 100, 0.7);
+    client.messages.push(Message { role: "user".to_string(), content: "Hi".to_string(), ..Default::default() });
+    client.messages.push(Message { role: "assistant".to_string(), content: "Hello!".to_string(), ..Default::default() });
+    client.total_input_tokens = 3;
+    client.total_output_tokens = 5;
+    client
+}
+
+#[cfg(test)]
+mod round_trip_tests {
+    use super::*;
+
+    #[test]
+    fn test_system_prompt_round_trip() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("conversation.json");
+        let mut client = create_test_client();
+        client.system_prompt = Some("Answer in French.".to_string());
+
+        save_conversation(&client, &path).expect("Save should succeed");
+        let loaded = load_conversation(&path).expect("Load should succeed");
+
+        assert_eq!(loaded.system_prompt.as_deref(), Some("Answer in French."));
+        assert_eq!(loaded.messages.len(), 2);
+        assert_eq!(loaded.total_input_tokens, 3);
+        assert_eq!(loaded.total_output_tokens, 5);
+    }
+
+    #[test]
+    fn test_file_without_system_prompt_still_loads() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("old.json");
+        std::fs::write(&path, r#"{
+            "version": "1.0",
+            "timestamp": "2024-12-01T00:00:00Z",
+            "model": "test_model",
+            "total_input_tokens": 1,
+            "total_output_tokens": 2,
+            "messages": [{"role": "user", "content": "Hi"}]
+        }"#).unwrap();
+
+        let loaded = load_conversation(&path).expect("Older files should still load");
+        assert!(loaded.system_prompt.is_none());
+        assert_eq!(loaded.messages.len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod format_version_tests {
+    use super::*;
+    use crate::handlers::file_ops::SAVE_FORMAT_VERSION;
+
+    /// Branches that aren't on screen are saved too, along with which one is
+    #[test]
+    fn test_branches_round_trip() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("branches.json");
+        let mut client = create_test_client();
+        client.truncate_messages(1);
+        client.messages.push(Message { role: "assistant".to_string(), content: "Bonjour!".to_string(), ..Default::default() });
+
+        save_conversation(&client, &path).expect("Save should succeed");
+        let loaded = load_conversation(&path).expect("Load should succeed");
+
+        assert_eq!(loaded.version, SAVE_FORMAT_VERSION);
+        assert_eq!(loaded.messages.len(), 3);
+        let current = loaded.messages.iter().find(|m| m.id == loaded.current).unwrap();
+        assert_eq!(current.content, "Bonjour!");
+        assert_eq!(current.parent, loaded.messages[0].id);
+    }
+
+    /// A flat 1.0 conversation becomes a single branch
+    #[test]
+    fn test_flat_format_is_migrated() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("flat.json");
+        std::fs::write(&path, r#"{
+            "version": "1.0",
+            "timestamp": "2024-12-01T00:00:00Z",
+            "model": "test_model",
+            "total_input_tokens": 1,
+            "total_output_tokens": 2,
+            "messages": [
+                {"role": "user", "content": "Hi"},
+                {"role": "assistant", "content": "Hello!"},
+                {"role": "user", "content": "Bye"}
+            ]
+        }"#).unwrap();
+
+        let loaded = load_conversation(&path).expect("1.0 files should load");
+
+        assert_eq!(loaded.version, SAVE_FORMAT_VERSION);
+        let ids: Vec<_> = loaded.messages.iter().map(|m| m.id).collect();
+        let parents: Vec<_> = loaded.messages.iter().map(|m| m.parent).collect();
+        assert_eq!(parents, vec![None, ids[0], ids[1]]);
+        assert_eq!(loaded.current, ids[2]);
+    }
+
+    #[test]
+    fn test_unknown_version_is_rejected() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("future.json");
+        std::fs::write(&path, r#"{
+            "version": "9.0",
+            "timestamp": "2024-12-01T00:00:00Z",
+            "model": "test_model",
+            "total_input_tokens": 0,
+            "total_output_tokens": 0,
+            "messages": []
+        }"#).unwrap();
+
+        let error = load_conversation(&path).err().expect("Unknown versions should fail");
+        assert!(error.to_string().contains("9.0"));
+    }
+}
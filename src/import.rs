@@ -0,0 +1,269 @@
+// src/import.rs
+//! Conversations started somewhere else, so they can be continued here:
+//! Messages API request bodies, JSONL files of role/content records, and
+//! Markdown transcripts written by `/export md`. `load_conversation` hands
+//! anything that isn't one of our own save files to `import_conversation`.
+use std::path::Path;
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use crate::api::{Message, Usage};
+use crate::handlers::file_ops::{SavedConversation, SAVE_FORMAT_VERSION};
+use crate::tree::ConversationTree;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// One of our own save files
+    Saved,
+    /// The body of a Messages API request: `messages`, and optionally `system` and `model`
+    MessagesApi,
+    /// One `{"role": ..., "content": ...}` object per line
+    Jsonl,
+    /// A transcript written by the Markdown exporter
+    Markdown,
+}
+
+/// Work out what kind of file `text` is, going by its extension first.
+/// Anything unrecognised is treated as a save file, so the error says why
+/// it didn't load as one.
+pub fn detect_format(path: &Path, text: &str) -> ImportFormat {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    if matches!(extension, "md" | "markdown") {
+        return ImportFormat::Markdown;
+    }
+
+    if let Ok(Value::Object(object)) = serde_json::from_str::<Value>(text) {
+        return if object.contains_key("version") {
+            ImportFormat::Saved
+        } else if object.contains_key("messages") {
+            ImportFormat::MessagesApi
+        } else if object.contains_key("role") {
+            ImportFormat::Jsonl
+        } else {
+            ImportFormat::Saved
+        };
+    }
+
+    let first_line = text.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+    if extension == "jsonl" || serde_json::from_str::<Value>(first_line).is_ok_and(|value| value.get("role").is_some()) {
+        ImportFormat::Jsonl
+    } else if first_line.starts_with("# ") || text.contains("\n## User\n") {
+        ImportFormat::Markdown
+    } else {
+        ImportFormat::Saved
+    }
+}
+
+/// Turn a file in one of the other formats into a conversation
+pub fn import_conversation(format: ImportFormat, text: &str) -> Result<SavedConversation> {
+    let imported = match format {
+        ImportFormat::MessagesApi => import_messages_api(text)?,
+        ImportFormat::Jsonl => import_jsonl(text)?,
+        ImportFormat::Markdown => import_markdown(text)?,
+        ImportFormat::Saved => bail!("Not an imported format"),
+    };
+    if imported.messages.is_empty() {
+        bail!("No messages found");
+    }
+    Ok(imported.into_saved())
+}
+
+/// What an importer found, before it is turned into a save file
+#[derive(Default)]
+struct Imported {
+    title: Option<String>,
+    model: Option<String>,
+    system_prompt: Option<String>,
+    messages: Vec<Message>,
+    /// Totals given by the file; otherwise they're added up from the messages
+    tokens: Option<(u32, u32)>,
+}
+
+impl Imported {
+    fn push(&mut self, role: &str, content: String) -> Result<()> {
+        match role {
+            "user" | "assistant" => {
+                self.messages.push(Message { role: role.to_string(), content, ..Default::default() });
+                Ok(())
+            }
+            "system" => {
+                self.system_prompt = Some(content);
+                Ok(())
+            }
+            other => bail!("Unsupported message role \"{}\"", other),
+        }
+    }
+
+    fn into_saved(self) -> SavedConversation {
+        let (input, output) = self.tokens.unwrap_or_else(|| {
+            self.messages
+                .iter()
+                .filter_map(|message| message.usage.as_ref())
+                .fold((0, 0), |(input, output), usage| (input + usage.input_tokens, output + usage.output_tokens))
+        });
+
+        let mut tree = ConversationTree::default();
+        let mut branch = self.messages;
+        tree.record(&mut branch);
+
+        SavedConversation {
+            version: SAVE_FORMAT_VERSION.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            model: self.model.unwrap_or_default(),
+            total_input_tokens: input,
+            total_output_tokens: output,
+            system_prompt: self.system_prompt.filter(|prompt| !prompt.trim().is_empty()),
+            messages: tree.nodes().to_vec(),
+            current: branch.last().and_then(|message| message.id),
+            title: self.title,
+            library_id: None,
+        }
+    }
+}
+
+/// Text of a `content` or `system` value: a string, or an array of content
+/// blocks whose text blocks are joined. Images and tool calls are left out.
+fn content_text(value: &Value) -> Result<String> {
+    match value {
+        Value::String(text) => Ok(text.clone()),
+        Value::Array(blocks) => Ok(blocks
+            .iter()
+            .filter(|block| block.get("type").and_then(Value::as_str).is_none_or(|kind| kind == "text"))
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n\n")),
+        _ => bail!("Message content must be a string or an array of content blocks"),
+    }
+}
+
+/// Role and text of a `{"role": ..., "content": ...}` record
+fn record(value: &Value) -> Result<(&str, String)> {
+    let role = value
+        .get("role")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("Message without a role"))?;
+    let content = content_text(value.get("content").unwrap_or(&Value::Null))?;
+    Ok((role, content))
+}
+
+fn import_messages_api(text: &str) -> Result<Imported> {
+    let request: Value = serde_json::from_str(text)?;
+    let mut imported = Imported {
+        model: request.get("model").and_then(Value::as_str).map(str::to_string),
+        ..Default::default()
+    };
+    if let Some(system) = request.get("system") {
+        imported.system_prompt = Some(content_text(system)?);
+    }
+    let messages = request
+        .get("messages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("\"messages\" must be an array"))?;
+    for message in messages {
+        let (role, content) = record(message)?;
+        imported.push(role, content)?;
+    }
+    Ok(imported)
+}
+
+fn import_jsonl(text: &str) -> Result<Imported> {
+    let mut imported = Imported::default();
+    for (number, line) in (1..).zip(text.lines()) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(line).map_err(|e| anyhow!("Line {}: {}", number, e))?;
+        let (role, content) = record(&value).map_err(|e| anyhow!("Line {}: {}", number, e))?;
+        imported.push(role, content).map_err(|e| anyhow!("Line {}: {}", number, e))?;
+    }
+    Ok(imported)
+}
+
+/// Section of a Markdown transcript a `## ` heading starts
+fn section_role(heading: &str) -> Option<&'static str> {
+    match heading {
+        "## User" => Some("user"),
+        "## Assistant" => Some("assistant"),
+        "## System prompt" => Some("system"),
+        _ => None,
+    }
+}
+
+/// "12 input tokens, 34 output tokens" as written under a reply
+fn parse_usage_note(line: &str) -> Option<Usage> {
+    let note = line.strip_prefix('*')?.strip_suffix('*')?;
+    let (input, output) = note.split_once(", ")?;
+    Some(Usage {
+        input_tokens: input.strip_suffix(" input tokens")?.parse().ok()?,
+        output_tokens: output.strip_suffix(" output tokens")?.parse().ok()?,
+    })
+}
+
+/// "12 input, 34 output, 46 total" from the metadata list
+fn parse_token_totals(value: &str) -> Option<(u32, u32)> {
+    let mut parts = value.split(", ");
+    let input = parts.next()?.strip_suffix(" input")?.parse().ok()?;
+    let output = parts.next()?.strip_suffix(" output")?.parse().ok()?;
+    Some((input, output))
+}
+
+fn import_markdown(text: &str) -> Result<Imported> {
+    let mut imported = Imported::default();
+    // Role of the section being read and its lines so far
+    let mut section: Option<(&str, Vec<&str>)> = None;
+    let mut in_fence = false;
+
+    for line in text.lines() {
+        let fence = line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~");
+        let role = if in_fence { None } else { section_role(line.trim_end()) };
+        if let Some(role) = role {
+            if let Some((role, lines)) = section.take() {
+                finish_section(&mut imported, role, &lines)?;
+            }
+            section = Some((role, Vec::new()));
+        } else if let Some((_, lines)) = &mut section {
+            lines.push(line);
+        } else if let Some(title) = line.strip_prefix("# ") {
+            imported.title = Some(title.trim().to_string());
+        } else if let Some(model) = line.strip_prefix("- **Model:** ") {
+            imported.model = Some(model.trim().to_string());
+        } else if let Some(tokens) = line.strip_prefix("- **Tokens:** ") {
+            imported.tokens = parse_token_totals(tokens.trim());
+        }
+        if fence {
+            in_fence = !in_fence;
+        }
+    }
+    if let Some((role, lines)) = section {
+        finish_section(&mut imported, role, &lines)?;
+    }
+    if imported.messages.is_empty() {
+        bail!("No \"## User\" or \"## Assistant\" sections found");
+    }
+    Ok(imported)
+}
+
+/// Add a transcript section, taking the token note off the end of a reply
+fn finish_section(imported: &mut Imported, role: &str, lines: &[&str]) -> Result<()> {
+    let mut lines = lines.to_vec();
+    let trim_end = |lines: &mut Vec<&str>| {
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+    };
+    trim_end(&mut lines);
+    let usage = lines.last().and_then(|line| parse_usage_note(line.trim()));
+    if usage.is_some() {
+        lines.pop();
+        trim_end(&mut lines);
+    }
+    let start = lines.iter().position(|line| !line.trim().is_empty()).unwrap_or(lines.len());
+    let content = lines[start..].join("\n");
+
+    imported.push(role, content)?;
+    if let (Some(usage), Some(message)) = (usage, imported.messages.last_mut()) {
+        if role != "system" {
+            message.usage = Some(usage);
+        }
+    }
+    Ok(())
+}
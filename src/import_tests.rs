@@ -0,0 +1,185 @@
+//! Unit tests for importing conversations
+//! Tests detecting file formats, the Messages API, JSONL and Markdown
+//! importers, and loading imported files like saved ones
+
+use std::path::Path;
+use tempfile::TempDir;
+
+use crate::api::{Message, Usage};
+use crate::client::ConversationClient;
+use crate::export::{export_conversation, ExportFormat};
+use crate::handlers::file_ops::{load_conversation, SavedConversation};
+use crate::import::*;
+
+/// Role and content of each message on the imported branch
+fn transcript(conversation: &SavedConversation) -> Vec<(String, String)> {
+    conversation
+        .branch()
+        .into_iter()
+        .map(|message| (message.role, message.content))
+        .collect()
+}
+
+fn pair(role: &str, content: &str) -> (String, String) {
+    (role.to_string(), content.to_string())
+}
+
+#[cfg(test)]
+mod detect_tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_formats() {
+        let json = Path::new("chat.json");
+        assert_eq!(detect_format(json, r#"{"version": "2.0", "messages": []}"#), ImportFormat::Saved);
+        assert_eq!(detect_format(json, r#"{"model": "m", "messages": []}"#), ImportFormat::MessagesApi);
+        assert_eq!(detect_format(json, "{\"role\": \"user\", \"content\": \"a\"}\n{\"role\": \"assistant\", \"content\": \"b\"}"), ImportFormat::Jsonl);
+        assert_eq!(detect_format(Path::new("chat.jsonl"), ""), ImportFormat::Jsonl);
+        assert_eq!(detect_format(Path::new("chat.md"), "{}"), ImportFormat::Markdown);
+        assert_eq!(detect_format(Path::new("chat"), "# Title\n\n## User\n\nHi\n"), ImportFormat::Markdown);
+    }
+
+    /// Broken files are left to the save file loader, which explains what's wrong
+    #[test]
+    fn test_unrecognised_files_load_as_saves() {
+        let json = Path::new("chat.json");
+        assert_eq!(detect_format(json, "not json at all"), ImportFormat::Saved);
+        assert_eq!(detect_format(json, r#"{"unrelated": true}"#), ImportFormat::Saved);
+    }
+}
+
+#[cfg(test)]
+mod messages_api_tests {
+    use super::*;
+
+    #[test]
+    fn test_request_with_string_content() {
+        let request = r#"{
+            "model": "claude-3-5-haiku-20241022",
+            "max_tokens": 1024,
+            "system": "Be terse.",
+            "messages": [
+                {"role": "user", "content": "Hi"},
+                {"role": "assistant", "content": "Hello"}
+            ]
+        }"#;
+        let conversation = import_conversation(ImportFormat::MessagesApi, request).unwrap();
+
+        assert_eq!(conversation.model, "claude-3-5-haiku-20241022");
+        assert_eq!(conversation.system_prompt.as_deref(), Some("Be terse."));
+        assert_eq!(transcript(&conversation), vec![pair("user", "Hi"), pair("assistant", "Hello")]);
+        assert!(conversation.validate());
+    }
+
+    /// Text blocks are joined; images and tool calls are dropped
+    #[test]
+    fn test_request_with_content_blocks() {
+        let request = r#"{
+            "system": [{"type": "text", "text": "Rule one"}, {"type": "text", "text": "Rule two"}],
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "Look at this"},
+                {"type": "image", "source": {"type": "base64", "data": ""}},
+                {"type": "text", "text": "What is it?"}
+            ]}]
+        }"#;
+        let conversation = import_conversation(ImportFormat::MessagesApi, request).unwrap();
+
+        assert_eq!(conversation.system_prompt.as_deref(), Some("Rule one\n\nRule two"));
+        assert_eq!(transcript(&conversation), vec![pair("user", "Look at this\n\nWhat is it?")]);
+    }
+
+    #[test]
+    fn test_request_problems_are_reported() {
+        let error = import_conversation(ImportFormat::MessagesApi, r#"{"messages": [{"role": "tool", "content": "x"}]}"#);
+        assert!(error.err().expect("Import should fail").to_string().contains("\"tool\""));
+
+        let error = import_conversation(ImportFormat::MessagesApi, r#"{"messages": []}"#);
+        assert_eq!(error.err().expect("Import should fail").to_string(), "No messages found");
+    }
+}
+
+#[cfg(test)]
+mod jsonl_tests {
+    use super::*;
+
+    #[test]
+    fn test_records_and_system_line() {
+        let text = "{\"role\": \"system\", \"content\": \"Be brief.\"}\n\n\
+            {\"role\": \"user\", \"content\": \"Hi\"}\n\
+            {\"role\": \"assistant\", \"content\": [{\"type\": \"text\", \"text\": \"Hello\"}]}\n";
+        let conversation = import_conversation(ImportFormat::Jsonl, text).unwrap();
+
+        assert_eq!(conversation.system_prompt.as_deref(), Some("Be brief."));
+        assert_eq!(transcript(&conversation), vec![pair("user", "Hi"), pair("assistant", "Hello")]);
+    }
+
+    #[test]
+    fn test_bad_line_is_named() {
+        let text = "{\"role\": \"user\", \"content\": \"Hi\"}\n{\"content\": \"no role\"}\n";
+        let error = import_conversation(ImportFormat::Jsonl, text).err().expect("Import should fail");
+        assert_eq!(error.to_string(), "Line 2: Message without a role");
+    }
+}
+
+#[cfg(test)]
+mod markdown_tests {
+    use super::*;
+
+    /// What the Markdown exporter writes comes back as the same conversation
+    #[test]
+    fn test_exported_transcript_round_trip() {
+        let reply = "Like this:\n\n```rust\n## User\nfn main() {}\n```\n\n## Notes\n\nDone.";
+        let mut client = ConversationClient::new("key".to_string(), "test-model".to_string(), 100, 0.7);
+        client.title = Some("Reading files".to_string());
+        client.system_prompt = Some("Be terse.".to_string());
+        client.messages.push(Message { role: "user".to_string(), content: "How?".to_string(), ..Default::default() });
+        client.messages.push(Message {
+            role: "assistant".to_string(),
+            content: reply.to_string(),
+            usage: Some(Usage { input_tokens: 12, output_tokens: 34 }),
+            ..Default::default()
+        });
+        client.total_input_tokens = 12;
+        client.total_output_tokens = 34;
+        let markdown = export_conversation(&SavedConversation::new(&client), ExportFormat::Markdown);
+
+        let conversation = import_conversation(ImportFormat::Markdown, &markdown).unwrap();
+        assert_eq!(conversation.title.as_deref(), Some("Reading files"));
+        assert_eq!(conversation.model, "test-model");
+        assert_eq!(conversation.system_prompt.as_deref(), Some("Be terse."));
+        assert_eq!((conversation.total_input_tokens, conversation.total_output_tokens), (12, 34));
+        assert_eq!(transcript(&conversation), vec![pair("user", "How?"), pair("assistant", reply)]);
+
+        let usage = conversation.branch()[1].usage.expect("Usage should be kept");
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 34));
+    }
+
+    #[test]
+    fn test_transcript_without_sections_fails() {
+        let error = import_conversation(ImportFormat::Markdown, "# Notes\n\nJust some text\n").err().expect("Import should fail");
+        assert!(error.to_string().contains("## User"));
+    }
+}
+
+#[cfg(test)]
+mod load_tests {
+    use super::*;
+
+    /// The load dialog and `/load` take imported files like saved ones
+    #[test]
+    fn test_load_conversation_imports() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("request.json");
+        std::fs::write(&path, r#"{"messages": [{"role": "user", "content": "Hi"}]}"#).unwrap();
+        let conversation = load_conversation(&path).expect("API requests should load");
+        assert_eq!(transcript(&conversation), vec![pair("user", "Hi")]);
+
+        let path = temp_dir.path().join("log.jsonl");
+        std::fs::write(&path, "{\"role\": \"user\", \"content\": \"Hi\"}\n").unwrap();
+        assert_eq!(load_conversation(&path).unwrap().messages.len(), 1);
+
+        let path = temp_dir.path().join("broken.json");
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(load_conversation(&path).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+// src/keymap.rs
+//! Key bindings for the shortcuts that work anywhere in the main interface.
+//! Each key chord maps to a named action; the `[keys]` section of the
+//! settings replaces an action's chords, for terminals that swallow some of
+//! the defaults. Keys inside dialogs and the search bar are not remappable.
+use std::collections::BTreeMap;
+use std::fmt;
+use crossterm::event::{KeyCode, KeyModifiers};
+use serde::Deserialize;
+
+/// Something a shortcut can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    ScrollUp,
+    ScrollDown,
+    HalfPageUp,
+    HalfPageDown,
+    Save,
+    Load,
+    Library,
+    EditMessage,
+    Search,
+    Find,
+    Regenerate,
+    CycleReply,
+    Colors,
+    Profiles,
+    Settings,
+    Models,
+    Help,
+    Quit,
+}
+
+impl Action {
+    /// Every action, in the order the help overlay lists them
+    pub const ALL: [Action; 18] = [
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::HalfPageUp,
+        Action::HalfPageDown,
+        Action::Save,
+        Action::Load,
+        Action::Library,
+        Action::EditMessage,
+        Action::Search,
+        Action::Find,
+        Action::Regenerate,
+        Action::CycleReply,
+        Action::Colors,
+        Action::Profiles,
+        Action::Settings,
+        Action::Models,
+        Action::Help,
+        Action::Quit,
+    ];
+
+    /// The name used in the `[keys]` settings
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::ScrollUp => "scroll_up",
+            Action::ScrollDown => "scroll_down",
+            Action::HalfPageUp => "half_page_up",
+            Action::HalfPageDown => "half_page_down",
+            Action::Save => "save",
+            Action::Load => "load",
+            Action::Library => "library",
+            Action::EditMessage => "edit_message",
+            Action::Search => "search",
+            Action::Find => "find",
+            Action::Regenerate => "regenerate",
+            Action::CycleReply => "cycle_reply",
+            Action::Colors => "colors",
+            Action::Profiles => "profiles",
+            Action::Settings => "settings",
+            Action::Models => "models",
+            Action::Help => "help",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::ScrollUp => "Scroll the conversation up",
+            Action::ScrollDown => "Scroll the conversation down",
+            Action::HalfPageUp => "Scroll up five lines",
+            Action::HalfPageDown => "Scroll down five lines",
+            Action::Save => "Save the conversation",
+            Action::Load => "Load a conversation",
+            Action::Library => "Open the session library",
+            Action::EditMessage => "Select a message to edit",
+            Action::Search => "Search the conversation",
+            Action::Find => "Search saved conversations",
+            Action::Regenerate => "Regenerate the last reply",
+            Action::CycleReply => "Switch between regenerated replies",
+            Action::Colors => "Color configuration",
+            Action::Profiles => "Color profiles",
+            Action::Settings => "Settings",
+            Action::Models => "Pick a model preset",
+            Action::Help => "Show the key bindings",
+            Action::Quit => "Exit",
+        }
+    }
+}
+
+/// A key with its modifiers, e.g. `ctrl+shift+c` or `f3`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        // Terminals disagree on whether Shift+C arrives as 'C' or as 'c' with
+        // SHIFT, and whether shifted symbols like '?' carry SHIFT at all
+        match code {
+            KeyCode::Char(c) if c.is_uppercase() => Self {
+                code: KeyCode::Char(c.to_lowercase().next().unwrap_or(c)),
+                modifiers: modifiers | KeyModifiers::SHIFT,
+            },
+            KeyCode::Char(c) if !c.is_alphabetic() => Self {
+                code,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => Self { code, modifiers },
+        }
+    }
+
+    /// Read a chord such as `ctrl+k`, `alt+shift+p`, `f1` or `ctrl+[`.
+    /// Letters are case-insensitive; Shift must be given as `shift+`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let (prefix, key) = match s.strip_suffix("++") {
+            Some(prefix) => (Some(prefix), "+"),
+            None => match s.rsplit_once('+') {
+                Some((prefix, key)) => (Some(prefix), key),
+                None => (None, s),
+            },
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for name in prefix.into_iter().flat_map(|prefix| prefix.split('+')) {
+            modifiers |= match name.trim().to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "option" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => return Err(format!("unknown modifier '{}' in '{}'", other, s)),
+            };
+        }
+
+        let lower = key.to_lowercase();
+        let code = match lower.as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "tab" => KeyCode::Tab,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "space" => KeyCode::Char(' '),
+            _ => {
+                let mut chars = lower.chars();
+                match (chars.next(), chars.as_str()) {
+                    (Some(c), "") => KeyCode::Char(c),
+                    (Some('f'), number) => match number.parse() {
+                        Ok(n @ 1..=24) => KeyCode::F(n),
+                        _ => return Err(format!("unknown key '{}' in '{}'", key, s)),
+                    },
+                    _ => return Err(format!("unknown key '{}' in '{}'", key, s)),
+                }
+            }
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::parse(&s)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_uppercase()),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::PageUp => f.write_str("PageUp"),
+            KeyCode::PageDown => f.write_str("PageDown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// The default bindings: several chords for the common actions, since
+/// terminals (macOS ones especially) swallow different modifier combinations
+const DEFAULT_BINDINGS: &[(&str, Action)] = &[
+    ("ctrl+up", Action::ScrollUp),
+    ("alt+up", Action::ScrollUp),
+    ("shift+up", Action::ScrollUp),
+    ("ctrl+k", Action::ScrollUp),
+    ("ctrl+[", Action::ScrollUp),
+    ("ctrl+-", Action::ScrollUp),
+    ("f1", Action::ScrollUp),
+    ("ctrl+down", Action::ScrollDown),
+    ("alt+down", Action::ScrollDown),
+    ("shift+down", Action::ScrollDown),
+    ("ctrl+j", Action::ScrollDown),
+    ("ctrl+]", Action::ScrollDown),
+    ("ctrl+=", Action::ScrollDown),
+    ("f2", Action::ScrollDown),
+    ("ctrl+u", Action::HalfPageUp),
+    ("ctrl+d", Action::HalfPageDown),
+    ("ctrl+s", Action::Save),
+    ("ctrl+l", Action::Load),
+    ("ctrl+o", Action::Library),
+    ("ctrl+e", Action::EditMessage),
+    ("ctrl+f", Action::Search),
+    ("ctrl+g", Action::Find),
+    ("ctrl+r", Action::Regenerate),
+    ("ctrl+n", Action::CycleReply),
+    ("ctrl+shift+c", Action::Colors),
+    ("f3", Action::Colors),
+    ("ctrl+alt+c", Action::Colors),
+    ("alt+shift+c", Action::Colors),
+    ("ctrl+shift+p", Action::Profiles),
+    ("f4", Action::Profiles),
+    ("ctrl+alt+p", Action::Profiles),
+    ("alt+shift+p", Action::Profiles),
+    ("f5", Action::Settings),
+    ("f6", Action::Models),
+    ("f7", Action::Help),
+    ("ctrl+q", Action::Quit),
+];
+
+/// Which action each key chord runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(KeyChord, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|&(chord, action)| (KeyChord::parse(chord).expect("default key binding"), action))
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The defaults with the chords of each action in `overrides` replaced.
+    /// A chord given to an action is taken away from whichever action had it
+    /// by default, and an empty list leaves the action unbound.
+    pub fn with_overrides(overrides: &BTreeMap<Action, Vec<KeyChord>>) -> Self {
+        let taken: Vec<KeyChord> = overrides.values().flatten().copied().collect();
+        let mut keymap = Self::default();
+        keymap.bindings.retain(|(chord, action)| !overrides.contains_key(action) && !taken.contains(chord));
+        for (&action, chords) in overrides {
+            keymap.bindings.extend(chords.iter().map(|&chord| (chord, action)));
+        }
+        keymap
+    }
+
+    /// The action bound to a key press, if any
+    pub fn action(&self, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        let pressed = KeyChord::new(code, modifiers);
+        self.bindings.iter().find(|(chord, _)| *chord == pressed).map(|&(_, action)| action)
+    }
+
+    /// The chords bound to `action`, in the order they were given
+    pub fn chords(&self, action: Action) -> Vec<KeyChord> {
+        self.bindings.iter().filter(|(_, bound)| *bound == action).map(|&(chord, _)| chord).collect()
+    }
+
+    /// How to trigger `action`, for help text: its chords joined with " / "
+    pub fn describe(&self, action: Action) -> String {
+        let chords: Vec<String> = self.chords(action).iter().map(ToString::to_string).collect();
+        if chords.is_empty() {
+            "unbound".to_string()
+        } else {
+            chords.join(" / ")
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Unit tests for the keymap
+//! Tests parsing key chords, the default bindings and replacing them from
+//! the settings
+
+use std::collections::BTreeMap;
+use crossterm::event::{KeyCode, KeyModifiers};
+use tempfile::TempDir;
+
+use crate::keymap::*;
+use crate::settings::load_settings_from;
+
+fn chord(s: &str) -> KeyChord {
+    KeyChord::parse(s).unwrap()
+}
+
+#[cfg(test)]
+mod chord_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(chord("ctrl+k"), KeyChord::new(KeyCode::Char('k'), KeyModifiers::CONTROL));
+        assert_eq!(chord("Ctrl+Shift+C"), KeyChord::new(KeyCode::Char('c'), KeyModifiers::CONTROL | KeyModifiers::SHIFT));
+        assert_eq!(chord("f12"), KeyChord::new(KeyCode::F(12), KeyModifiers::NONE));
+        assert_eq!(chord("alt+up"), KeyChord::new(KeyCode::Up, KeyModifiers::ALT));
+        assert_eq!(chord("ctrl+["), KeyChord::new(KeyCode::Char('['), KeyModifiers::CONTROL));
+        assert_eq!(chord("ctrl++"), KeyChord::new(KeyCode::Char('+'), KeyModifiers::CONTROL));
+        assert_eq!(chord("ctrl+space"), KeyChord::new(KeyCode::Char(' '), KeyModifiers::CONTROL));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for bad in ["", "hyper+k", "ctrl+", "f25", "ctrl+banana"] {
+            assert!(KeyChord::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+
+    /// Key presses match however the terminal reports Shift
+    #[test]
+    fn test_shift_normalized() {
+        let shifted = KeyChord::new(KeyCode::Char('c'), KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+        assert_eq!(KeyChord::new(KeyCode::Char('C'), KeyModifiers::CONTROL), shifted);
+        assert_eq!(KeyChord::new(KeyCode::Char('C'), KeyModifiers::CONTROL | KeyModifiers::SHIFT), shifted);
+        assert_eq!(
+            KeyChord::new(KeyCode::Char('?'), KeyModifiers::SHIFT),
+            KeyChord::new(KeyCode::Char('?'), KeyModifiers::NONE)
+        );
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for s in ["Ctrl+K", "Ctrl+Shift+C", "Alt+Up", "F3", "Ctrl+[", "Ctrl+Space", "PageDown"] {
+            assert_eq!(chord(s).to_string(), s);
+        }
+    }
+}
+
+#[cfg(test)]
+mod binding_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_bindings() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.action(KeyCode::Char('k'), KeyModifiers::CONTROL), Some(Action::ScrollUp));
+        assert_eq!(keymap.action(KeyCode::F(2), KeyModifiers::NONE), Some(Action::ScrollDown));
+        assert_eq!(keymap.action(KeyCode::Char('C'), KeyModifiers::CONTROL | KeyModifiers::SHIFT), Some(Action::Colors));
+        assert_eq!(keymap.action(KeyCode::Char('p'), KeyModifiers::ALT | KeyModifiers::SHIFT), Some(Action::Profiles));
+        assert_eq!(keymap.action(KeyCode::Char('r'), KeyModifiers::CONTROL), Some(Action::Regenerate));
+        assert_eq!(keymap.action(KeyCode::Char('k'), KeyModifiers::NONE), None);
+        assert_eq!(keymap.describe(Action::Colors), "Ctrl+Shift+C / F3 / Ctrl+Alt+C / Alt+Shift+C");
+    }
+
+    /// Every action has a default binding, and no chord is bound twice
+    #[test]
+    fn test_defaults_complete_and_unique() {
+        let keymap = Keymap::default();
+        let mut seen = Vec::new();
+        for action in Action::ALL {
+            let chords = keymap.chords(action);
+            assert!(!chords.is_empty(), "{} is unbound", action.name());
+            for chord in chords {
+                assert!(!seen.contains(&chord), "{} is bound twice", chord);
+                seen.push(chord);
+            }
+        }
+    }
+
+    /// Listing an action replaces its chords; a chord moved to another
+    /// action stops doing what it did by default
+    #[test]
+    fn test_overrides() {
+        let overrides = BTreeMap::from([
+            (Action::Colors, vec![chord("f9")]),
+            (Action::Help, vec![chord("f1")]),
+            (Action::Quit, vec![]),
+        ]);
+        let keymap = Keymap::with_overrides(&overrides);
+
+        assert_eq!(keymap.action(KeyCode::F(9), KeyModifiers::NONE), Some(Action::Colors));
+        assert_eq!(keymap.action(KeyCode::F(3), KeyModifiers::NONE), None);
+        assert_eq!(keymap.action(KeyCode::F(1), KeyModifiers::NONE), Some(Action::Help));
+        assert_eq!(keymap.action(KeyCode::F(7), KeyModifiers::NONE), None);
+        assert_eq!(keymap.describe(Action::ScrollUp), "Ctrl+Up / Alt+Up / Shift+Up / Ctrl+K / Ctrl+[ / Ctrl+-");
+        assert_eq!(keymap.describe(Action::Quit), "unbound");
+    }
+
+    #[test]
+    fn test_keys_in_settings() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("config.toml");
+        std::fs::write(&path, "[keys]\nscroll_up = [\"ctrl+p\", \"F1\"]\nsettings = []\n").unwrap();
+
+        let settings = load_settings_from(std::slice::from_ref(&path)).unwrap();
+        assert_eq!(settings.keys[&Action::ScrollUp], vec![chord("ctrl+p"), chord("f1")]);
+        assert!(settings.keys[&Action::Settings].is_empty());
+
+        std::fs::write(&path, "[keys]\nscroll_up = [\"ctrl+\"]\n").unwrap();
+        let error = load_settings_from(std::slice::from_ref(&path)).unwrap_err().to_string();
+        assert!(error.contains("config.toml") && error.contains("ctrl+"), "{}", error);
+
+        std::fs::write(&path, "[keys]\nteleport = [\"f9\"]\n").unwrap();
+        let error = load_settings_from(&[path]).unwrap_err().to_string();
+        assert!(error.contains("teleport"), "{}", error);
+    }
+}
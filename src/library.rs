@@ -0,0 +1,215 @@
+// src/library.rs
+//! The session library: every conversation, kept in the data directory as it
+//! grows so it can be found and resumed later without hunting for a file.
+//! Entries are ordinary save files, named after the time they were started.
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use crate::api::Message;
+use crate::client::ConversationClient;
+use crate::handlers::file_ops::{load_conversation, SavedConversation};
+use crate::session::write_session;
+
+/// How many messages of a session the library dialog previews
+const PREVIEW_MESSAGES: usize = 4;
+
+pub fn get_library_directory() -> PathBuf {
+    let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("claudecli");
+    path.push("sessions");
+    path
+}
+
+/// Load a save file to carry on with. A file from the library keeps
+/// updating its entry rather than starting a new one.
+pub fn load_session_file(path: &Path) -> Result<SavedConversation> {
+    let mut conversation = load_conversation(&path.to_path_buf())?;
+    if path.parent() == Some(get_library_directory().as_path()) {
+        conversation.library_id = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string);
+    }
+    Ok(conversation)
+}
+
+/// What the library dialog shows of a session without holding all of it
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub id: String,
+    pub title: String,
+    /// When the session was last saved, as RFC 3339
+    pub timestamp: String,
+    pub model: String,
+    pub total_tokens: u32,
+    pub message_count: usize,
+    /// Opening messages of the branch that was on screen
+    pub preview: Vec<Message>,
+}
+
+impl SessionSummary {
+    fn new(id: String, conversation: SavedConversation) -> Self {
+        let branch = conversation.branch();
+        Self {
+            title: conversation.title.unwrap_or_else(|| default_title(&branch)),
+            id,
+            timestamp: conversation.timestamp,
+            model: conversation.model,
+            total_tokens: conversation.total_input_tokens + conversation.total_output_tokens,
+            message_count: branch.len(),
+            preview: branch.into_iter().take(PREVIEW_MESSAGES).collect(),
+        }
+    }
+
+    /// The last save in local time, for display
+    pub fn local_time(&self) -> String {
+        local_time(&self.timestamp)
+    }
+}
+
+/// An RFC 3339 timestamp in local time, for display; unchanged if it doesn't parse
+pub fn local_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+/// Title for a session that was never named: the start of its first prompt
+pub fn default_title(messages: &[Message]) -> String {
+    let first_line = messages
+        .iter()
+        .find(|message| message.role == "user")
+        .and_then(|message| message.content.lines().find(|line| !line.trim().is_empty()))
+        .map(str::trim)
+        .unwrap_or("Untitled");
+    if first_line.chars().count() > 60 {
+        format!("{}…", first_line.chars().take(59).collect::<String>())
+    } else {
+        first_line.to_string()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionLibrary {
+    dir: PathBuf,
+}
+
+impl SessionLibrary {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Every readable session, most recently saved first
+    pub fn list(&self) -> Vec<SessionSummary> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut sessions: Vec<SessionSummary> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .filter_map(|path| {
+                let id = path.file_stem()?.to_str()?.to_string();
+                let conversation = load_conversation(&path).ok()?;
+                Some(SessionSummary::new(id, conversation))
+            })
+            .collect();
+        sessions.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| b.id.cmp(&a.id)));
+        sessions
+    }
+
+    /// Store the conversation under `id`, or under a new id if it has none
+    /// yet. Returns the id it was stored under.
+    pub fn save(&self, client: &ConversationClient, id: Option<&str>) -> Result<String> {
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => self.new_id(),
+        };
+        write_session(&SavedConversation::new(client), &self.path(&id))?;
+        Ok(id)
+    }
+
+    pub fn load(&self, id: &str) -> Result<SavedConversation> {
+        load_conversation(&self.path(id))
+    }
+
+    pub fn delete(&self, id: &str) -> Result<()> {
+        fs::remove_file(self.path(id))
+            .map_err(|e| anyhow!("Could not delete session {}: {}", id, e))
+    }
+
+    /// Give a session a new title; an empty one goes back to the default
+    pub fn rename(&self, id: &str, title: &str) -> Result<()> {
+        let mut conversation = self.load(id)?;
+        let title = title.trim();
+        conversation.title = if title.is_empty() { None } else { Some(title.to_string()) };
+        write_session(&conversation, &self.path(id))
+    }
+
+    /// An id from the current time, with a suffix if a session already has it
+    fn new_id(&self) -> String {
+        let base = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut id = base.clone();
+        let mut suffix = 2;
+        while self.path(&id).exists() {
+            id = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        id
+    }
+}
+
+/// Positions in `sessions` matching `query`, best match first. Every
+/// character of the query has to appear in the title, model or first
+/// messages, in order; an empty query keeps the library order.
+pub fn filter_sessions(sessions: &[SessionSummary], query: &str) -> Vec<usize> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return (0..sessions.len()).collect();
+    }
+
+    let mut scored: Vec<(i64, usize)> = sessions
+        .iter()
+        .enumerate()
+        .filter_map(|(position, session)| {
+            let title = fuzzy_score(&session.title, &query).map(|score| score * 2);
+            let others = std::iter::once(session.model.as_str())
+                .chain(session.preview.iter().map(|message| message.content.as_str()))
+                .filter_map(|text| fuzzy_score(text, &query));
+            title.into_iter().chain(others).max().map(|score| (score, position))
+        })
+        .collect();
+    // Stable, so equally good matches stay newest first
+    scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    scored.into_iter().map(|(_, position)| position).collect()
+}
+
+/// Score how well `text` matches the lowercase `query` as a subsequence,
+/// favouring runs of consecutive characters and matches at word starts
+fn fuzzy_score(text: &str, query: &str) -> Option<i64> {
+    let mut wanted = query.chars().filter(|c| !c.is_whitespace()).peekable();
+    let mut score = 0;
+    let mut run = 0;
+    let mut previous = ' ';
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let Some(&next) = wanted.peek() else {
+            break;
+        };
+        if c == next {
+            run += 1;
+            score += run;
+            if !previous.is_alphanumeric() {
+                score += 3;
+            }
+            wanted.next();
+        } else {
+            run = 0;
+        }
+        previous = c;
+    }
+    match wanted.peek() {
+        None => Some(score),
+        Some(_) => None,
+    }
+}
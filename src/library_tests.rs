@@ -0,0 +1,258 @@
+//! Unit tests for the session library
+//! Tests storing, listing, filtering and managing sessions, and the library dialog
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use tempfile::TempDir;
+
+use crate::app::AppState;
+use crate::client::ConversationClient;
+use crate::config::get_default_colors;
+use crate::library::{default_title, filter_sessions, SessionLibrary};
+use crate::session::Autosave;
+use crate::test_support::{message, press};
+
+fn client_saying(prompt: &str) -> ConversationClient {
+    let mut client = ConversationClient::new("key".to_string(), "test_model".to_string(), 100, 0.7);
+    client.messages.push(message("user", prompt));
+    client.messages.push(message("assistant", "Sure."));
+    client
+}
+
+#[cfg(test)]
+mod store_tests {
+    use super::*;
+
+    /// A session saved twice under its id stays a single entry
+    #[test]
+    fn test_save_and_list() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let library = SessionLibrary::new(temp_dir.path().join("sessions"));
+        assert!(library.list().is_empty());
+
+        let mut client = client_saying("Explain lifetimes\nin Rust");
+        let id = library.save(&client, None).unwrap();
+        client.messages.push(message("user", "More"));
+        assert_eq!(library.save(&client, Some(&id)).unwrap(), id);
+
+        let sessions = library.list();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].title, "Explain lifetimes");
+        assert_eq!(sessions[0].model, "test_model");
+        assert_eq!(sessions[0].message_count, 3);
+    }
+
+    /// Sessions started in the same second get ids of their own
+    #[test]
+    fn test_new_ids_do_not_collide() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let library = SessionLibrary::new(temp_dir.path().to_path_buf());
+
+        let first = library.save(&client_saying("a"), None).unwrap();
+        let second = library.save(&client_saying("b"), None).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(library.list().len(), 2);
+    }
+
+    #[test]
+    fn test_rename_and_delete() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let library = SessionLibrary::new(temp_dir.path().to_path_buf());
+        let id = library.save(&client_saying("a question"), None).unwrap();
+
+        library.rename(&id, "  Borrow checker notes ").unwrap();
+        assert_eq!(library.list()[0].title, "Borrow checker notes");
+        assert_eq!(library.load(&id).unwrap().messages.len(), 2);
+
+        library.rename(&id, "").unwrap();
+        assert_eq!(library.list()[0].title, "a question");
+
+        library.delete(&id).unwrap();
+        assert!(library.list().is_empty());
+        assert!(library.delete(&id).is_err());
+    }
+
+    #[test]
+    fn test_default_title() {
+        assert_eq!(default_title(&[]), "Untitled");
+        assert_eq!(default_title(&[message("user", "\n  Hello there  \nmore")]), "Hello there");
+        let long = "x".repeat(100);
+        assert_eq!(default_title(&[message("user", &long)]).chars().count(), 60);
+    }
+
+    /// Autosave keeps the library entry in step, and a cleared conversation starts a new one
+    #[test]
+    fn test_autosave_writes_library_entry() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let library = SessionLibrary::new(temp_dir.path().join("sessions"));
+        let mut client = ConversationClient::new("key".to_string(), "m".to_string(), 100, 0.7);
+        let mut autosave = Autosave::new(temp_dir.path().join("session.json"), &client)
+            .with_library(library.clone());
+
+        client.messages.push(message("user", "first"));
+        autosave.save(&client).unwrap();
+        let entry = autosave.entry().unwrap().to_string();
+        client.messages.push(message("assistant", "reply"));
+        autosave.save(&client).unwrap();
+        assert_eq!(autosave.entry(), Some(entry.as_str()));
+        assert_eq!(library.list().len(), 1);
+        assert_eq!(library.list()[0].message_count, 2);
+
+        client.messages.clear();
+        autosave.save(&client).unwrap();
+        assert!(autosave.entry().is_none());
+        assert_eq!(library.list().len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+
+    fn sessions(titles: &[&str]) -> Vec<crate::library::SessionSummary> {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let library = SessionLibrary::new(temp_dir.path().to_path_buf());
+        for title in titles {
+            let id = library.save(&client_saying("prompt"), None).unwrap();
+            library.rename(&id, title).unwrap();
+        }
+        let mut sessions = library.list();
+        sessions.sort_by(|a, b| a.title.cmp(&b.title));
+        sessions
+    }
+
+    #[test]
+    fn test_empty_filter_keeps_everything() {
+        let sessions = sessions(&["a", "b"]);
+        assert_eq!(filter_sessions(&sessions, "  "), vec![0, 1]);
+    }
+
+    /// Query characters must appear in order, and tighter matches come first
+    #[test]
+    fn test_fuzzy_matches_rank_closer_first() {
+        let sessions = sessions(&["Deploy script", "Docker setup", "Rust lifetimes"]);
+
+        assert_eq!(filter_sessions(&sessions, "dock"), vec![1]);
+        assert_eq!(filter_sessions(&sessions, "dep"), vec![0, 1]);
+        assert!(filter_sessions(&sessions, "zzz").is_empty());
+    }
+
+    /// The first messages are searched as well as the title
+    #[test]
+    fn test_filter_searches_preview() {
+        let sessions = sessions(&["One", "Two"]);
+        assert_eq!(filter_sessions(&sessions, "sure").len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod library_dialog_tests {
+    use super::*;
+
+    /// App with a library in a temp dir holding two sessions
+    fn app_with_library(temp_dir: &TempDir) -> AppState {
+        let library = SessionLibrary::new(temp_dir.path().join("sessions"));
+        let older = library.save(&client_saying("How do I parse TOML?"), None).unwrap();
+        library.rename(&older, "TOML parsing").unwrap();
+        let newer = library.save(&client_saying("Write a haiku"), None).unwrap();
+        library.rename(&newer, "Haiku").unwrap();
+
+        let mut app = AppState::new(
+            "test_key".to_string(),
+            "test_model".to_string(),
+            1000,
+            0.7,
+            true,
+            get_default_colors(),
+        ).expect("Failed to create AppState");
+        app.autosave = Autosave::new(temp_dir.path().join("session.json"), &app.client)
+            .with_library(library);
+        app
+    }
+
+    async fn type_text(app: &mut AppState, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c), KeyModifiers::NONE).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filter_and_resume() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let mut app = app_with_library(&temp_dir);
+
+        press(&mut app, KeyCode::Char('o'), KeyModifiers::CONTROL).await;
+        assert!(app.show_library_dialog);
+        assert_eq!(app.library_matches.len(), 2);
+
+        type_text(&mut app, "parse").await;
+        assert_eq!(app.library_matches.len(), 1);
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+
+        assert!(!app.show_library_dialog);
+        assert_eq!(app.client.messages[0].content, "How do I parse TOML?");
+        assert_eq!(app.client.title.as_deref(), Some("TOML parsing"));
+        assert!(app.autosave.entry().is_some());
+    }
+
+    /// Delete asks first; anything but yes keeps the session
+    #[tokio::test]
+    async fn test_delete_confirms() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let mut app = app_with_library(&temp_dir);
+        press(&mut app, KeyCode::Char('o'), KeyModifiers::CONTROL).await;
+
+        press(&mut app, KeyCode::Delete, KeyModifiers::NONE).await;
+        assert!(app.library_confirm_delete);
+        press(&mut app, KeyCode::Char('n'), KeyModifiers::NONE).await;
+        assert_eq!(app.library_sessions.len(), 2);
+
+        press(&mut app, KeyCode::Delete, KeyModifiers::NONE).await;
+        press(&mut app, KeyCode::Char('y'), KeyModifiers::NONE).await;
+        assert_eq!(app.library_sessions.len(), 1);
+        assert_eq!(app.autosave.library().unwrap().list().len(), 1);
+        assert!(app.show_library_dialog);
+    }
+
+    #[tokio::test]
+    async fn test_rename_selected_session() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let mut app = app_with_library(&temp_dir);
+        app.input = "/library".to_string();
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+        assert!(app.show_library_dialog);
+        let title = app.library_sessions[app.library_matches[0]].title.clone();
+
+        press(&mut app, KeyCode::F(2), KeyModifiers::NONE).await;
+        assert_eq!(app.library_rename.as_deref(), Some(title.as_str()));
+        for _ in 0..title.chars().count() {
+            press(&mut app, KeyCode::Backspace, KeyModifiers::NONE).await;
+        }
+        type_text(&mut app, "Renamed").await;
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+
+        assert!(app.library_rename.is_none());
+        let titles: Vec<String> = app.library_sessions.iter().map(|s| s.title.clone()).collect();
+        assert!(titles.contains(&"Renamed".to_string()));
+        assert!(!titles.contains(&title));
+        assert!(app.input.is_empty());
+    }
+
+    /// Without autosave there is no library to open
+    #[tokio::test]
+    async fn test_library_needs_autosave() {
+        let mut app = AppState::new(
+            "test_key".to_string(),
+            "test_model".to_string(),
+            1000,
+            0.7,
+            true,
+            get_default_colors(),
+        ).expect("Failed to create AppState");
+
+        press(&mut app, KeyCode::Char('o'), KeyModifiers::CONTROL).await;
+
+        assert!(!app.show_library_dialog);
+        assert!(app.status.contains("--no-autosave"));
+    }
+}
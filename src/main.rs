@@ -1,11 +1,24 @@
 mod api;
+mod backend;
 mod client;
 mod syntax;
 mod tui;
 mod app;
 mod config;
+mod export;
+mod find;
 mod utils;
 mod handlers;
+mod import;
+mod keymap;
+mod library;
+mod markdown;
+mod oneshot;
+mod plain;
+mod session;
+mod settings;
+mod title;
+mod tree;
 mod ui;
 
 // Test modules
@@ -22,13 +35,38 @@ mod config_module_tests;
 #[cfg(test)]
 mod config_tests;
 #[cfg(test)]
+mod export_tests;
+#[cfg(test)]
+mod find_tests;
+#[cfg(test)]
+mod import_tests;
+#[cfg(test)]
 mod integration_tests;
 #[cfg(test)]
+mod keymap_tests;
+#[cfg(test)]
+mod library_tests;
+#[cfg(test)]
 mod main_tests;
+#[cfg(test)]
+mod markdown_tests;
+#[cfg(test)]
+mod oneshot_tests;
+#[cfg(test)]
+mod plain_tests;
+#[cfg(test)]
+mod session_tests;
+#[cfg(test)]
+mod settings_tests;
+#[cfg(test)]
+mod title_tests;
+#[cfg(test)]
+mod tree_tests;
+#[cfg(test)]
+mod test_support;
 
 use anyhow::Result;
-use clap::Parser;
-use api::Message;
+use clap::{CommandFactory, FromArgMatches};
 use ratatui::{
     backend::CrosstermBackend,
     Terminal,
@@ -39,14 +77,121 @@ use crossterm::{
     execute,
 };
 use tokio::sync::mpsc;
-use config::{Args, ColorConfig, SCROLL_ON_USER_INPUT, SCROLL_ON_API_RESPONSE, 
+use config::{Args, ColorConfig,
            MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT, MIN_MESSAGE_DISPLAY_WIDTH, MIN_MESSAGE_DISPLAY_HEIGHT};
+use std::io::IsTerminal;
+use std::sync::Arc;
 use std::time::Duration;
+use backend::{AnthropicBackend, ChatBackend, RequestUpdate, SimulatedBackend};
 use ui::{layout::create_main_layout, render::draw_ui};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(command) = &args.command {
+        return run_command(command);
+    }
+    let working_dir = std::env::current_dir()?;
+    let project_dir = match settings::find_project_dir(&working_dir) {
+        Some(dir) if trust_project(&dir, args.trust_project)? => Some(dir),
+        _ => None,
+    };
+    let settings = settings::load_settings(project_dir.as_deref())?;
+    args.apply_settings(&settings, &matches);
+    args.apply_preset(&settings, &matches)?;
+    let system_prompt = args.system_prompt()?;
+    // Context files are named relative to the settings file that lists them
+    let context_dir = settings.context_dir.as_deref().unwrap_or(&working_dir);
+    let context = settings::read_context_files(&settings.context_files, context_dir)?;
+    let saves_directory = match &settings.saves_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| anyhow::anyhow!("Failed to create saves directory {}: {}", dir.display(), e))?;
+            dir.clone()
+        }
+        None => handlers::file_ops::get_saves_directory(),
+    };
+    let opened = args.open.as_deref().map(library::load_session_file).transpose()?;
+
+    // Build the backend up front so bad settings fail before the TUI starts
+    let chat_backend: Arc<dyn ChatBackend> = if args.simulate {
+        Arc::new(SimulatedBackend::new())
+    } else {
+        let api_config = args.api_config(settings.api.clone());
+        let mut backend = AnthropicBackend::new(args.api_key.clone())
+            .with_retry(args.retry_policy())
+            .with_headers(&api_config.headers)?;
+        if let Some(base_url) = api_config.base_url {
+            backend = backend.with_base_url(base_url);
+        }
+        if let Some(version) = api_config.anthropic_version {
+            backend = backend.with_anthropic_version(version);
+        }
+        Arc::new(backend)
+    };
+
+    // One-shot mode: `-p` or a piped prompt skips the TUI entirely
+    let piped = if oneshot::wants_stdin(args.plain, std::io::stdin().is_terminal()) {
+        oneshot::read_piped_stdin()?
+    } else {
+        None
+    };
+    if args.prompt.is_some() || piped.is_some() {
+        let Some(prompt) = oneshot::build_prompt(args.prompt.as_deref(), piped.as_deref()) else {
+            eprintln!("Error: no prompt given (use -p or pipe text on stdin)");
+            std::process::exit(oneshot::EXIT_NO_PROMPT);
+        };
+        let mut client = client::ConversationClient::new(
+            args.api_key.clone(),
+            args.model.clone(),
+            args.max_tokens,
+            args.temperature,
+        ).with_backend(chat_backend);
+        client.stream = !args.no_stream;
+        client.system_prompt = system_prompt;
+        client.context = context;
+
+        let code = oneshot::run(&mut client, &prompt, &mut std::io::stdout(), &mut std::io::stderr()).await;
+        std::process::exit(code);
+    }
+
+    // Plain mode: a line-oriented REPL in the normal screen
+    if args.plain {
+        let mut app = app::AppState::new(
+            args.api_key,
+            args.model,
+            args.max_tokens,
+            args.temperature,
+            args.simulate,
+            ColorConfig::default(),
+        )?;
+        app.client.backend = chat_backend;
+        app.client.stream = !args.no_stream;
+        app.client.system_prompt = system_prompt;
+        app.client.context = context;
+        app.ui = settings.ui;
+        app.presets = settings.presets.clone();
+        app.active_preset = args.preset.clone();
+        if let Some(model) = &settings.title_model {
+            app.title_model = model.clone();
+        }
+        app.saves_directory = saves_directory;
+        if !args.no_autosave {
+            start_autosave(&mut app);
+        }
+        if let Some(conversation) = opened {
+            app.apply_saved_conversation(conversation);
+        }
+
+        // Ctrl+C cancels the reply in progress; at the prompt rustyline handles it
+        let interrupted = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let flag = interrupted.clone();
+        ctrlc::set_handler(move || flag.store(true, std::sync::atomic::Ordering::SeqCst))
+            .expect("Error setting Ctrl-C handler");
+
+        return plain::run(&mut app, interrupted).await;
+    }
 
     // Set up a panic hook to ensure we always clean up the terminal
     let original_hook = std::panic::take_hook();
@@ -101,6 +246,27 @@ async fn main() -> Result<()> {
         args.simulate,
         colors,
     )?;
+    app.client.stream = !args.no_stream;
+    app.client.system_prompt = system_prompt;
+    app.client.context = context;
+    app.client.backend = chat_backend;
+    app.ui = settings.ui;
+    app.ui_from_project = settings.ui_from_project.clone();
+    app.presets = settings.presets.clone();
+    app.active_preset = args.preset.clone();
+    if let Some(model) = &settings.title_model {
+        app.title_model = model.clone();
+    }
+    app.current_directory = saves_directory.clone();
+    app.saves_directory = saves_directory;
+    app.keymap = keymap::Keymap::with_overrides(&settings.keys);
+    if !args.no_autosave {
+        start_autosave(&mut app);
+        app.show_restore_dialog = app.restore_candidate.is_some() && opened.is_none();
+    }
+    if let Some(conversation) = opened {
+        app.apply_saved_conversation(conversation);
+    }
     
     // Show config error dialog if there was an issue loading the config
     if let Some(error_msg) = config_error {
@@ -108,7 +274,10 @@ async fn main() -> Result<()> {
     }
 
     // Channel for API responses
-    let (tx, mut rx) = mpsc::channel::<Result<(String, u32, u32, Vec<Message>), String>>(10);
+    let (tx, mut rx) = mpsc::channel::<RequestUpdate>(64);
+
+    // Slows the progress animation down relative to the event loop
+    let mut frame_counter: u32 = 0;
 
     loop {
         // Check if we should exit due to Ctrl+C
@@ -125,9 +294,9 @@ async fn main() -> Result<()> {
             
             app.last_message_count = current_message_count;
             
-            // Apply feature flags to control when to enable auto-scroll
-            if (is_user_message && SCROLL_ON_USER_INPUT) || 
-               (!is_user_message && SCROLL_ON_API_RESPONSE) {
+            // Apply the scroll settings to control when to enable auto-scroll
+            if (is_user_message && app.ui.scroll_on_user_input) || 
+               (!is_user_message && app.ui.scroll_on_api_response) {
                 app.auto_scroll = true;
             }
             
@@ -139,13 +308,10 @@ async fn main() -> Result<()> {
         let needs_animation_update = app.waiting;
         if needs_animation_update {
             // Slow down progress animation - only increment every 4th iteration
-            static mut FRAME_COUNTER: u32 = 0;
-            unsafe {
-                FRAME_COUNTER += 1;
-                if FRAME_COUNTER % 4 == 0 {
-                    app.progress_i += 1;
-                    app.mark_dirty(); // Mark for redraw when progress changes
-                }
+            frame_counter = frame_counter.wrapping_add(1);
+            if frame_counter.is_multiple_of(4) {
+                app.progress_i += 1;
+                app.mark_dirty(); // Mark for redraw when progress changes
             }
         }
 
@@ -237,34 +403,39 @@ async fn main() -> Result<()> {
             }
         }
 
-        // Check for API responses
-        if let Ok(result) = rx.try_recv() {
-            app.waiting = false;
-            app.status = "Ready".to_string();
-            
-            match result {
-                Ok((_response, input_tokens, output_tokens, updated_messages)) => {
-                    // Normal response handling
-                    if let Some(assistant_msg) = updated_messages.last() {
-                        if assistant_msg.role == "assistant" {
-                            app.client.messages.push(assistant_msg.clone());
-                        }
-                    }
-                    
-                    app.client.total_input_tokens += input_tokens;
-                    app.client.total_output_tokens += output_tokens;
-                }
-                Err(error_msg) => {
-                    // Show the actual error message
-                    app.show_error_dialog = true;
-                    app.error_message = error_msg;
-                }
+        // Check for API responses - drain everything that arrived since the last frame
+        let was_waiting = app.waiting;
+        while let Ok(update) = rx.try_recv() {
+            if !handlers::api::is_current(&app, &update) {
+                // Left over from a request that was cancelled or replaced
+                continue;
             }
             
+            handlers::api::handle_api_update(&mut app, update.update);
+            
             // Mark for redraw after API response
             app.mark_dirty();
         }
 
+        // Name the conversation after its first exchange
+        if was_waiting && !app.waiting {
+            title::request_title(&mut app);
+        }
+        if title::collect_title(&mut app).await {
+            app.mark_dirty();
+        }
+
+        // Autosave every finished reply, and anything else now and then
+        let autosaved = if was_waiting && !app.waiting {
+            app.autosave.save(&app.client)
+        } else {
+            app.autosave.tick(&app.client)
+        };
+        if let Err(e) = autosaved {
+            app.status = format!("Autosave failed: {}", e);
+            app.mark_dirty();
+        }
+
         // Add a small delay to prevent excessive CPU usage
         if app.waiting {
             tokio::time::sleep(Duration::from_millis(10)).await;
@@ -274,6 +445,11 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Keep the session for next time, however the loop ended
+    if let Err(e) = app.autosave.save(&app.client) {
+        eprintln!("Warning: Failed to autosave the session: {}", e);
+    }
+
     // Save color configuration before cleanup
     if let Err(e) = app.save_color_config() {
         eprintln!("Warning: Failed to save color configuration: {}", e);
@@ -283,4 +459,91 @@ async fn main() -> Result<()> {
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Autosave to the session file and the session library, remembering the
+/// session the last run left behind so the user can be offered to restore it
+fn start_autosave(app: &mut app::AppState) {
+    let path = session::get_session_path();
+    app.restore_candidate = session::load_session(&path);
+    app.autosave = session::Autosave::new(path, &app.client)
+        .with_library(library::SessionLibrary::new(library::get_library_directory()));
+}
+
+/// Whether to use the settings files of the project in `dir`. A project is
+/// trusted once, with `--trust-project` or by answering the question asked
+/// here, and remembered; until then its settings are ignored.
+fn trust_project(dir: &std::path::Path, mut trust: bool) -> Result<bool> {
+    let list = settings::trusted_projects_path();
+    if settings::is_trusted_in(dir, &list) {
+        return Ok(true);
+    }
+    if !trust && std::io::stdin().is_terminal() && std::io::stderr().is_terminal() {
+        eprint!(
+            "Use the project settings in {}? They can change the model and system prompt, \
+             and send files from the project to the API. [y/N] ",
+            dir.display()
+        );
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        trust = answer.trim().eq_ignore_ascii_case("y");
+    }
+    if trust {
+        settings::trust_project_in(dir, &list)?;
+    } else {
+        eprintln!(
+            "Warning: Ignoring the project settings in {}; run with --trust-project to use them",
+            dir.display()
+        );
+    }
+    Ok(trust)
+}
+
+/// Run a subcommand, which works on files without starting a conversation
+fn run_command(command: &config::Command) -> Result<()> {
+    match command {
+        config::Command::Export { file, format, output } => {
+            let document = export::export_file(file, *format, output.as_deref())?;
+            if output.is_none() {
+                print!("{}", document);
+            }
+        }
+        config::Command::Search { query, dirs, no_index } => {
+            let dirs = if dirs.is_empty() {
+                let project = std::env::current_dir().ok().and_then(|dir| settings::trusted_project_dir(&dir));
+                let saves = settings::load_settings(project.as_deref())?
+                    .saves_dir
+                    .unwrap_or_else(handlers::file_ops::get_saves_directory);
+                find::default_search_dirs(&saves)
+            } else {
+                dirs.clone()
+            };
+            let index = (!no_index).then(find::get_index_path);
+            let conversations = find::scan(&dirs, index.as_deref());
+            print_search_hits(&find::search(&conversations, query), query);
+        }
+    }
+    Ok(())
+}
+
+/// Hits grouped by conversation, each under its title and file
+fn print_search_hits(hits: &[find::SearchHit], query: &str) {
+    if hits.is_empty() {
+        eprintln!("No saved messages match \"{}\"", query.trim());
+        return;
+    }
+    let mut previous: Option<&std::path::Path> = None;
+    for hit in hits {
+        if previous != Some(hit.path.as_path()) {
+            if previous.is_some() {
+                println!();
+            }
+            println!("{} · {}", hit.title, hit.local_time());
+            println!("  {}", hit.path.display());
+            previous = Some(hit.path.as_path());
+        }
+        println!("  [{}]: {}", hit.role, hit.snippet);
+    }
+    println!();
+    println!("Open a conversation with: claudecli --open <file>");
+}
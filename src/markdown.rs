@@ -0,0 +1,550 @@
+// src/markdown.rs
+//! Markdown rendering for assistant messages in the chat pane.
+//! Text is wrapped here rather than by the chat `Paragraph`, so list items and
+//! block quotes keep their indentation on continuation lines.
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::config::BorderStyle;
+use crate::syntax::highlight_code_block;
+
+/// Narrowest column text is wrapped to, however deep the indentation gets
+const MIN_TEXT_WIDTH: usize = 10;
+
+/// Table columns aren't squeezed below this when the pane is narrow
+const MIN_COLUMN_WIDTH: usize = 3;
+
+/// Render `content` as Markdown wrapped to `width` columns (0 disables wrapping).
+/// `label` starts the first line of every block; continuation lines are padded
+/// so the text lines up underneath it. Tables are drawn in `border` style.
+pub fn render_markdown(content: &str, label: Span<'static>, width: usize, border: BorderStyle) -> Vec<Line<'static>> {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES;
+    let mut renderer = Renderer::new(label, width, border);
+    for event in Parser::new_ext(content, options) {
+        renderer.event(event);
+    }
+    renderer.finish()
+}
+
+fn heading_style(level: HeadingLevel) -> Style {
+    let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    match level {
+        HeadingLevel::H1 => style.add_modifier(Modifier::UNDERLINED),
+        HeadingLevel::H2 => style,
+        _ => Style::default().add_modifier(Modifier::BOLD),
+    }
+}
+
+fn inline_code_style() -> Style {
+    Style::default().fg(Color::LightYellow).bg(Color::DarkGray)
+}
+
+fn link_style() -> Style {
+    Style::default().fg(Color::LightBlue).add_modifier(Modifier::UNDERLINED)
+}
+
+fn decoration_style() -> Style {
+    Style::default().fg(Color::DarkGray)
+}
+
+/// One level of list nesting
+struct ListLevel {
+    /// Number of the next item, or None for a bullet list
+    next_number: Option<u64>,
+    /// Width of this level's marker, which nested content is indented by
+    marker_width: usize,
+}
+
+/// A table being collected, drawn once it's complete
+#[derive(Default)]
+struct Table {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Vec<Span<'static>>>>,
+    /// Number of rows at the top that form the header
+    header_rows: usize,
+    row: Vec<Vec<Span<'static>>>,
+}
+
+struct Renderer {
+    label: Span<'static>,
+    width: usize,
+    border: BorderStyle,
+    lines: Vec<Line<'static>>,
+    /// Inline content of the block being built
+    inline: Vec<Span<'static>>,
+    styles: Vec<Style>,
+    lists: Vec<ListLevel>,
+    /// Marker for the first line of the current list item, until it's used
+    item_marker: Option<String>,
+    quote_depth: usize,
+    /// Language and contents of the fenced code block being collected
+    code: Option<(String, String)>,
+    /// Target and start position in `inline` of the links being built
+    links: Vec<(String, usize)>,
+    table: Option<Table>,
+    /// Put a blank line before the next block
+    pending_gap: bool,
+}
+
+impl Renderer {
+    fn new(label: Span<'static>, width: usize, border: BorderStyle) -> Self {
+        Self {
+            label,
+            width,
+            border,
+            lines: Vec::new(),
+            inline: Vec::new(),
+            styles: Vec::new(),
+            lists: Vec::new(),
+            item_marker: None,
+            quote_depth: 0,
+            code: None,
+            links: Vec::new(),
+            table: None,
+            pending_gap: false,
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => match &mut self.code {
+                Some((_, code)) => code.push_str(&text),
+                None => self.push_text(&text, self.style()),
+            },
+            Event::Code(code) => self.push_text(&code, self.style().patch(inline_code_style())),
+            Event::InlineMath(text) | Event::DisplayMath(text) => {
+                self.push_text(&text, self.style().patch(inline_code_style()))
+            }
+            Event::Html(html) | Event::InlineHtml(html) => self.push_text(html.trim_end_matches('\n'), self.style()),
+            Event::SoftBreak => self.push_text(" ", self.style()),
+            Event::HardBreak => self.flush(false),
+            Event::Rule => {
+                self.flush(true);
+                self.gap();
+                let width = self.text_width(0).min(self.width.max(MIN_TEXT_WIDTH));
+                let rule = Span::styled("─".repeat(width.min(80)), decoration_style());
+                self.lines.push(Line::from(rule));
+                self.pending_gap = true;
+            }
+            Event::TaskListMarker(checked) => {
+                self.push_text(if checked { "[x] " } else { "[ ] " }, decoration_style());
+            }
+            Event::FootnoteReference(name) => self.push_text(&format!("[^{}]", name), self.style()),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => self.flush(false),
+            Tag::Heading { level, .. } => {
+                self.flush(true);
+                self.styles.push(heading_style(level));
+            }
+            Tag::BlockQuote(_) => {
+                self.flush(true);
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush(true);
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush(true);
+                self.lists.push(ListLevel { next_number: start, marker_width: 2 });
+            }
+            Tag::Item => {
+                self.flush(true);
+                if let Some(level) = self.lists.last_mut() {
+                    let marker = match &mut level.next_number {
+                        Some(number) => {
+                            *number += 1;
+                            format!("{}. ", *number - 1)
+                        }
+                        None => "• ".to_string(),
+                    };
+                    level.marker_width = marker.width();
+                    self.item_marker = Some(marker);
+                }
+            }
+            Tag::Table(alignments) => {
+                self.flush(true);
+                self.table = Some(Table { alignments, ..Table::default() });
+            }
+            Tag::TableHead => self.styles.push(Style::default().add_modifier(Modifier::BOLD)),
+            Tag::TableCell => self.inline.clear(),
+            Tag::Emphasis => self.styles.push(Style::default().add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self.styles.push(Style::default().add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self.styles.push(Style::default().add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                self.styles.push(link_style());
+                self.links.push((dest_url.to_string(), self.inline.len()));
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph => {
+                self.flush(false);
+                self.pending_gap = true;
+            }
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.flush(false);
+                self.pending_gap = true;
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush(false);
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.pending_gap = true;
+            }
+            TagEnd::CodeBlock => {
+                if let Some((language, code)) = self.code.take() {
+                    self.push_code_block(&language, &code);
+                }
+                self.pending_gap = true;
+            }
+            TagEnd::List(_) => {
+                self.flush(false);
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.pending_gap = true;
+                }
+            }
+            TagEnd::Item => self.flush(false),
+            TagEnd::TableCell => {
+                let cell = std::mem::take(&mut self.inline);
+                if let Some(table) = &mut self.table {
+                    table.row.push(cell);
+                }
+            }
+            TagEnd::TableHead | TagEnd::TableRow => {
+                if tag == TagEnd::TableHead {
+                    self.styles.pop();
+                }
+                if let Some(table) = &mut self.table {
+                    let row = std::mem::take(&mut table.row);
+                    table.rows.push(row);
+                    if tag == TagEnd::TableHead {
+                        table.header_rows = table.rows.len();
+                    }
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.push_table(table);
+                }
+                self.pending_gap = true;
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            }
+            TagEnd::Link | TagEnd::Image => {
+                self.styles.pop();
+                if let Some((url, start)) = self.links.pop() {
+                    let text: String = self.inline[start.min(self.inline.len())..]
+                        .iter()
+                        .map(|span| span.content.as_ref())
+                        .collect();
+                    // Autolinks already show their target
+                    let shown = text == url || url.strip_prefix("mailto:") == Some(text.as_str());
+                    if !shown && !url.is_empty() {
+                        self.push_text(&format!(" ({})", url), decoration_style());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> Vec<Line<'static>> {
+        // Unterminated fences are common while a reply is still streaming in
+        if let Some((language, code)) = self.code.take() {
+            self.push_code_block(&language, &code);
+        }
+        self.flush(false);
+        self.lines
+    }
+
+    fn style(&self) -> Style {
+        self.styles.iter().fold(Style::default(), |style, s| style.patch(*s))
+    }
+
+    fn push_text(&mut self, text: &str, style: Style) {
+        if !text.is_empty() {
+            self.inline.push(Span::styled(text.to_string(), style));
+        }
+    }
+
+    fn gap(&mut self) {
+        if self.pending_gap && !self.lines.is_empty() {
+            let mut spans = Vec::new();
+            if self.quote_depth > 0 {
+                spans.push(Span::raw(" ".repeat(self.label.width())));
+                spans.extend(self.quote_prefix());
+            }
+            self.lines.push(Line::from(spans));
+        }
+        self.pending_gap = false;
+    }
+
+    fn quote_prefix(&self) -> Vec<Span<'static>> {
+        (0..self.quote_depth)
+            .map(|_| Span::styled("│ ", decoration_style()))
+            .collect()
+    }
+
+    /// Columns taken by list indentation on continuation lines
+    fn list_indent(&self) -> usize {
+        self.lists.iter().map(|level| level.marker_width).sum()
+    }
+
+    /// Columns left for text once the label and `indent` are accounted for
+    fn text_width(&self, indent: usize) -> usize {
+        if self.width == 0 {
+            return usize::MAX;
+        }
+        self.width
+            .saturating_sub(self.label.width() + indent)
+            .max(MIN_TEXT_WIDTH)
+    }
+
+    /// Wrap the pending inline content into lines. With `marker_only`, a list
+    /// item that has no text of its own yet still gets its marker shown.
+    fn flush(&mut self, marker_only: bool) {
+        if self.inline.is_empty() && !(marker_only && self.item_marker.is_some()) {
+            return;
+        }
+        self.gap();
+
+        let quote = self.quote_prefix();
+        let quote_width = 2 * self.quote_depth;
+        let list_indent = self.list_indent();
+        let first_indent = match self.item_marker.take() {
+            Some(marker) => vec![
+                Span::raw(" ".repeat(list_indent.saturating_sub(marker.width()))),
+                Span::styled(marker, Style::default().add_modifier(Modifier::BOLD)),
+            ],
+            None => vec![Span::raw(" ".repeat(list_indent))],
+        };
+
+        let inline = std::mem::take(&mut self.inline);
+        let rows = wrap_spans(&inline, self.text_width(quote_width + list_indent));
+        for (i, row) in rows.into_iter().enumerate() {
+            let mut spans = Vec::new();
+            if i == 0 {
+                spans.push(self.label.clone());
+                spans.extend(quote.iter().cloned());
+                spans.extend(first_indent.iter().cloned());
+            } else {
+                spans.push(Span::raw(" ".repeat(self.label.width())));
+                spans.extend(quote.iter().cloned());
+                spans.push(Span::raw(" ".repeat(list_indent)));
+            }
+            spans.extend(row);
+            self.lines.push(Line::from(spans));
+        }
+    }
+
+    /// Label (or padding the same width), quote bars and list indentation
+    fn line_prefix(&self, first: bool) -> Vec<Span<'static>> {
+        let mut spans = vec![if first {
+            self.label.clone()
+        } else {
+            Span::raw(" ".repeat(self.label.width()))
+        }];
+        spans.extend(self.quote_prefix());
+        if !self.lists.is_empty() {
+            spans.push(Span::raw(" ".repeat(self.list_indent())));
+        }
+        spans
+    }
+
+    /// Draw a table with box-drawing borders. When it's wider than the pane,
+    /// the widest columns give way first and their cells wrap.
+    fn push_table(&mut self, table: Table) {
+        self.gap();
+
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0).max(table.alignments.len());
+        if columns == 0 {
+            return;
+        }
+        let natural: Vec<usize> = (0..columns)
+            .map(|column| {
+                table.rows.iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|cell| cell.iter().map(Span::width).sum::<usize>())
+                    .max()
+                    .unwrap_or(0)
+                    .max(1)
+            })
+            .collect();
+        // Each column adds "│ " plus a trailing space, and the row ends with "│"
+        let chrome = 3 * columns + 1;
+        let indent = 2 * self.quote_depth + self.list_indent();
+        let widths = fit_columns(&natural, self.text_width(indent).saturating_sub(chrome));
+
+        let set = self.border.to_ratatui_line_set();
+        let rule = |left: &str, join: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|w| set.horizontal.repeat(w + 2)).collect();
+            Span::raw(format!("{}{}{}", left, segments.join(join), right))
+        };
+
+        let mut rendered = vec![Line::from(rule(set.top_left, set.horizontal_down, set.top_right))];
+        for (index, row) in table.rows.iter().enumerate() {
+            let cells: Vec<Vec<Vec<Span<'static>>>> = widths.iter().enumerate()
+                .map(|(column, &width)| wrap_spans(row.get(column).map_or(&[][..], Vec::as_slice), width))
+                .collect();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(1);
+
+            for line in 0..height {
+                let mut spans = vec![Span::raw(set.vertical)];
+                for (column, &width) in widths.iter().enumerate() {
+                    let content = cells[column].get(line).cloned().unwrap_or_default();
+                    let padding = width.saturating_sub(content.iter().map(Span::width).sum());
+                    let (before, after) = match table.alignments.get(column) {
+                        Some(Alignment::Right) => (padding, 0),
+                        Some(Alignment::Center) => (padding / 2, padding - padding / 2),
+                        _ => (0, padding),
+                    };
+                    spans.push(Span::raw(format!(" {}", " ".repeat(before))));
+                    spans.extend(content);
+                    spans.push(Span::raw(format!("{} {}", " ".repeat(after), set.vertical)));
+                }
+                rendered.push(Line::from(spans));
+            }
+
+            if index + 1 == table.header_rows && table.rows.len() > table.header_rows {
+                rendered.push(Line::from(rule(set.vertical_right, set.cross, set.vertical_left)));
+            }
+        }
+        rendered.push(Line::from(rule(set.bottom_left, set.horizontal_up, set.bottom_right)));
+
+        for (i, line) in rendered.into_iter().enumerate() {
+            let mut spans = self.line_prefix(i == 0);
+            spans.extend(line.spans);
+            self.lines.push(Line::from(spans));
+        }
+    }
+
+    fn push_code_block(&mut self, language: &str, code: &str) {
+        self.gap();
+        // Same default as before Markdown rendering: unlabelled fences are Rust
+        let language = if language.is_empty() { "rust" } else { language };
+        let indent = self.list_indent();
+        for line in highlight_code_block(code, language) {
+            let mut spans = Vec::new();
+            if indent > 0 || self.quote_depth > 0 {
+                spans.push(Span::raw(" ".repeat(self.label.width())));
+                spans.extend(self.quote_prefix());
+                spans.push(Span::raw(" ".repeat(indent)));
+            }
+            spans.extend(line.spans);
+            self.lines.push(Line::from(spans));
+        }
+    }
+}
+
+/// Shrink column widths until they fit in `budget`, taking from the widest
+/// columns first so narrow ones stay readable
+fn fit_columns(natural: &[usize], budget: usize) -> Vec<usize> {
+    let mut widths = natural.to_vec();
+    while widths.iter().sum::<usize>() > budget {
+        let widest = widths.iter().copied().max().unwrap_or(0);
+        if widest <= MIN_COLUMN_WIDTH {
+            // Can't shrink any further; the pane will have to wrap it
+            break;
+        }
+        for width in widths.iter_mut().filter(|w| **w == widest) {
+            *width -= 1;
+        }
+    }
+    widths
+}
+
+/// Greedy word wrap of styled spans to `width` columns. Words longer than a
+/// line are split; whitespace at the start of a wrapped line is dropped.
+fn wrap_spans(spans: &[Span<'static>], width: usize) -> Vec<Vec<Span<'static>>> {
+    let mut rows: Vec<Vec<Span<'static>>> = vec![Vec::new()];
+    let mut row_width = 0;
+    let mut pending_space: Option<Span<'static>> = None;
+
+    for span in spans {
+        for (piece, is_space) in split_words(&span.content) {
+            if is_space {
+                if row_width > 0 {
+                    pending_space = Some(Span::styled(piece.to_string(), span.style));
+                }
+                continue;
+            }
+
+            let space_width = pending_space.as_ref().map_or(0, |s| s.width());
+            let piece_width = piece.width();
+            if row_width > 0 && row_width + space_width + piece_width > width {
+                rows.push(Vec::new());
+                row_width = 0;
+                pending_space = None;
+            }
+            if let Some(space) = pending_space.take() {
+                row_width += space.width();
+                rows.last_mut().unwrap().push(space);
+            }
+
+            if piece_width <= width.saturating_sub(row_width) {
+                rows.last_mut().unwrap().push(Span::styled(piece.to_string(), span.style));
+                row_width += piece_width;
+                continue;
+            }
+
+            // Too long for any line: hard-break it
+            let mut chunk = String::new();
+            for ch in piece.chars() {
+                let ch_width = ch.width().unwrap_or(0);
+                if row_width + ch_width > width && row_width > 0 {
+                    if !chunk.is_empty() {
+                        rows.last_mut().unwrap().push(Span::styled(std::mem::take(&mut chunk), span.style));
+                    }
+                    rows.push(Vec::new());
+                    row_width = 0;
+                }
+                chunk.push(ch);
+                row_width += ch_width;
+            }
+            if !chunk.is_empty() {
+                rows.last_mut().unwrap().push(Span::styled(chunk, span.style));
+            }
+        }
+    }
+
+    rows
+}
+
+/// Split text into alternating runs of whitespace and non-whitespace
+fn split_words(text: &str) -> Vec<(&str, bool)> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+    for (i, ch) in text.char_indices() {
+        let is_space = ch.is_whitespace();
+        if in_space != Some(is_space) {
+            if let Some(was_space) = in_space {
+                pieces.push((&text[start..i], was_space));
+            }
+            start = i;
+            in_space = Some(is_space);
+        }
+    }
+    if let Some(was_space) = in_space {
+        pieces.push((&text[start..], was_space));
+    }
+    pieces
+}
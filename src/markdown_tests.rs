@@ -0,0 +1,237 @@
+//! Unit tests for Markdown rendering
+//! Tests block and inline formatting and how wrapped lines are indented
+
+use ratatui::style::Modifier;
+use ratatui::text::{Line, Span};
+
+use crate::config::BorderStyle;
+use crate::markdown::render_markdown;
+
+const LABEL: &str = "[assistant]: ";
+
+fn render(content: &str, width: usize) -> Vec<Line<'static>> {
+    render_markdown(content, Span::raw(LABEL), width, BorderStyle::Rounded)
+}
+
+/// Plain text of each rendered line
+fn text(lines: &[Line]) -> Vec<String> {
+    lines.iter()
+        .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect()
+}
+
+/// The first span on any line whose content is exactly `content`
+fn span<'a>(lines: &'a [Line], content: &str) -> &'a Span<'a> {
+    lines.iter()
+        .flat_map(|line| line.spans.iter())
+        .find(|s| s.content == content)
+        .unwrap_or_else(|| panic!("No span {:?} in {:?}", content, text(lines)))
+}
+
+#[cfg(test)]
+mod block_tests {
+    use super::*;
+
+    #[test]
+    fn test_paragraphs_are_separated_by_a_blank_line() {
+        let lines = render("First paragraph.\n\nSecond paragraph.", 80);
+        assert_eq!(text(&lines), vec![
+            "[assistant]: First paragraph.",
+            "",
+            "[assistant]: Second paragraph.",
+        ]);
+    }
+
+    #[test]
+    fn test_heading_markup_is_replaced_by_style() {
+        let lines = render("# Title\n\nBody", 80);
+        assert_eq!(text(&lines)[0], "[assistant]: Title");
+        assert!(span(&lines, "Title").style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn test_bullet_and_numbered_lists() {
+        let lines = render("- one\n- two\n\n1. first\n2. second", 80);
+        assert_eq!(text(&lines), vec![
+            "[assistant]: • one",
+            "[assistant]: • two",
+            "",
+            "[assistant]: 1. first",
+            "[assistant]: 2. second",
+        ]);
+    }
+
+    #[test]
+    fn test_numbered_list_keeps_its_start() {
+        let lines = render("3. third\n4. fourth", 80);
+        assert_eq!(text(&lines)[0], "[assistant]: 3. third");
+    }
+
+    #[test]
+    fn test_nested_list_is_indented() {
+        let lines = render("- outer\n  - inner", 80);
+        assert_eq!(text(&lines), vec![
+            "[assistant]: • outer",
+            "[assistant]:   • inner",
+        ]);
+    }
+
+    #[test]
+    fn test_block_quote_has_a_bar() {
+        let lines = render("> quoted text", 80);
+        assert_eq!(text(&lines), vec!["[assistant]: │ quoted text"]);
+    }
+
+    #[test]
+    fn test_code_block_is_highlighted_without_label() {
+        let lines = render("Look:\n\n```rust\nfn main() {}\n```", 80);
+        let rendered = text(&lines);
+        assert_eq!(rendered[0], "[assistant]: Look:");
+        assert!(rendered.iter().any(|l| l.starts_with("fn main() {}")), "{:?}", rendered);
+        assert!(!rendered.iter().any(|l| l.contains("```")));
+    }
+
+    #[test]
+    fn test_unterminated_fence_still_renders_code() {
+        // Replies stream in, so the closing fence may not have arrived yet
+        let lines = render("```python\nprint('hi')\n", 80);
+        assert!(text(&lines).iter().any(|l| l.starts_with("print('hi')")));
+    }
+}
+
+#[cfg(test)]
+mod inline_tests {
+    use super::*;
+
+    #[test]
+    fn test_emphasis_markup_is_replaced_by_style() {
+        let lines = render("Some **bold** and *italic* and ~~gone~~ text", 80);
+        assert_eq!(text(&lines), vec!["[assistant]: Some bold and italic and gone text"]);
+        assert!(span(&lines, "bold").style.add_modifier.contains(Modifier::BOLD));
+        assert!(span(&lines, "italic").style.add_modifier.contains(Modifier::ITALIC));
+        assert!(span(&lines, "gone").style.add_modifier.contains(Modifier::CROSSED_OUT));
+    }
+
+    #[test]
+    fn test_inline_code_is_highlighted() {
+        let lines = render("Call `foo()` now", 80);
+        assert_eq!(text(&lines), vec!["[assistant]: Call foo() now"]);
+        assert!(span(&lines, "foo()").style.bg.is_some());
+    }
+
+    #[test]
+    fn test_link_target_is_shown() {
+        let lines = render("See [the docs](https://docs.rs) and <https://crates.io>", 80);
+        assert_eq!(
+            text(&lines),
+            vec!["[assistant]: See the docs (https://docs.rs) and https://crates.io"]
+        );
+        assert!(span(&lines, "docs").style.add_modifier.contains(Modifier::UNDERLINED));
+    }
+}
+
+#[cfg(test)]
+mod wrap_tests {
+    use super::*;
+
+    #[test]
+    fn test_wrapped_lines_fit_the_width() {
+        let content = "word ".repeat(40);
+        let lines = render(&content, 40);
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| l.width() <= 40), "{:?}", text(&lines));
+    }
+
+    #[test]
+    fn test_continuation_lines_align_with_text() {
+        let lines = render("Lorem ipsum dolor sit amet consectetur adipiscing", 30);
+        let rendered = text(&lines);
+        assert!(rendered.len() > 1);
+        assert!(rendered[1].starts_with(&" ".repeat(LABEL.len())));
+        assert!(!rendered[1].trim_start().is_empty());
+    }
+
+    #[test]
+    fn test_list_indentation_survives_wrapping() {
+        let lines = render("- alpha beta gamma delta epsilon zeta eta theta", 30);
+        let rendered = text(&lines);
+        assert!(rendered.len() > 1, "{:?}", rendered);
+        assert!(rendered[0].starts_with("[assistant]: • alpha"));
+        // Continuation text starts under the item text, not under the bullet
+        let indent = LABEL.len() + 2;
+        for line in &rendered[1..] {
+            assert!(line.starts_with(&" ".repeat(indent)), "{:?}", rendered);
+            assert_ne!(line.chars().nth(indent), Some(' '), "{:?}", rendered);
+        }
+    }
+
+    #[test]
+    fn test_overlong_word_is_split() {
+        let lines = render(&"x".repeat(60), 30);
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|l| l.width() <= 30), "{:?}", text(&lines));
+    }
+
+    #[test]
+    fn test_zero_width_disables_wrapping() {
+        let content = "word ".repeat(40);
+        assert_eq!(render(&content, 0).len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod table_tests {
+    use super::*;
+
+    const TABLE: &str = "| Name | Qty |\n|:-----|----:|\n| apple | 3 |\n| kiwi | 12 |";
+
+    #[test]
+    fn test_table_is_drawn_with_borders_and_alignment() {
+        let lines = render(TABLE, 80);
+        assert_eq!(text(&lines), vec![
+            "[assistant]: ╭───────┬─────╮",
+            "             │ Name  │ Qty │",
+            "             ├───────┼─────┤",
+            "             │ apple │   3 │",
+            "             │ kiwi  │  12 │",
+            "             ╰───────┴─────╯",
+        ]);
+        assert!(span(&lines, "Name").style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn test_table_uses_the_border_style() {
+        let lines = render_markdown(TABLE, Span::raw(LABEL), 80, BorderStyle::Double);
+        let rendered = text(&lines);
+        assert!(rendered[0].ends_with("╔═══════╦═════╗"), "{:?}", rendered);
+        assert!(rendered[2].ends_with("╠═══════╬═════╣"), "{:?}", rendered);
+        assert!(rendered[5].ends_with("╚═══════╩═════╝"), "{:?}", rendered);
+    }
+
+    #[test]
+    fn test_centered_column() {
+        let lines = render("| x |\n|:-:|\n| a |\n| abcde |", 80);
+        assert!(text(&lines).iter().any(|l| l.ends_with("│   a   │")), "{:?}", text(&lines));
+    }
+
+    #[test]
+    fn test_narrow_pane_wraps_the_widest_column() {
+        let table = "| id | description |\n|----|-------------|\n| 1 | a rather long description of the first item |";
+        let lines = render(table, 40);
+        let rendered = text(&lines);
+        assert!(lines.iter().all(|l| l.width() <= 40), "{:?}", rendered);
+        // The short column keeps its width; the long cell spills onto more rows
+        assert!(rendered.iter().any(|l| l.contains("│ 1  │")), "{:?}", rendered);
+        assert!(rendered.len() > 6, "{:?}", rendered);
+        let joined = rendered.join(" ");
+        for word in ["rather", "long", "description", "first", "item"] {
+            assert!(joined.contains(word), "{} missing from {:?}", word, rendered);
+        }
+    }
+
+    #[test]
+    fn test_table_inline_markup_is_rendered() {
+        let lines = render("| a |\n|---|\n| `code` |", 80);
+        assert!(span(&lines, "code").style.bg.is_some());
+    }
+}
@@ -0,0 +1,110 @@
+// src/oneshot.rs
+//! Non-interactive mode: send a single prompt, print the reply to stdout and
+//! exit. Used by `claudecli -p "..."` and when a prompt is piped in on stdin.
+use std::io::{IsTerminal, Read, Write};
+use tokio::sync::mpsc;
+use crate::backend::ApiUpdate;
+use crate::client::ConversationClient;
+
+/// The reply was printed in full
+pub const EXIT_SUCCESS: i32 = 0;
+/// The request failed (after any retries)
+pub const EXIT_REQUEST_FAILED: i32 = 1;
+/// There was nothing to send
+pub const EXIT_NO_PROMPT: i32 = 2;
+
+/// Read stdin to the end if it's piped or redirected; None when it's a terminal
+pub fn read_piped_stdin() -> anyhow::Result<Option<String>> {
+    let mut stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Ok(None);
+    }
+    let mut input = String::new();
+    stdin.read_to_string(&mut input)?;
+    Ok(Some(input))
+}
+
+/// Whether the prompt should be read from stdin: whenever stdin is piped or
+/// redirected, wherever stdout goes. `--plain` reads its input line by line
+/// instead, so it never consumes stdin up front.
+pub fn wants_stdin(plain: bool, stdin_is_terminal: bool) -> bool {
+    !plain && !stdin_is_terminal
+}
+
+/// Combine the `-p` prompt with piped input. When both are given the piped
+/// text follows the prompt, so `git diff | claudecli -p "Review this"` works.
+/// `-p -` stands for the piped text alone. Returns None if there is nothing
+/// but whitespace to send.
+pub fn build_prompt(flag: Option<&str>, piped: Option<&str>) -> Option<String> {
+    let flag = flag.filter(|flag| *flag != "-");
+    let parts: Vec<&str> = [flag, piped]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("\n\n"))
+    }
+}
+
+/// Send `prompt`, writing the reply to `out` as it arrives and retry notices
+/// and errors to `err`. Returns the process exit code.
+pub async fn run<W: Write, E: Write>(
+    client: &mut ConversationClient,
+    prompt: &str,
+    out: &mut W,
+    err: &mut E,
+) -> i32 {
+    let (tx, mut rx) = mpsc::channel::<ApiUpdate>(64);
+
+    let request = async {
+        // Moved in so the channel closes as soon as the request finishes
+        let tx = tx;
+        client.send_message(prompt, Some(&tx)).await
+    };
+
+    let print = async {
+        let mut at_line_start = true;
+        while let Some(update) = rx.recv().await {
+            match update {
+                ApiUpdate::Delta(text) => {
+                    // A closed pipe (e.g. `| head`) isn't worth failing over
+                    write!(out, "{}", text).ok();
+                    out.flush().ok();
+                    if !text.is_empty() {
+                        at_line_start = text.ends_with('\n');
+                    }
+                }
+                ApiUpdate::Retrying { attempt, max_retries, delay } => {
+                    writeln!(
+                        err,
+                        "Retrying ({}/{}) in {}s…",
+                        attempt,
+                        max_retries,
+                        delay.as_secs_f32().ceil() as u64
+                    ).ok();
+                }
+                ApiUpdate::Done { .. } | ApiUpdate::Error(_) => {}
+            }
+        }
+        at_line_start
+    };
+
+    let (result, at_line_start) = tokio::join!(request, print);
+
+    if !at_line_start {
+        writeln!(out).ok();
+    }
+
+    match result {
+        Ok(_) => EXIT_SUCCESS,
+        Err(e) => {
+            writeln!(err, "Error: {}", e).ok();
+            EXIT_REQUEST_FAILED
+        }
+    }
+}
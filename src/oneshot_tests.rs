@@ -0,0 +1,118 @@
+//! Unit tests for one-shot mode
+//! Tests prompt assembly and printing a reply through a simulated backend
+
+use std::sync::Arc;
+use tokio::time::Duration;
+
+use crate::backend::SimulatedBackend;
+use crate::client::ConversationClient;
+use crate::oneshot::*;
+
+/// Client with a simulated backend that answers immediately
+fn simulated_client() -> ConversationClient {
+    ConversationClient::new("key".to_string(), "m".to_string(), 100, 0.5)
+        .with_backend(Arc::new(SimulatedBackend { delay: Duration::ZERO, word_delay: Duration::ZERO }))
+}
+
+#[cfg(test)]
+mod prompt_tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_only() {
+        assert_eq!(build_prompt(Some("What is Rust?"), None).as_deref(), Some("What is Rust?"));
+    }
+
+    #[test]
+    fn test_piped_only() {
+        assert_eq!(build_prompt(None, Some("question\n")).as_deref(), Some("question"));
+    }
+
+    #[test]
+    fn test_piped_text_follows_flag() {
+        assert_eq!(
+            build_prompt(Some("Review this"), Some("diff --git a b\n")).as_deref(),
+            Some("Review this\n\ndiff --git a b")
+        );
+    }
+
+    #[test]
+    fn test_dash_reads_piped_text_alone() {
+        assert_eq!(build_prompt(Some("-"), Some("question\n")).as_deref(), Some("question"));
+        assert_eq!(build_prompt(Some("-"), None), None);
+    }
+
+    /// Piped stdin is always read, except in plain mode
+    #[test]
+    fn test_wants_stdin() {
+        assert!(wants_stdin(false, false));
+        assert!(!wants_stdin(false, true));
+        assert!(!wants_stdin(true, false));
+    }
+
+    #[test]
+    fn test_piped_diff_with_prompt_to_terminal() {
+        // `git diff | claudecli -p "Review this"` printing to the terminal:
+        // stdout isn't consulted, so the diff still follows the prompt
+        assert!(wants_stdin(false, false));
+        let prompt = build_prompt(Some("Review this"), Some("+added line\n"));
+        assert_eq!(prompt.as_deref(), Some("Review this\n\n+added line"));
+    }
+
+    #[test]
+    fn test_blank_input_is_no_prompt() {
+        assert_eq!(build_prompt(None, None), None);
+        assert_eq!(build_prompt(Some("  "), Some("\n\n")), None);
+    }
+}
+
+#[cfg(test)]
+mod run_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reply_is_printed_with_trailing_newline() {
+        let mut client = simulated_client();
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+
+        let code = run(&mut client, "Hello there", &mut out, &mut err).await;
+
+        let printed = String::from_utf8(out).unwrap();
+        assert_eq!(code, EXIT_SUCCESS);
+        assert!(printed.contains("Hello there"));
+        assert!(printed.ends_with('\n'));
+        assert_eq!(printed.trim_end(), client.messages[1].content.trim_end());
+        assert!(err.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unbuffered_reply_matches_streamed_one() {
+        let mut client = simulated_client();
+        client.stream = false;
+        let mut out = Vec::new();
+
+        let code = run(&mut client, "Hello there", &mut out, &mut Vec::new()).await;
+
+        assert_eq!(code, EXIT_SUCCESS);
+        assert!(String::from_utf8(out).unwrap().contains("Hello there"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_reports_error() {
+        // Nothing listens on port 9 locally, and retries are off
+        let backend = crate::backend::AnthropicBackend::new("key".to_string())
+            .with_base_url("http://127.0.0.1:9")
+            .with_retry(crate::backend::RetryPolicy { max_retries: 0, ..Default::default() });
+        let mut client = simulated_client().with_backend(Arc::new(backend));
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+
+        let code = run(&mut client, "Hi", &mut out, &mut err).await;
+
+        assert_eq!(code, EXIT_REQUEST_FAILED);
+        assert!(out.is_empty());
+        assert!(String::from_utf8(err).unwrap().starts_with("Error: "));
+        assert!(client.messages.is_empty());
+    }
+}
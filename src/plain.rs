@@ -0,0 +1,304 @@
+// src/plain.rs
+//! Line-oriented REPL (`--plain`) for terminals where the full-screen TUI
+//! misbehaves. Replies are printed as they arrive, so the conversation stays in
+//! normal scrollback. Input goes through the same rustyline editor, slash
+//! commands and request path as the TUI.
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use anyhow::Result;
+use rustyline::error::ReadlineError;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+use crate::app::AppState;
+use crate::backend::{ApiUpdate, RequestUpdate};
+use crate::handlers::api::{handle_api_update, is_current};
+use crate::handlers::events::{regenerate_reply, send_message};
+use crate::handlers::file_ops::{load_conversation, save_conversation};
+use crate::export::{export_to_directory, ExportFormat};
+use crate::title::{collect_title, request_title};
+
+const PROMPT: &str = "[user]: ";
+
+const HELP: &str = "\
+Commands:
+  /save <file>     Save the conversation (relative to saves_dir if set, else the current directory)
+  /load [file]     Load a conversation, or list saved ones
+  /export <format> Export the conversation as md, html or txt
+  /retry           Ask for another answer to the last message
+  /system [text]   Show the system prompt, or set it (\"/system \" with no text clears it)
+  /model [name]    Show the model and presets, or switch to a preset or model
+  /help            Show this help
+  /quit            Exit (or press Ctrl+D)
+Ctrl+C cancels a reply that is still arriving.";
+
+/// What the REPL should do after a line has been handled as a command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommandOutcome {
+    /// The line isn't a command; send it as a message
+    NotACommand,
+    Handled,
+    /// Regenerate the last reply
+    Retry,
+    Quit,
+}
+
+/// Handle a slash command typed at the prompt, writing any output to `out`
+pub fn handle_command<W: Write>(app: &mut AppState, line: &str, out: &mut W) -> Result<CommandOutcome> {
+    let (command, argument) = match line.split_once(' ') {
+        Some((command, argument)) => (command, Some(argument.trim())),
+        None => (line, None),
+    };
+    // Trailing spaces don't turn "/quit " into a message; only /system cares
+    let bare = argument.is_none_or(str::is_empty);
+
+    match (command, argument) {
+        ("/quit" | "/exit", _) if bare => return Ok(CommandOutcome::Quit),
+        ("/help", _) if bare => writeln!(out, "{}", HELP)?,
+        ("/retry", _) if bare => return Ok(CommandOutcome::Retry),
+        ("/save", Some(name)) if !name.is_empty() => {
+            let filepath = app.saves_directory.join(name);
+            match save_conversation(&app.client, &filepath) {
+                Ok(_) => {
+                    writeln!(out, "Conversation saved to {}", filepath.display())?;
+                    app.autosave.mark_saved(&app.client).ok();
+                }
+                Err(e) => writeln!(out, "Save failed: {}", e)?,
+            }
+        }
+        ("/save", _) => writeln!(out, "Usage: /save <file>")?,
+        ("/load", Some(name)) if !name.is_empty() => {
+            let filepath = app.saves_directory.join(name);
+            match load_conversation(&filepath) {
+                Ok(conversation) => {
+                    app.apply_saved_conversation(conversation);
+                    writeln!(out, "Conversation loaded from {}", filepath.display())?;
+                    print_transcript(app, out)?;
+                }
+                Err(e) => writeln!(out, "Load failed: {}", e)?,
+            }
+        }
+        ("/load", _) => list_saved_conversations(&app.saves_directory, out)?,
+        ("/export", Some(name)) if ExportFormat::from_name(name).is_some() => {
+            let format = ExportFormat::from_name(name).unwrap_or(ExportFormat::Markdown);
+            match export_to_directory(&app.client, format, &app.saves_directory) {
+                Ok(path) => writeln!(out, "Exported to {}", path.display())?,
+                Err(e) => writeln!(out, "Export failed: {}", e)?,
+            }
+        }
+        ("/export", _) => writeln!(out, "Usage: /export md|html|txt")?,
+        ("/system", None) => match &app.client.system_prompt {
+            Some(prompt) => writeln!(out, "System prompt:\n{}", prompt)?,
+            None => writeln!(out, "No system prompt set")?,
+        },
+        ("/system", Some(prompt)) => {
+            app.client.system_prompt = if prompt.is_empty() {
+                writeln!(out, "System prompt cleared")?;
+                None
+            } else {
+                writeln!(out, "System prompt updated")?;
+                Some(prompt.to_string())
+            };
+        }
+        ("/model", _) if bare => {
+            writeln!(out, "Model: {}", app.model_label())?;
+            for (name, preset) in &app.presets {
+                writeln!(out, "  {} - {}", name, preset.model)?;
+            }
+        }
+        ("/model", Some(name)) => {
+            let status = app.switch_model(name);
+            writeln!(out, "{}", status)?;
+        }
+        ("/colors" | "/color" | "/profiles" | "/profile", _) if bare => {
+            writeln!(out, "Colors are not used in plain mode")?;
+        }
+        _ => return Ok(CommandOutcome::NotACommand),
+    }
+
+    Ok(CommandOutcome::Handled)
+}
+
+/// Print every message in the conversation
+pub fn print_transcript<W: Write>(app: &AppState, out: &mut W) -> Result<()> {
+    for message in &app.client.messages {
+        writeln!(out, "[{}]: {}\n", message.role, message.content.trim_end())?;
+    }
+    Ok(())
+}
+
+fn list_saved_conversations<W: Write>(dir: &std::path::Path, out: &mut W) -> Result<()> {
+    let mut files: Vec<String> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| {
+                    entry.path().extension().is_some_and(|ext| ext == "json" || ext == "jsonl" || ext == "md")
+                })
+                .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+
+    if files.is_empty() {
+        writeln!(out, "No saved conversations in {}", dir.display())?;
+    } else {
+        for file in files {
+            writeln!(out, "  {}", file)?;
+        }
+    }
+    writeln!(out, "Usage: /load <file>")?;
+    Ok(())
+}
+
+/// Run the REPL until the user quits. `interrupted` is set by the Ctrl+C
+/// handler and cancels the reply in progress.
+pub async fn run(app: &mut AppState, interrupted: Arc<AtomicBool>) -> Result<()> {
+    let (tx, mut rx) = mpsc::channel::<RequestUpdate>(64);
+    let mut out = std::io::stdout();
+
+    writeln!(
+        out,
+        "claudecli {} - model {}{}. Type /help for commands, Ctrl+D to quit.\n",
+        env!("CARGO_PKG_VERSION"),
+        app.client.model,
+        if app.simulate_mode { " (simulate mode)" } else { "" }
+    )?;
+
+    if let Some(conversation) = app.restore_candidate.take() {
+        let question = format!(
+            "Restore the unsaved session from {} ({} messages)? [y/N] ",
+            conversation.timestamp,
+            conversation.messages.len()
+        );
+        let answer = app.rl.readline(&question).unwrap_or_default();
+        if answer.trim().eq_ignore_ascii_case("y") {
+            app.apply_saved_conversation(conversation);
+            print_transcript(app, &mut out)?;
+        } else {
+            app.autosave.discard()?;
+        }
+    }
+
+    loop {
+        // A cancelled prompt comes back pre-filled, as it does in the TUI
+        let initial = std::mem::take(&mut app.input);
+        let line = match app.rl.readline_with_initial(PROMPT, (&initial, "")) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line = line.as_str();
+
+        let outcome = handle_command(app, line, &mut out)?;
+        match outcome {
+            CommandOutcome::Quit => break,
+            CommandOutcome::Handled => {
+                app.rl.add_history_entry(line).ok();
+                continue;
+            }
+            CommandOutcome::Retry => {
+                app.rl.add_history_entry(line).ok();
+            }
+            CommandOutcome::NotACommand => {}
+        }
+
+        interrupted.store(false, Ordering::SeqCst);
+
+        if outcome == CommandOutcome::Retry {
+            regenerate_reply(app, &tx);
+            if !app.waiting {
+                writeln!(out, "{}", app.status)?;
+                continue;
+            }
+        } else {
+            app.input = line.to_string();
+            app.cursor_position = app.input.chars().count();
+            send_message(app, &tx).await?;
+        }
+        receive_reply(app, &mut rx, &interrupted, &mut out).await?;
+        request_title(app);
+        collect_title(app).await;
+        if let Err(e) = app.autosave.save(&app.client) {
+            writeln!(out, "Autosave failed: {}", e)?;
+        }
+    }
+
+    collect_title(app).await;
+    app.autosave.save(&app.client)?;
+    Ok(())
+}
+
+/// Print the reply to the message just sent until it completes, fails or is cancelled
+pub async fn receive_reply<W: Write>(
+    app: &mut AppState,
+    rx: &mut mpsc::Receiver<RequestUpdate>,
+    interrupted: &AtomicBool,
+    out: &mut W,
+) -> Result<()> {
+    let mut started = false;
+
+    while app.waiting {
+        tokio::select! {
+            Some(update) = rx.recv() => {
+                // Left over from a request that was cancelled
+                if !is_current(app, &update) {
+                    continue;
+                }
+                let update = update.update;
+                match &update {
+                    ApiUpdate::Delta(text) => {
+                        if !started {
+                            write!(out, "[assistant]: ")?;
+                            started = true;
+                        }
+                        write!(out, "{}", text)?;
+                        out.flush()?;
+                    }
+                    ApiUpdate::Retrying { attempt, max_retries, delay } => {
+                        writeln!(
+                            out,
+                            "Retrying ({}/{}) in {}s…",
+                            attempt,
+                            max_retries,
+                            delay.as_secs_f32().ceil() as u64
+                        )?;
+                    }
+                    ApiUpdate::Done { .. } | ApiUpdate::Error(_) => {}
+                }
+                handle_api_update(app, update);
+
+                if app.show_error_dialog {
+                    app.show_error_dialog = false;
+                    if started {
+                        writeln!(out)?;
+                    }
+                    writeln!(out, "{}", app.error_message)?;
+                    started = false;
+                }
+            }
+            _ = tokio::time::sleep(Duration::from_millis(50)) => {
+                if interrupted.swap(false, Ordering::SeqCst) {
+                    app.cancel_request();
+                    if started {
+                        writeln!(out)?;
+                    }
+                    writeln!(out, "[{}]", app.status)?;
+                    started = false;
+                }
+            }
+        }
+    }
+
+    if started {
+        writeln!(out)?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
@@ -0,0 +1,224 @@
+//! Unit tests for plain (line-oriented) mode
+//! Tests slash command handling and printing replies without the TUI
+
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+
+use crate::api::Message;
+use crate::app::AppState;
+use crate::backend::SimulatedBackend;
+use crate::config::ColorConfig;
+use crate::plain::*;
+
+/// App in simulate mode whose backend answers immediately
+fn create_test_app() -> AppState {
+    let mut app = AppState::new(
+        "test_key".to_string(),
+        "test-model".to_string(),
+        1024,
+        0.7,
+        true,
+        ColorConfig::default(),
+    ).expect("Failed to create test app");
+    app.client.backend = Arc::new(SimulatedBackend { delay: Duration::ZERO, word_delay: Duration::ZERO });
+    app
+}
+
+/// Run a command and return its outcome and printed output
+fn command(app: &mut AppState, line: &str) -> (CommandOutcome, String) {
+    let mut out = Vec::new();
+    let outcome = handle_command(app, line, &mut out).expect("Command should not fail");
+    (outcome, String::from_utf8(out).unwrap())
+}
+
+#[cfg(test)]
+mod command_tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_are_not_commands() {
+        let mut app = create_test_app();
+        assert_eq!(command(&mut app, "Hello there").0, CommandOutcome::NotACommand);
+        assert_eq!(command(&mut app, "/unknown thing").0, CommandOutcome::NotACommand);
+    }
+
+    #[test]
+    fn test_quit_commands() {
+        let mut app = create_test_app();
+        assert_eq!(command(&mut app, "/quit").0, CommandOutcome::Quit);
+        assert_eq!(command(&mut app, "/exit ").0, CommandOutcome::Quit);
+    }
+
+    #[test]
+    fn test_retry_command() {
+        let mut app = create_test_app();
+        assert_eq!(command(&mut app, "/retry").0, CommandOutcome::Retry);
+        assert_eq!(command(&mut app, "/retry please").0, CommandOutcome::NotACommand);
+    }
+
+    #[test]
+    fn test_system_prompt_commands() {
+        let mut app = create_test_app();
+
+        let (_, output) = command(&mut app, "/system");
+        assert_eq!(output, "No system prompt set\n");
+
+        command(&mut app, "/system You are terse.");
+        assert_eq!(app.client.system_prompt.as_deref(), Some("You are terse."));
+        assert!(command(&mut app, "/system").1.contains("You are terse."));
+
+        let (outcome, output) = command(&mut app, "/system ");
+        assert_eq!(outcome, CommandOutcome::Handled);
+        assert_eq!(output, "System prompt cleared\n");
+        assert!(app.client.system_prompt.is_none());
+    }
+
+    #[test]
+    fn test_save_then_load_round_trip() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("chat.json");
+        let mut app = create_test_app();
+        app.client.messages.push(Message { role: "user".to_string(), content: "Hi".to_string(), ..Default::default() });
+        app.client.messages.push(Message { role: "assistant".to_string(), content: "Hello!".to_string(), ..Default::default() });
+
+        let (_, output) = command(&mut app, &format!("/save {}", path.display()));
+        assert!(output.starts_with("Conversation saved to"), "{}", output);
+
+        let mut other = create_test_app();
+        let (_, output) = command(&mut other, &format!("/load {}", path.display()));
+        assert_eq!(other.client.messages.len(), 2);
+        assert!(output.contains("[user]: Hi"));
+        assert!(output.contains("[assistant]: Hello!"));
+    }
+
+    #[test]
+    fn test_save_and_load_report_problems() {
+        let mut app = create_test_app();
+        assert_eq!(command(&mut app, "/save").1, "Usage: /save <file>\n");
+        assert!(command(&mut app, "/load /nonexistent/chat.json").1.starts_with("Load failed:"));
+    }
+
+    #[test]
+    fn test_export_needs_a_format() {
+        let mut app = create_test_app();
+        assert_eq!(command(&mut app, "/export").1, "Usage: /export md|html|txt\n");
+        assert_eq!(command(&mut app, "/export pdf").1, "Usage: /export md|html|txt\n");
+    }
+
+    #[test]
+    fn test_color_commands_are_explained() {
+        let mut app = create_test_app();
+        let (outcome, output) = command(&mut app, "/colors");
+        assert_eq!(outcome, CommandOutcome::Handled);
+        assert!(output.contains("not used in plain mode"));
+    }
+}
+
+#[cfg(test)]
+mod saves_directory_tests {
+    use super::*;
+
+    /// Relative names are saved to and listed from the project's saves directory
+    #[test]
+    fn test_relative_names_use_saves_directory() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let mut app = create_test_app();
+        app.saves_directory = temp_dir.path().to_path_buf();
+
+        let (_, output) = command(&mut app, "/load");
+        assert!(output.starts_with(&format!("No saved conversations in {}", temp_dir.path().display())), "{}", output);
+
+        app.client.messages.push(Message { role: "user".to_string(), content: "Hi".to_string(), ..Default::default() });
+        command(&mut app, "/save chat.json");
+        assert!(temp_dir.path().join("chat.json").exists());
+
+        let (_, output) = command(&mut app, "/load");
+        assert!(output.contains("  chat.json\n"), "{}", output);
+    }
+}
+
+#[cfg(test)]
+mod model_command_tests {
+    use super::*;
+    use crate::settings::ModelPreset;
+
+    fn preset(model: &str, max_tokens: u32, temperature: f32) -> ModelPreset {
+        ModelPreset { model: model.to_string(), max_tokens: Some(max_tokens), temperature: Some(temperature) }
+    }
+
+    #[test]
+    fn test_model_command_lists_presets() {
+        let mut app = create_test_app();
+        app.presets.insert("fast".to_string(), preset("haiku", 512, 0.2));
+
+        let (_, output) = command(&mut app, "/model");
+        assert_eq!(output, "Model: test-model\n  fast - haiku\n");
+    }
+
+    /// A preset brings its parameters; any other name is taken as a model id
+    #[test]
+    fn test_model_command_switches() {
+        let mut app = create_test_app();
+        app.presets.insert("deep".to_string(), preset("opus", 8192, 1.0));
+
+        command(&mut app, "/model deep");
+        assert_eq!(app.client.model, "opus");
+        assert_eq!(app.client.max_tokens, 8192);
+        assert_eq!(app.client.temperature, 1.0);
+        assert_eq!(app.model_label(), "opus (deep)");
+
+        let (_, output) = command(&mut app, "/model claude-other");
+        assert_eq!(output, "Switched to claude-other\n");
+        assert_eq!(app.client.model, "claude-other");
+        assert_eq!(app.client.max_tokens, 8192);
+        assert_eq!(app.active_preset, None);
+    }
+}
+
+#[cfg(test)]
+mod reply_tests {
+    use super::*;
+    use crate::handlers::events::send_message;
+    use rustyline::history::History;
+
+    #[tokio::test]
+    async fn test_reply_is_printed_and_recorded() {
+        let mut app = create_test_app();
+        let (tx, mut rx) = mpsc::channel(64);
+        let interrupted = AtomicBool::new(false);
+        let mut out = Vec::new();
+
+        app.input = "Hello plain mode".to_string();
+        send_message(&mut app, &tx).await.unwrap();
+        receive_reply(&mut app, &mut rx, &interrupted, &mut out).await.unwrap();
+
+        let printed = String::from_utf8(out).unwrap();
+        assert!(!app.waiting);
+        assert!(printed.starts_with("[assistant]: "));
+        assert!(printed.contains("Hello plain mode"));
+        assert_eq!(app.client.messages.len(), 2);
+        assert!(app.client.total_tokens() > 0);
+        assert_eq!(app.rl.history().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_cancels_reply() {
+        let mut app = create_test_app();
+        app.client.backend = Arc::new(SimulatedBackend { delay: Duration::from_secs(30), word_delay: Duration::ZERO });
+        let (tx, mut rx) = mpsc::channel(64);
+        let interrupted = AtomicBool::new(true);
+        let mut out = Vec::new();
+
+        app.input = "Never answered".to_string();
+        send_message(&mut app, &tx).await.unwrap();
+        receive_reply(&mut app, &mut rx, &interrupted, &mut out).await.unwrap();
+
+        assert!(!app.waiting);
+        assert!(app.client.messages.is_empty());
+        assert_eq!(app.input, "Never answered");
+        assert!(String::from_utf8(out).unwrap().contains("[Request cancelled]"));
+    }
+}
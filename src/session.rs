@@ -0,0 +1,194 @@
+// src/session.rs
+//! Autosave of the conversation in progress, so a crash, a dead terminal or
+//! Ctrl+C doesn't lose it. The session file sits next to config.json and is
+//! offered for restoring at the next start, unless the conversation was saved
+//! explicitly after its last change. Every write also updates the
+//! conversation's entry in the session library.
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use crate::client::ConversationClient;
+use crate::config::AUTOSAVE_INTERVAL_SECS;
+use crate::handlers::file_ops::{load_conversation, SavedConversation};
+use crate::library::SessionLibrary;
+
+pub fn get_session_path() -> PathBuf {
+    let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push("claudecli");
+    path.push("session.json");
+    path
+}
+
+/// Keeps the session file in step with the conversation
+pub struct Autosave {
+    /// None when autosave is off
+    path: Option<PathBuf>,
+    /// Fingerprint of the conversation when it was last written or saved
+    written: u64,
+    last_check: Instant,
+    /// Where finished conversations are kept, when the library is on
+    library: Option<SessionLibrary>,
+    /// The conversation's library entry, once it has one
+    entry: Option<String>,
+}
+
+impl Autosave {
+    pub fn disabled() -> Self {
+        Self {
+            path: None,
+            written: 0,
+            last_check: Instant::now(),
+            library: None,
+            entry: None,
+        }
+    }
+
+    /// Autosave to `path`. Nothing is written until `client` changes, so a
+    /// session waiting to be restored isn't replaced by an empty one.
+    pub fn new(path: PathBuf, client: &ConversationClient) -> Self {
+        Self {
+            path: Some(path),
+            written: fingerprint(client),
+            last_check: Instant::now(),
+            library: None,
+            entry: None,
+        }
+    }
+
+    /// Also keep the conversation in `library`
+    pub fn with_library(mut self, library: SessionLibrary) -> Self {
+        self.library = Some(library);
+        self
+    }
+
+    pub fn library(&self) -> Option<&SessionLibrary> {
+        self.library.as_ref()
+    }
+
+    /// The library entry the conversation is being saved to
+    pub fn entry(&self) -> Option<&str> {
+        self.entry.as_deref()
+    }
+
+    /// Carry on with a conversation that was just loaded, saving it to
+    /// library entry `entry` (or a new one) once it changes
+    pub fn resume(&mut self, client: &ConversationClient, entry: Option<String>) {
+        self.entry = entry;
+        self.written = fingerprint(client);
+    }
+
+    /// The conversation's library entry was deleted; a later change starts a new one
+    pub fn forget_entry(&mut self, id: &str) {
+        if self.entry.as_deref() == Some(id) {
+            self.entry = None;
+        }
+    }
+
+    /// Write the session file if the conversation changed since it was last
+    /// written. A conversation that became empty removes the file instead.
+    pub fn save(&mut self, client: &ConversationClient) -> Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let current = fingerprint(client);
+        if current == self.written {
+            return Ok(());
+        }
+
+        if client.messages.is_empty() && client.tree.nodes().is_empty() {
+            remove_session(&path)?;
+            // The next conversation gets an entry of its own
+            self.entry = None;
+        } else {
+            self.save_to_library(client)?;
+            let mut conversation = SavedConversation::new(client);
+            conversation.library_id = self.entry.clone();
+            write_session(&conversation, &path)?;
+        }
+        self.written = current;
+        Ok(())
+    }
+
+    fn save_to_library(&mut self, client: &ConversationClient) -> Result<()> {
+        if let Some(library) = &self.library {
+            self.entry = Some(library.save(client, self.entry.as_deref())?);
+        }
+        Ok(())
+    }
+
+    /// Save if `AUTOSAVE_INTERVAL_SECS` have passed since the last check
+    pub fn tick(&mut self, client: &ConversationClient) -> Result<()> {
+        if self.last_check.elapsed() < Duration::from_secs(AUTOSAVE_INTERVAL_SECS) {
+            return Ok(());
+        }
+        self.last_check = Instant::now();
+        self.save(client)
+    }
+
+    /// Throw away the session left by the last run, which the user chose not to restore
+    pub fn discard(&mut self) -> Result<()> {
+        match &self.path {
+            Some(path) => remove_session(path),
+            None => Ok(()),
+        }
+    }
+
+    /// The conversation was saved to a file of the user's choosing, so there's
+    /// nothing to recover until it changes again. It stays in the library.
+    pub fn mark_saved(&mut self, client: &ConversationClient) -> Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        remove_session(&path)?;
+        if !client.messages.is_empty() {
+            self.save_to_library(client)?;
+        }
+        self.written = fingerprint(client);
+        Ok(())
+    }
+}
+
+/// The session left behind by the last run, if it has anything in it
+pub fn load_session(path: &Path) -> Option<SavedConversation> {
+    load_conversation(&path.to_path_buf())
+        .ok()
+        .filter(|conversation| !conversation.messages.is_empty())
+}
+
+fn remove_session(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Write through a temporary file so a crash mid-write can't leave a truncated session
+pub fn write_session(conversation: &SavedConversation, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(conversation)?;
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, json)?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Changes whenever anything worth saving does
+fn fingerprint(client: &ConversationClient) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    client.system_prompt.hash(&mut hasher);
+    client.title.hash(&mut hasher);
+    client.total_input_tokens.hash(&mut hasher);
+    client.total_output_tokens.hash(&mut hasher);
+    client.tree.nodes().len().hash(&mut hasher);
+    for message in &client.messages {
+        message.id.hash(&mut hasher);
+        message.role.hash(&mut hasher);
+        message.content.hash(&mut hasher);
+    }
+    hasher.finish()
+}
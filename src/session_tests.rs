@@ -0,0 +1,167 @@
+//! Unit tests for session autosave
+//! Tests when the session file is written, removed and offered for restoring
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use tempfile::TempDir;
+
+use crate::app::AppState;
+use crate::client::ConversationClient;
+use crate::config::get_default_colors;
+use crate::session::{load_session, Autosave};
+use crate::test_support::{message, press};
+
+fn create_test_client() -> ConversationClient {
+    ConversationClient::new("key".to_string(), "test_model".to_string(), 100, 0.7)
+}
+
+#[cfg(test)]
+mod autosave_tests {
+    use super::*;
+
+    /// Nothing is written until the conversation changes
+    #[test]
+    fn test_save_writes_only_changes() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("claudecli").join("session.json");
+        let mut client = create_test_client();
+        let mut autosave = Autosave::new(path.clone(), &client);
+
+        autosave.save(&client).unwrap();
+        assert!(!path.exists());
+
+        client.messages.push(message("user", "Hi"));
+        autosave.save(&client).unwrap();
+        let restored = load_session(&path).expect("Session should load");
+        assert_eq!(restored.messages[0].content, "Hi");
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    /// Clearing the conversation removes the session rather than saving an empty one
+    #[test]
+    fn test_empty_conversation_removes_session() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("session.json");
+        let mut client = create_test_client();
+        let mut autosave = Autosave::new(path.clone(), &client);
+        client.messages.push(message("user", "Hi"));
+        autosave.save(&client).unwrap();
+
+        client.messages.clear();
+        autosave.save(&client).unwrap();
+
+        assert!(!path.exists());
+    }
+
+    /// An explicit save leaves nothing to recover until the next change
+    #[test]
+    fn test_mark_saved_removes_session() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("session.json");
+        let mut client = create_test_client();
+        let mut autosave = Autosave::new(path.clone(), &client);
+        client.messages.push(message("user", "Hi"));
+        autosave.save(&client).unwrap();
+
+        autosave.mark_saved(&client).unwrap();
+        autosave.save(&client).unwrap();
+        assert!(!path.exists());
+
+        client.messages.push(message("assistant", "Hello!"));
+        autosave.save(&client).unwrap();
+        assert!(path.exists());
+    }
+
+    /// Periodic saves wait for the interval; a disabled autosave never writes
+    #[test]
+    fn test_tick_and_disabled() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("session.json");
+        let mut client = create_test_client();
+        let mut autosave = Autosave::new(path.clone(), &client);
+        client.messages.push(message("user", "Hi"));
+
+        autosave.tick(&client).unwrap();
+        assert!(!path.exists());
+
+        let mut disabled = Autosave::disabled();
+        disabled.save(&client).unwrap();
+        disabled.mark_saved(&client).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_missing_or_empty_session_is_not_offered() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("session.json");
+        assert!(load_session(&path).is_none());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(load_session(&path).is_none());
+    }
+}
+
+#[cfg(test)]
+mod restore_dialog_tests {
+    use super::*;
+
+    /// App autosaving into a temp dir, with a previous session waiting there
+    fn app_with_previous_session(temp_dir: &TempDir) -> AppState {
+        let path = temp_dir.path().join("session.json");
+        let mut previous = create_test_client();
+        previous.messages.push(message("user", "Where were we?"));
+        Autosave::new(path.clone(), &create_test_client()).save(&previous).unwrap();
+
+        let mut app = AppState::new(
+            "test_key".to_string(),
+            "test_model".to_string(),
+            1000,
+            0.7,
+            true,
+            get_default_colors(),
+        ).expect("Failed to create AppState");
+        app.restore_candidate = load_session(&path);
+        app.autosave = Autosave::new(path, &app.client);
+        app.show_restore_dialog = true;
+        app
+    }
+
+    #[tokio::test]
+    async fn test_restore_brings_back_conversation() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let mut app = app_with_previous_session(&temp_dir);
+
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+
+        assert!(!app.show_restore_dialog);
+        assert_eq!(app.client.messages.len(), 1);
+        assert_eq!(app.client.messages[0].content, "Where were we?");
+        assert!(temp_dir.path().join("session.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_discard_removes_session() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let mut app = app_with_previous_session(&temp_dir);
+
+        press(&mut app, KeyCode::Down, KeyModifiers::NONE).await;
+        assert_eq!(app.restore_selected, 1);
+        press(&mut app, KeyCode::Enter, KeyModifiers::NONE).await;
+
+        assert!(!app.show_restore_dialog);
+        assert!(app.client.messages.is_empty());
+        assert!(app.restore_candidate.is_none());
+        assert!(!temp_dir.path().join("session.json").exists());
+    }
+
+    /// Other keys wait for an answer instead of reaching the input box
+    #[tokio::test]
+    async fn test_dialog_holds_other_keys() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let mut app = app_with_previous_session(&temp_dir);
+
+        press(&mut app, KeyCode::Char('x'), KeyModifiers::NONE).await;
+
+        assert!(app.show_restore_dialog);
+        assert!(app.input.is_empty());
+    }
+}
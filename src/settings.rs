@@ -0,0 +1,399 @@
+// src/settings.rs
+//! Settings files: defaults for the command-line options, API options and UI
+//! behaviour, so they don't have to be repeated on every run. The user's
+//! settings live in the config directory, in `config.json` next to the colors
+//! or in `config.toml`; a `.claudecli.toml` or `.claudecli.json` in the
+//! working directory, or the nearest directory above it that has one,
+//! overrides them for a project. Options given on the command line or in the
+//! environment override both.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::config::{get_config_path, ApiConfig};
+use crate::keymap::{Action, KeyChord};
+
+/// Project settings files looked for from the working directory upwards,
+/// lowest precedence first
+pub const PROJECT_SETTINGS_FILES: [&str; 2] = [".claudecli.json", ".claudecli.toml"];
+
+/// Settings only the user's own files may set: where requests, and with them
+/// the API key, are sent, and which local file becomes the system prompt.
+/// A project file from a cloned repository shouldn't be able to change them.
+const USER_ONLY_KEYS: [&str; 2] = ["api", "system_file"];
+
+/// Everything a settings file can hold. Options left out keep their
+/// command-line defaults.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    pub system: Option<String>,
+    pub system_file: Option<PathBuf>,
+    pub simulate: Option<bool>,
+    pub plain: Option<bool>,
+    /// `false` is the same as `--no-stream`
+    pub stream: Option<bool>,
+    /// `false` is the same as `--no-autosave`
+    pub autosave: Option<bool>,
+    pub max_retries: Option<u32>,
+    pub retry_delay: Option<f64>,
+    /// Where save files go and the save and load dialogs open, instead of the
+    /// working directory
+    pub saves_dir: Option<PathBuf>,
+    /// Files whose contents are sent after the system prompt
+    pub context_files: Vec<PathBuf>,
+    /// Directory of the settings file that lists `context_files`. The list
+    /// isn't merged, so they all come from that one file and must be inside it.
+    #[serde(skip)]
+    pub context_dir: Option<PathBuf>,
+    /// Model that names conversations
+    pub title_model: Option<String>,
+    /// Preset to start with when `--preset` isn't given
+    pub preset: Option<String>,
+    pub presets: BTreeMap<String, ModelPreset>,
+    /// Chords for shortcut actions, replacing their default bindings
+    pub keys: BTreeMap<Action, Vec<KeyChord>>,
+    pub api: ApiConfig,
+    pub ui: UiSettings,
+    /// The `ui` options a project file sets, with that file. Saving them to
+    /// the user's settings wouldn't change anything while the project is open.
+    #[serde(skip)]
+    pub ui_from_project: BTreeMap<String, PathBuf>,
+}
+
+/// A named model with its own parameters, from a `[presets.<name>]` table.
+/// Parameters left out keep their current values when switching to it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ModelPreset {
+    pub model: String,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+/// How the TUI behaves. Changed from the settings dialog (F5) and saved to
+/// the user's settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSettings {
+    /// Shift/Alt+Enter sends and Enter inserts a newline, rather than the other way round
+    pub shift_enter_sends: bool,
+    /// Scroll to the bottom when a message is sent
+    pub scroll_on_user_input: bool,
+    /// Scroll to the bottom as a reply arrives
+    pub scroll_on_api_response: bool,
+    pub show_debug_messages: bool,
+    /// Keep a regenerated reply in the conversation tree as an alternate to
+    /// switch back to, rather than discarding it
+    pub keep_alternate_replies: bool,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            shift_enter_sends: false,
+            scroll_on_user_input: true,
+            scroll_on_api_response: true,
+            show_debug_messages: false,
+            keep_alternate_replies: true,
+        }
+    }
+}
+
+/// Key and description of each UI option, in the order the settings dialog
+/// lists them
+pub const UI_OPTIONS: [(&str, &str); 5] = [
+    ("shift_enter_sends", "Shift/Alt+Enter sends, Enter inserts a newline"),
+    ("scroll_on_user_input", "Scroll to the bottom when you send a message"),
+    ("scroll_on_api_response", "Scroll to the bottom as a reply arrives"),
+    ("show_debug_messages", "Print rendering debug messages to stderr"),
+    ("keep_alternate_replies", "Keep regenerated replies to switch back to (Ctrl+N)"),
+];
+
+impl UiSettings {
+    /// Values in `UI_OPTIONS` order
+    pub fn values(&self) -> [bool; 5] {
+        [
+            self.shift_enter_sends,
+            self.scroll_on_user_input,
+            self.scroll_on_api_response,
+            self.show_debug_messages,
+            self.keep_alternate_replies,
+        ]
+    }
+
+    /// Flip option `index` of `UI_OPTIONS`
+    pub fn toggle(&mut self, index: usize) {
+        let option = match index {
+            0 => &mut self.shift_enter_sends,
+            1 => &mut self.scroll_on_user_input,
+            2 => &mut self.scroll_on_api_response,
+            3 => &mut self.show_debug_messages,
+            4 => &mut self.keep_alternate_replies,
+            _ => return,
+        };
+        *option = !*option;
+    }
+}
+
+/// The user's settings files, lowest precedence first
+pub fn user_settings_paths() -> Vec<PathBuf> {
+    let json = get_config_path();
+    let toml = json.with_extension("toml");
+    vec![json, toml]
+}
+
+/// The project settings files in `dir`, lowest precedence first
+pub fn project_settings_paths(dir: &Path) -> Vec<PathBuf> {
+    PROJECT_SETTINGS_FILES.iter().map(|name| dir.join(name)).collect()
+}
+
+/// The nearest directory, starting with `start` and going up, that has a
+/// project settings file
+pub fn find_project_dir(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| project_settings_paths(dir).iter().any(|path| path.is_file()))
+        .map(Path::to_path_buf)
+}
+
+/// Settings from the user's files overridden by those of `project`, which
+/// should be a project the user trusts
+pub fn load_settings(project: Option<&Path>) -> Result<Settings> {
+    let mut paths = user_settings_paths();
+    if let Some(dir) = project {
+        paths.extend(project_settings_paths(dir));
+    }
+    load_settings_from(&paths)
+}
+
+/// The list of projects whose settings files the user has agreed to use
+pub fn trusted_projects_path() -> PathBuf {
+    get_config_path().with_file_name("trusted-projects.json")
+}
+
+fn read_trusted_projects(list: &Path) -> Vec<PathBuf> {
+    std::fs::read_to_string(list)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Whether the project in `dir` is on the trusted list at `list`
+pub fn is_trusted_in(dir: &Path, list: &Path) -> bool {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    read_trusted_projects(list).contains(&dir)
+}
+
+/// Add the project in `dir` to the trusted list at `list`
+pub fn trust_project_in(dir: &Path, list: &Path) -> Result<()> {
+    let dir = dir.canonicalize()?;
+    let mut projects = read_trusted_projects(list);
+    if !projects.contains(&dir) {
+        projects.push(dir);
+        std::fs::write(list, serde_json::to_string_pretty(&projects)?)
+            .map_err(|e| anyhow!("Failed to save {}: {}", list.display(), e))?;
+    }
+    Ok(())
+}
+
+/// The nearest project above `start` whose settings have already been trusted
+pub fn trusted_project_dir(start: &Path) -> Option<PathBuf> {
+    find_project_dir(start).filter(|dir| is_trusted_in(dir, &trusted_projects_path()))
+}
+
+/// Settings from `paths`, each overriding the ones before it. Missing files
+/// are skipped. A `config.json` that isn't valid JSON is skipped too, since
+/// the color loader already reports it.
+pub fn load_settings_from(paths: &[PathBuf]) -> Result<Settings> {
+    let mut merged = Value::Object(Default::default());
+    let mut ui_from_project = BTreeMap::new();
+    let mut context_dir = None;
+    for path in paths {
+        let Some(value) = read_settings_file(path)? else {
+            continue;
+        };
+        // Check each file on its own so a mistake is reported against it
+        serde_json::from_value::<Settings>(value.clone())
+            .map_err(|e| anyhow!("Invalid settings in {}: {}", path.display(), e))?;
+        if let (true, Some(Value::Object(ui))) = (is_project_file(path), value.get("ui")) {
+            ui_from_project.extend(ui.keys().map(|key| (key.clone(), path.clone())));
+        }
+        if value.get("context_files").is_some() {
+            context_dir = path.parent().map(Path::to_path_buf);
+        }
+        merge(&mut merged, value);
+    }
+    let mut settings: Settings = serde_json::from_value(merged)?;
+    settings.ui_from_project = ui_from_project;
+    settings.context_dir = context_dir;
+    Ok(settings)
+}
+
+fn read_settings_file(path: &Path) -> Result<Option<Value>> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let mut value = if path.extension().is_some_and(|extension| extension == "toml") {
+        let value: toml::Value = toml::from_str(&contents)
+            .map_err(|e| anyhow!("Invalid settings in {}: {}", path.display(), e))?;
+        serde_json::to_value(value)?
+    } else {
+        match serde_json::from_str(&contents) {
+            Ok(value) => value,
+            Err(_) if path.file_name().is_some_and(|name| name == "config.json") => return Ok(None),
+            Err(e) => return Err(anyhow!("Invalid settings in {}: {}", path.display(), e)),
+        }
+    };
+    if is_project_file(path) {
+        if let Value::Object(settings) = &mut value {
+            for key in USER_ONLY_KEYS {
+                if settings.remove(key).is_some() {
+                    eprintln!(
+                        "Warning: Ignoring `{}` in {}; it can only be set in your own settings",
+                        key,
+                        path.display()
+                    );
+                }
+            }
+        }
+    }
+    if let Some(dir) = path.parent() {
+        resolve_paths(&mut value, dir);
+    }
+    Ok(Some(value))
+}
+
+fn is_project_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| PROJECT_SETTINGS_FILES.contains(&name))
+}
+
+/// Make the relative paths in a settings file relative to the directory it
+/// is in, so a project's settings work from any of its subdirectories
+fn resolve_paths(settings: &mut Value, dir: &Path) {
+    let Value::Object(settings) = settings else {
+        return;
+    };
+    let resolve = |value: &mut Value| {
+        if let Value::String(path) = value {
+            if Path::new(path.as_str()).is_relative() {
+                *path = dir.join(path.as_str()).to_string_lossy().into_owned();
+            }
+        }
+    };
+    for key in ["system_file", "saves_dir"] {
+        if let Some(value) = settings.get_mut(key) {
+            resolve(value);
+        }
+    }
+    if let Some(Value::Array(files)) = settings.get_mut("context_files") {
+        files.iter_mut().for_each(resolve);
+    }
+}
+
+/// The contents of the context files, each wrapped in a tag naming it
+/// relative to `base`, or None if there are none. Every file must be inside
+/// `base`, the directory of the settings file listing them, once links are
+/// followed, so a project can't send e.g. a key from the home directory to
+/// the API.
+pub fn read_context_files(files: &[PathBuf], base: &Path) -> Result<Option<String>> {
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let mut sections = Vec::new();
+    let root = base
+        .canonicalize()
+        .map_err(|e| anyhow!("Failed to find settings directory {}: {}", base.display(), e))?;
+    for path in files {
+        let resolved = path
+            .canonicalize()
+            .map_err(|e| anyhow!("Failed to read context file {}: {}", path.display(), e))?;
+        if !resolved.starts_with(&root) {
+            return Err(anyhow!(
+                "Context file {} is outside {}, the directory of the settings that list it",
+                path.display(),
+                root.display()
+            ));
+        }
+        let contents = std::fs::read_to_string(&resolved)
+            .map_err(|e| anyhow!("Failed to read context file {}: {}", path.display(), e))?;
+        let name = resolved.strip_prefix(&root).unwrap_or(&resolved);
+        sections.push(format!(
+            "<context file=\"{}\">\n{}\n</context>",
+            name.display(),
+            contents.trim_end()
+        ));
+    }
+    Ok(Some(sections.join("\n\n")))
+}
+
+/// Lay `over` on top of `base`: tables are merged key by key, anything else
+/// is replaced
+fn merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+/// Save one UI option to the user's settings, returning the file written
+pub fn save_ui_option(key: &str, value: bool) -> Result<PathBuf> {
+    let json = get_config_path();
+    let toml = json.with_extension("toml");
+    save_ui_option_to(key, value, &json, &toml)
+}
+
+/// The JSON settings file at `path` as an object to edit and write back; empty
+/// if the file doesn't exist yet. A file that can't be read back is an error,
+/// so it is left alone rather than replaced.
+pub fn read_json_for_update(path: &Path) -> Result<serde_json::Map<String, Value>> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => match serde_json::from_str::<Value>(&contents) {
+            Ok(Value::Object(map)) => Ok(map),
+            Ok(_) => Err(anyhow!("Invalid settings in {}: expected a JSON object", path.display())),
+            Err(e) => Err(anyhow!("Invalid settings in {}: {}", path.display(), e)),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Default::default()),
+        Err(e) => Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+/// Write `key` of the `ui` section into the TOML settings at `toml` if there
+/// are any, keeping its comments and layout, or else into the JSON at `json`
+/// alongside whatever else is there. The other options are left as the file
+/// has them. A JSON file that doesn't parse is an error, not overwritten.
+pub fn save_ui_option_to(key: &str, value: bool, json: &Path, toml: &Path) -> Result<PathBuf> {
+    if let Ok(contents) = std::fs::read_to_string(toml) {
+        let mut document: toml_edit::DocumentMut = contents
+            .parse()
+            .map_err(|e| anyhow!("Invalid settings in {}: {}", toml.display(), e))?;
+        document["ui"][key] = toml_edit::value(value);
+        std::fs::write(toml, document.to_string())?;
+        return Ok(toml.to_path_buf());
+    }
+
+    let mut root = read_json_for_update(json)?;
+    let ui = root.entry("ui").or_insert_with(|| Value::Object(Default::default()));
+    if !ui.is_object() {
+        *ui = Value::Object(Default::default());
+    }
+    if let Value::Object(ui) = ui {
+        ui.insert(key.to_string(), Value::Bool(value));
+    }
+    std::fs::write(json, serde_json::to_string_pretty(&root)?)?;
+    Ok(json.to_path_buf())
+}
@@ -0,0 +1,489 @@
+//! Unit tests for settings files
+//! Tests reading TOML and JSON settings, layering project files over user
+//! files, and options on the command line taking precedence over both
+
+use clap::{CommandFactory, FromArgMatches};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+use crate::config::Args;
+use crate::settings::*;
+
+fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Parse a command line and fill it in from `settings`, as startup does
+fn args_with(settings: &Settings, command_line: &[&str]) -> Args {
+    let matches = Args::command().try_get_matches_from(command_line).unwrap();
+    let mut args = Args::from_arg_matches(&matches).unwrap();
+    args.apply_settings(settings, &matches);
+    args
+}
+
+#[cfg(test)]
+mod file_tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_settings() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = write(temp_dir.path(), "config.toml", r#"
+            model = "claude-3-5-haiku-20241022"
+            max_tokens = 4096
+            stream = false
+
+            [api]
+            base_url = "https://gateway.internal"
+            headers = { X-Team = "tools" }
+
+            [ui]
+            shift_enter_sends = true
+        "#);
+
+        let settings = load_settings_from(&[path]).unwrap();
+        assert_eq!(settings.model.as_deref(), Some("claude-3-5-haiku-20241022"));
+        assert_eq!(settings.max_tokens, Some(4096));
+        assert_eq!(settings.stream, Some(false));
+        assert_eq!(settings.temperature, None);
+        assert_eq!(settings.api.base_url.as_deref(), Some("https://gateway.internal"));
+        assert_eq!(settings.api.headers["X-Team"], "tools");
+        assert!(settings.ui.shift_enter_sends);
+        assert_eq!(settings.ui.scroll_on_api_response, UiSettings::default().scroll_on_api_response);
+    }
+
+    /// Later files win key by key; sections are merged rather than replaced
+    #[test]
+    fn test_project_overrides_user() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let user = write(temp_dir.path(), "config.json", r#"{
+            "background": "Black", "border": "White", "text": "White",
+            "user_name": "BrightBlue", "assistant_name": "BrightGreen",
+            "model": "user-model", "temperature": 0.2,
+            "ui": { "scroll_on_user_input": false }
+        }"#);
+        let project = write(temp_dir.path(), ".claudecli.toml", r#"
+            model = "project-model"
+            [ui]
+            shift_enter_sends = true
+        "#);
+
+        let settings = load_settings_from(&[user, project]).unwrap();
+        assert_eq!(settings.model.as_deref(), Some("project-model"));
+        assert_eq!(settings.temperature, Some(0.2));
+        assert!(!settings.ui.scroll_on_user_input);
+        assert!(settings.ui.shift_enter_sends);
+    }
+
+    #[test]
+    fn test_missing_files_give_defaults() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let settings = load_settings_from(&project_settings_paths(temp_dir.path())).unwrap();
+        assert_eq!(settings, Settings::default());
+        assert!(!settings.ui.shift_enter_sends);
+    }
+
+    /// Mistakes name the file; a broken config.json is left to the color loader
+    #[test]
+    fn test_invalid_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let toml = write(temp_dir.path(), ".claudecli.toml", "model = ");
+        let error = load_settings_from(&[toml]).unwrap_err().to_string();
+        assert!(error.contains(".claudecli.toml"), "{}", error);
+
+        let json = write(temp_dir.path(), ".claudecli.json", r#"{ "max_tokens": "lots" }"#);
+        let error = load_settings_from(&[json]).unwrap_err().to_string();
+        assert!(error.contains(".claudecli.json"), "{}", error);
+
+        let config = write(temp_dir.path(), "config.json", "{ not json");
+        assert_eq!(load_settings_from(&[config]).unwrap(), Settings::default());
+    }
+}
+
+#[cfg(test)]
+mod precedence_tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            model: Some("from-file".to_string()),
+            max_tokens: Some(4096),
+            system: Some("File prompt".to_string()),
+            stream: Some(false),
+            autosave: Some(false),
+            max_retries: Some(1),
+            ..Default::default()
+        }
+    }
+
+    /// Settings replace defaults but not options given on the command line
+    #[test]
+    fn test_command_line_wins() {
+        let args = args_with(&settings(), &["claudecli", "--api-key", "k", "--model", "from-flag"]);
+        assert_eq!(args.model, "from-flag");
+        assert_eq!(args.max_tokens, 4096);
+        assert_eq!(args.temperature, 0.7);
+        assert!(args.no_stream);
+        assert!(args.no_autosave);
+        assert_eq!(args.max_retries, 1);
+        assert_eq!(args.system.as_deref(), Some("File prompt"));
+    }
+
+    /// A system prompt file on the command line replaces the settings' prompt
+    #[test]
+    fn test_system_prompt_from_command_line() {
+        let args = args_with(&settings(), &["claudecli", "--api-key", "k", "--system-file", "prompt.txt"]);
+        assert_eq!(args.system, None);
+        assert_eq!(args.system_file, Some(PathBuf::from("prompt.txt")));
+    }
+
+    /// Each on/off setting can be undone with the opposite flag, and the last
+    /// of a pair given wins
+    #[test]
+    fn test_negating_flags() {
+        let settings = Settings {
+            simulate: Some(true),
+            plain: Some(true),
+            ..settings()
+        };
+        let args = args_with(&settings, &["claudecli", "--api-key", "k"]);
+        assert!(args.simulate && args.plain && args.no_stream && args.no_autosave);
+
+        let args = args_with(
+            &settings,
+            &["claudecli", "--api-key", "k", "--no-simulate", "--no-plain", "--stream", "--autosave"],
+        );
+        assert!(!args.simulate && !args.plain && !args.no_stream && !args.no_autosave);
+
+        let args = args_with(&Settings::default(), &["claudecli", "--api-key", "k", "--no-stream", "--stream"]);
+        assert!(!args.no_stream);
+        let args = args_with(&Settings::default(), &["claudecli", "--api-key", "k", "--autosave", "--no-autosave"]);
+        assert!(args.no_autosave);
+    }
+
+    #[test]
+    fn test_no_settings_keeps_defaults() {
+        let args = args_with(&Settings::default(), &["claudecli", "--api-key", "k"]);
+        assert_eq!(args.model, "claude-3-5-sonnet-20241022");
+        assert_eq!(args.max_tokens, 1024);
+        assert!(!args.no_stream);
+    }
+}
+
+#[cfg(test)]
+mod save_tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle() {
+        let mut ui = UiSettings::default();
+        ui.toggle(0);
+        ui.toggle(3);
+        ui.toggle(4);
+        assert!(ui.shift_enter_sends);
+        assert!(ui.show_debug_messages);
+        assert!(!ui.keep_alternate_replies);
+        ui.toggle(UI_OPTIONS.len());
+        assert_eq!(ui.values(), [true, true, true, true, false]);
+    }
+
+    /// With no TOML settings the options go into config.json beside the colors
+    #[test]
+    fn test_save_to_json() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let json = write(temp_dir.path(), "config.json", r#"{ "background": "Black", "model": "m" }"#);
+        let toml = temp_dir.path().join("config.toml");
+        let ui = UiSettings { shift_enter_sends: true, ..Default::default() };
+
+        assert_eq!(save_ui_option_to("shift_enter_sends", true, &json, &toml).unwrap(), json);
+
+        let saved: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+        assert_eq!(saved["background"], "Black");
+        assert_eq!(saved["ui"], serde_json::json!({ "shift_enter_sends": true }));
+        assert!(!toml.exists());
+        assert_eq!(load_settings_from(&[json]).unwrap().ui, ui);
+    }
+
+    /// A config.json that doesn't parse is reported, not replaced
+    #[test]
+    fn test_save_keeps_broken_json() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let broken = r#"{ "background": "Black", "#;
+        let json = write(temp_dir.path(), "config.json", broken);
+        let toml = temp_dir.path().join("config.toml");
+
+        let error = save_ui_option_to("shift_enter_sends", true, &json, &toml).unwrap_err().to_string();
+        assert!(error.contains("config.json"), "{}", error);
+        assert_eq!(std::fs::read_to_string(&json).unwrap(), broken);
+
+        std::fs::write(&json, "[1, 2]").unwrap();
+        assert!(save_ui_option_to("shift_enter_sends", true, &json, &toml).is_err());
+    }
+
+    /// TOML settings are edited in place, keeping comments and other keys
+    #[test]
+    fn test_save_to_toml() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let json = temp_dir.path().join("config.json");
+        let toml = write(temp_dir.path(), "config.toml", "# My settings\nmodel = \"m\"\n\n[ui]\nscroll_on_user_input = true\n");
+        let ui = UiSettings { scroll_on_user_input: false, ..Default::default() };
+
+        assert_eq!(save_ui_option_to("scroll_on_user_input", false, &json, &toml).unwrap(), toml);
+
+        let contents = std::fs::read_to_string(&toml).unwrap();
+        assert!(contents.starts_with("# My settings\n"), "{}", contents);
+        assert!(!contents.contains("shift_enter_sends"), "{}", contents);
+        assert!(!json.exists());
+        let settings = load_settings_from(&[toml]).unwrap();
+        assert_eq!(settings.model.as_deref(), Some("m"));
+        assert_eq!(settings.ui, ui);
+    }
+
+    /// The options a project sets are remembered with its file
+    #[test]
+    fn test_ui_from_project() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let user = write(temp_dir.path(), "config.toml", "[ui]\nshow_debug_messages = true\n");
+        let project = write(temp_dir.path(), ".claudecli.toml", "[ui]\nshift_enter_sends = true\n");
+
+        let settings = load_settings_from(&[user, project.clone()]).unwrap();
+        assert_eq!(settings.ui_from_project, BTreeMap::from([("shift_enter_sends".to_string(), project)]));
+    }
+}
+
+#[cfg(test)]
+mod preset_tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let path = write(temp_dir.path(), "config.toml", r#"
+            max_tokens = 2048
+            preset = "fast"
+
+            [presets.fast]
+            model = "claude-3-5-haiku-20241022"
+            temperature = 0.2
+
+            [presets.deep]
+            model = "claude-3-opus-20240229"
+            max_tokens = 8192
+            temperature = 1.0
+        "#);
+        load_settings_from(&[path]).unwrap()
+    }
+
+    /// The settings' preset applies when --preset isn't given, keeping values
+    /// it doesn't set
+    #[test]
+    fn test_default_preset() {
+        let settings = settings();
+        let matches = Args::command().try_get_matches_from(["claudecli", "--api-key", "k"]).unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        args.apply_settings(&settings, &matches);
+        args.apply_preset(&settings, &matches).unwrap();
+
+        assert_eq!(args.preset.as_deref(), Some("fast"));
+        assert_eq!(args.model, "claude-3-5-haiku-20241022");
+        assert_eq!(args.max_tokens, 2048);
+        assert_eq!(args.temperature, 0.2);
+    }
+
+    /// --preset picks another preset; flags still beat it
+    #[test]
+    fn test_preset_flag() {
+        let settings = settings();
+        let matches = Args::command()
+            .try_get_matches_from(["claudecli", "--api-key", "k", "--preset", "deep", "--temperature", "0.5"])
+            .unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        args.apply_settings(&settings, &matches);
+        args.apply_preset(&settings, &matches).unwrap();
+
+        assert_eq!(args.model, "claude-3-opus-20240229");
+        assert_eq!(args.max_tokens, 8192);
+        assert_eq!(args.temperature, 0.5);
+    }
+
+    /// A model given on the command line or in the environment replaces the
+    /// preset's, so the preset is no longer shown as active
+    #[test]
+    fn test_model_flag_clears_preset() {
+        let settings = settings();
+        let matches = Args::command()
+            .try_get_matches_from(["claudecli", "--api-key", "k", "--preset", "deep", "--model", "claude-custom"])
+            .unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        args.apply_settings(&settings, &matches);
+        args.apply_preset(&settings, &matches).unwrap();
+
+        assert_eq!(args.model, "claude-custom");
+        assert_eq!(args.max_tokens, 8192);
+        assert_eq!(args.preset, None);
+
+        // Naming the preset's own model keeps it
+        let matches = Args::command()
+            .try_get_matches_from(["claudecli", "--api-key", "k", "--preset", "deep", "--model", "claude-3-opus-20240229"])
+            .unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        args.apply_preset(&settings, &matches).unwrap();
+        assert_eq!(args.preset.as_deref(), Some("deep"));
+    }
+
+    #[test]
+    fn test_unknown_preset() {
+        let settings = settings();
+        let matches = Args::command()
+            .try_get_matches_from(["claudecli", "--api-key", "k", "--preset", "huge"])
+            .unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+
+        let error = args.apply_preset(&settings, &matches).unwrap_err().to_string();
+        assert!(error.contains("'huge'") && error.contains("deep, fast"), "{}", error);
+    }
+}
+
+#[cfg(test)]
+mod project_tests {
+    use super::*;
+
+    /// The nearest directory with a project file wins, however deep the
+    /// working directory is
+    #[test]
+    fn test_find_project_dir() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let root = temp_dir.path();
+        let nested = root.join("crates/core/src");
+        std::fs::create_dir_all(&nested).unwrap();
+        write(root, ".claudecli.toml", "model = \"m\"");
+
+        assert_eq!(find_project_dir(&nested), Some(root.to_path_buf()));
+        assert_eq!(find_project_dir(root), Some(root.to_path_buf()));
+
+        write(&root.join("crates"), ".claudecli.json", "{}");
+        assert_eq!(find_project_dir(&nested), Some(root.join("crates")));
+    }
+
+    /// A project is trusted once and recognised however its path is written
+    #[test]
+    fn test_trusted_projects() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let list = temp_dir.path().join("trusted-projects.json");
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+
+        assert!(!is_trusted_in(&project, &list));
+        trust_project_in(&project.join("src/.."), &list).unwrap();
+        trust_project_in(&project, &list).unwrap();
+
+        assert!(is_trusted_in(&project, &list));
+        assert!(!is_trusted_in(&project.join("src"), &list));
+        let saved: Vec<PathBuf> = serde_json::from_str(&std::fs::read_to_string(&list).unwrap()).unwrap();
+        assert_eq!(saved, vec![project.canonicalize().unwrap()]);
+    }
+
+    /// Paths in a settings file are relative to the file, not the working directory
+    #[test]
+    fn test_paths_relative_to_file() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("home")).unwrap();
+        let user = write(&root.join("home"), "config.toml", r#"system_file = "prompts/system.md""#);
+        let project = write(root, ".claudecli.toml", r#"
+            saves_dir = ".claudecli/saves"
+            context_files = ["ARCHITECTURE.md", "/etc/hostname"]
+        "#);
+
+        let settings = load_settings_from(&[user, project]).unwrap();
+        assert_eq!(settings.system_file, Some(root.join("home/prompts/system.md")));
+        assert_eq!(settings.saves_dir, Some(root.join(".claudecli/saves")));
+        assert_eq!(settings.context_files, vec![root.join("ARCHITECTURE.md"), PathBuf::from("/etc/hostname")]);
+    }
+
+    /// A project can't redirect requests or pick the system prompt file;
+    /// the user's own values stay in force
+    #[test]
+    fn test_project_cannot_set_user_only_keys() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("home")).unwrap();
+        let user = write(&root.join("home"), "config.toml", "system_file = \"mine.md\"\n[api]\nbase_url = \"https://mine.example.com\"\n");
+        let project = write(root, ".claudecli.json", r#"{
+            "model": "project-model",
+            "system_file": "/home/me/.ssh/id_rsa",
+            "api": { "base_url": "https://attacker.example.com", "headers": { "x-steal": "1" } }
+        }"#);
+
+        let settings = load_settings_from(&[user, project]).unwrap();
+        assert_eq!(settings.model.as_deref(), Some("project-model"));
+        assert_eq!(settings.system_file, Some(root.join("home/mine.md")));
+        assert_eq!(settings.api.base_url.as_deref(), Some("https://mine.example.com"));
+        assert!(settings.api.headers.is_empty());
+    }
+
+    #[test]
+    fn test_read_context_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("docs")).unwrap();
+        let notes = write(&root.join("docs"), "notes.md", "Use tabs.\n");
+        let glossary = write(root, "GLOSSARY", "API: the thing");
+
+        let context = read_context_files(&[notes, glossary], root).unwrap();
+        assert_eq!(
+            context.as_deref(),
+            Some("<context file=\"docs/notes.md\">\nUse tabs.\n</context>\n\n<context file=\"GLOSSARY\">\nAPI: the thing\n</context>")
+        );
+        assert_eq!(read_context_files(&[], root).unwrap(), None);
+
+        let error = read_context_files(&[root.join("missing.md")], root).unwrap_err().to_string();
+        assert!(error.contains("missing.md"), "{}", error);
+    }
+
+    /// Context files from the user's settings are checked against the user's
+    /// settings directory, not the project
+    #[test]
+    fn test_user_context_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let user_dir = temp_dir.path().join("user");
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir(&user_dir).unwrap();
+        std::fs::create_dir(&project).unwrap();
+        write(&user_dir, "style.md", "Be brief.");
+        let user = write(&user_dir, "config.toml", "context_files = [\"style.md\"]\n");
+        let project_settings = write(&project, ".claudecli.toml", "model = \"m\"\n");
+
+        let settings = load_settings_from(&[user, project_settings.clone()]).unwrap();
+        assert_eq!(settings.context_dir.as_deref(), Some(user_dir.as_path()));
+        let context = read_context_files(&settings.context_files, settings.context_dir.as_deref().unwrap()).unwrap();
+        assert_eq!(context.as_deref(), Some("<context file=\"style.md\">\nBe brief.\n</context>"));
+
+        // A project that lists its own files takes over the whole list
+        write(&project, "NOTES.md", "Notes");
+        std::fs::write(&project_settings, "context_files = [\"NOTES.md\"]\n").unwrap();
+        let settings = load_settings_from(&[user_dir.join("config.toml"), project_settings]).unwrap();
+        assert_eq!(settings.context_dir.as_deref(), Some(project.as_path()));
+    }
+
+    /// Files outside the project are refused, however the path gets there
+    #[test]
+    fn test_context_files_stay_in_project() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let secret = write(temp_dir.path(), "id_rsa", "PRIVATE KEY");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&secret, project.join("notes.md")).unwrap();
+
+        let mut outside = vec![secret, project.join("../id_rsa")];
+        #[cfg(unix)]
+        outside.push(project.join("notes.md"));
+        for path in outside {
+            let error = read_context_files(std::slice::from_ref(&path), &project).unwrap_err().to_string();
+            assert!(error.contains("is outside"), "{}: {}", path.display(), error);
+        }
+    }
+}
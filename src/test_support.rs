@@ -0,0 +1,32 @@
+//! Fixtures shared by the unit tests
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tokio::sync::mpsc;
+
+use crate::api::{Message, Usage};
+use crate::app::AppState;
+use crate::backend::RequestSender;
+use crate::handlers::events::handle_key_event;
+
+pub fn message(role: &str, content: &str) -> Message {
+    Message { role: role.to_string(), content: content.to_string(), ..Default::default() }
+}
+
+/// An assistant message with the tokens it cost
+pub fn reply(content: &str, input_tokens: u32, output_tokens: u32) -> Message {
+    Message {
+        usage: Some(Usage { input_tokens, output_tokens }),
+        ..message("assistant", content)
+    }
+}
+
+/// Handle a key press in an 80x24 terminal; any request it starts goes nowhere
+pub async fn press(app: &mut AppState, code: KeyCode, modifiers: KeyModifiers) {
+    let (tx, _rx) = mpsc::channel(64);
+    press_sending_to(app, &tx, code, modifiers).await;
+}
+
+/// Handle a key press in an 80x24 terminal, sending any request it starts to `tx`
+pub async fn press_sending_to(app: &mut AppState, tx: &RequestSender, code: KeyCode, modifiers: KeyModifiers) {
+    handle_key_event(app, KeyEvent::new(code, modifiers), tx, (80, 24)).await.unwrap();
+}
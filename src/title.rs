@@ -0,0 +1,147 @@
+// src/title.rs
+//! Conversation titles, named after the first exchange. A short side request
+//! asks the model for one while the user carries on; simulate mode, and a
+//! side request that fails, fall back to a title made from the first prompt.
+use tokio::task::JoinHandle;
+use crate::api::{ApiRequest, Message};
+use crate::app::AppState;
+use crate::client::ConversationClient;
+use crate::config::{TITLE_MAX_TOKENS, TITLE_MESSAGE_CHARS};
+
+const TITLE_INSTRUCTION: &str = "Write a title of at most six words for the conversation above. \
+    Reply with the title only, without quotes or a full stop.";
+
+/// Longest title kept, in words
+const TITLE_WORDS: usize = 8;
+
+/// A title being generated for the conversation that starts with `prompt`
+pub struct PendingTitle {
+    prompt: String,
+    handle: JoinHandle<String>,
+}
+
+/// Title made from the first prompt: its first few words, capitalized
+pub fn heuristic_title(messages: &[Message]) -> String {
+    let line = messages
+        .iter()
+        .find(|message| message.role == "user")
+        .and_then(|message| message.content.lines().find(|line| !line.trim().is_empty()))
+        .unwrap_or_default();
+    clean_title(&line.split_whitespace().take(6).collect::<Vec<_>>().join(" "))
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+/// Tidy a title the model wrote: first line only, no quotes, label or full
+/// stop, and not too long. None if nothing is left.
+pub fn clean_title(text: &str) -> Option<String> {
+    let line = text.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.strip_prefix("Title:").unwrap_or(line);
+    let line = line
+        .trim_matches(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '*' | '#' | '`'))
+        .trim_end_matches(['.', ',', ':', ';', '?', '!']);
+    let title = line.split_whitespace().take(TITLE_WORDS).collect::<Vec<_>>().join(" ");
+
+    let mut chars = title.chars();
+    let first = chars.next()?;
+    Some(first.to_uppercase().chain(chars).collect())
+}
+
+/// The request asking `model` for a title of the first exchange. Only the
+/// start of each message is sent; it's enough to name the conversation by.
+pub fn title_request(client: &ConversationClient, model: &str) -> ApiRequest {
+    let mut messages: Vec<Message> = client
+        .messages
+        .iter()
+        .take(2)
+        .map(|message| Message {
+            role: message.role.clone(),
+            content: message.content.chars().take(TITLE_MESSAGE_CHARS).collect(),
+            ..Default::default()
+        })
+        .collect();
+    messages.push(Message {
+        role: "user".to_string(),
+        content: TITLE_INSTRUCTION.to_string(),
+        ..Default::default()
+    });
+    ApiRequest {
+        model: model.to_string(),
+        max_tokens: TITLE_MAX_TOKENS,
+        temperature: 0.0,
+        system: None,
+        messages,
+        stream: false,
+    }
+}
+
+/// Start naming the conversation once its first exchange is complete, unless
+/// it already has a title or one is on the way
+pub fn request_title(app: &mut AppState) {
+    let messages = &app.client.messages;
+    let answered = matches!(
+        messages.get(..2),
+        Some([prompt, reply]) if prompt.role == "user" && reply.role == "assistant" && !reply.content.is_empty()
+    );
+    if !answered || app.client.title.is_some() || app.pending_title.is_some() {
+        return;
+    }
+
+    let fallback = heuristic_title(messages);
+    if app.simulate_mode {
+        app.client.title = Some(fallback);
+        return;
+    }
+
+    let prompt = messages[0].content.clone();
+    let backend = app.client.backend.clone();
+    let request = title_request(&app.client, &app.title_model);
+    let handle = tokio::spawn(async move {
+        match backend.send(request, None).await {
+            Ok(response) => clean_title(&response.text).unwrap_or(fallback),
+            Err(_) => fallback,
+        }
+    });
+    app.pending_title = Some(PendingTitle { prompt, handle });
+}
+
+/// Give the conversation the title that was being generated, once it's ready.
+/// Returns true if the title changed. A title for a conversation that has
+/// since been cleared or replaced is dropped.
+pub async fn collect_title(app: &mut AppState) -> bool {
+    if !app.pending_title.as_ref().is_some_and(|pending| pending.handle.is_finished()) {
+        return false;
+    }
+    let Some(pending) = app.pending_title.take() else {
+        return false;
+    };
+    let Ok(title) = pending.handle.await else {
+        return false;
+    };
+
+    let same_conversation = app.client.messages.first().is_some_and(|first| first.content == pending.prompt);
+    if same_conversation && app.client.title.is_none() {
+        app.client.title = Some(title);
+        true
+    } else {
+        false
+    }
+}
+
+/// Filename the save dialog suggests for the conversation: its title in
+/// lowercase with dashes, or nothing if it has no title yet
+pub fn default_filename(client: &ConversationClient) -> String {
+    let Some(title) = &client.title else {
+        return String::new();
+    };
+    let slug = title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        String::new()
+    } else {
+        format!("{}.json", slug)
+    }
+}
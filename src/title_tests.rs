@@ -0,0 +1,227 @@
+//! Unit tests for conversation titles
+//! Tests cleaning up generated titles, the first-prompt fallback, generating a
+//! title after the first exchange and the filename the save dialog suggests
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use tokio::time::Duration;
+
+use crate::api::ApiRequest;
+use crate::app::AppState;
+use crate::backend::{ApiSender, ChatBackend, ChatResponse};
+use crate::client::ConversationClient;
+use crate::config::{ColorConfig, DEFAULT_TITLE_MODEL, TITLE_MAX_TOKENS, TITLE_MESSAGE_CHARS};
+use crate::test_support::message;
+use crate::title::*;
+
+/// Backend that answers every request with the same text
+struct FixedBackend(&'static str);
+
+impl ChatBackend for FixedBackend {
+    fn send<'a>(
+        &'a self,
+        _request: ApiRequest,
+        _updates: Option<&'a ApiSender>,
+    ) -> BoxFuture<'a, Result<ChatResponse>> {
+        let text = self.0.to_string();
+        Box::pin(async move { Ok(ChatResponse { text, input_tokens: 1, output_tokens: 1 }) })
+    }
+}
+
+/// Backend whose requests always fail
+struct FailingBackend;
+
+impl ChatBackend for FailingBackend {
+    fn send<'a>(
+        &'a self,
+        _request: ApiRequest,
+        _updates: Option<&'a ApiSender>,
+    ) -> BoxFuture<'a, Result<ChatResponse>> {
+        Box::pin(async { Err(anyhow::anyhow!("API error 500")) })
+    }
+}
+
+/// App with a finished first exchange, sending requests to `backend`
+fn answered_app(simulate: bool, backend: Option<Arc<dyn ChatBackend>>) -> AppState {
+    let mut app = AppState::new(
+        "test_key".to_string(),
+        "test-model".to_string(),
+        1024,
+        0.7,
+        simulate,
+        ColorConfig::default(),
+    ).expect("Failed to create test app");
+    if let Some(backend) = backend {
+        app.client.backend = backend;
+    }
+    app.client.messages.push(message("user", "how do I read a file in rust?"));
+    app.client.messages.push(message("assistant", "Use std::fs::read_to_string."));
+    app
+}
+
+/// Wait for the title request to finish and collect it
+async fn wait_for_title(app: &mut AppState) -> bool {
+    for _ in 0..100 {
+        if app.pending_title.is_none() {
+            return false;
+        }
+        if collect_title(app).await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("Title was never generated");
+}
+
+#[cfg(test)]
+mod clean_title_tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_quotes_label_and_full_stop() {
+        assert_eq!(clean_title("\"Reading files in Rust.\"").as_deref(), Some("Reading files in Rust"));
+        assert_eq!(clean_title("Title: **Lockfile bug**").as_deref(), Some("Lockfile bug"));
+    }
+
+    #[test]
+    fn test_keeps_first_line_only() {
+        let text = "\n  Borrow checker basics\nHere is why I chose it";
+        assert_eq!(clean_title(text).as_deref(), Some("Borrow checker basics"));
+    }
+
+    #[test]
+    fn test_capitalizes_and_limits_length() {
+        let title = clean_title("one two three four five six seven eight nine ten").unwrap();
+        assert_eq!(title, "One two three four five six seven eight");
+    }
+
+    #[test]
+    fn test_empty_text_has_no_title() {
+        assert_eq!(clean_title(""), None);
+        assert_eq!(clean_title("  \"\" \n"), None);
+    }
+}
+
+#[cfg(test)]
+mod heuristic_title_tests {
+    use super::*;
+
+    #[test]
+    fn test_uses_first_words_of_first_prompt() {
+        let messages = vec![
+            message("user", "\nplease explain how async closures work in stable rust"),
+            message("assistant", "Sure."),
+        ];
+        assert_eq!(heuristic_title(&messages), "Please explain how async closures work");
+    }
+
+    #[test]
+    fn test_without_prompt_is_untitled() {
+        assert_eq!(heuristic_title(&[]), "Untitled");
+        assert_eq!(heuristic_title(&[message("user", "   ")]), "Untitled");
+    }
+}
+
+#[cfg(test)]
+mod request_tests {
+    use super::*;
+
+    #[test]
+    fn test_title_request_covers_first_exchange_only() {
+        let mut app = answered_app(true, None);
+        app.client.system_prompt = Some("Be terse.".to_string());
+        app.client.messages.push(message("user", "And writing?"));
+
+        let request = title_request(&app.client, "small-model");
+        assert_eq!(request.model, "small-model");
+        assert_eq!(request.messages.len(), 3);
+        assert_eq!(request.messages[1].role, "assistant");
+        assert_eq!(request.messages[2].role, "user");
+        assert_eq!(request.max_tokens, TITLE_MAX_TOKENS);
+        assert_eq!(request.system, None);
+        assert!(!request.stream);
+    }
+
+    /// Long messages are cut short; the title only needs their gist
+    #[test]
+    fn test_title_request_truncates_messages() {
+        let mut app = answered_app(true, None);
+        app.client.messages[1].content = "é".repeat(TITLE_MESSAGE_CHARS * 10);
+
+        let request = title_request(&app.client, &app.title_model);
+        assert_eq!(request.model, DEFAULT_TITLE_MODEL);
+        assert_eq!(request.messages[0].content, app.client.messages[0].content);
+        assert_eq!(request.messages[1].content.chars().count(), TITLE_MESSAGE_CHARS);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_mode_uses_heuristic() {
+        let mut app = answered_app(true, None);
+        request_title(&mut app);
+
+        assert_eq!(app.client.title.as_deref(), Some("How do I read a file"));
+        assert!(app.pending_title.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generated_title_is_cleaned() {
+        let mut app = answered_app(false, Some(Arc::new(FixedBackend("\"Reading files in Rust.\""))));
+        request_title(&mut app);
+        assert!(app.pending_title.is_some());
+
+        assert!(wait_for_title(&mut app).await);
+        assert_eq!(app.client.title.as_deref(), Some("Reading files in Rust"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_falls_back_to_heuristic() {
+        let mut app = answered_app(false, Some(Arc::new(FailingBackend)));
+        request_title(&mut app);
+
+        assert!(wait_for_title(&mut app).await);
+        assert_eq!(app.client.title.as_deref(), Some("How do I read a file"));
+    }
+
+    #[tokio::test]
+    async fn test_not_requested_before_reply_or_when_titled() {
+        let mut app = answered_app(true, None);
+        app.client.messages.pop();
+        request_title(&mut app);
+        assert_eq!(app.client.title, None);
+
+        let mut app = answered_app(true, None);
+        app.client.title = Some("Mine".to_string());
+        request_title(&mut app);
+        assert_eq!(app.client.title.as_deref(), Some("Mine"));
+    }
+
+    #[tokio::test]
+    async fn test_title_for_replaced_conversation_is_dropped() {
+        let mut app = answered_app(false, Some(Arc::new(FixedBackend("Reading files"))));
+        request_title(&mut app);
+
+        app.client.messages.clear();
+        app.client.messages.push(message("user", "Something else"));
+
+        assert!(!wait_for_title(&mut app).await);
+        assert_eq!(app.client.title, None);
+    }
+}
+
+#[cfg(test)]
+mod default_filename_tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_from_title() {
+        let mut client = ConversationClient::new("key".to_string(), "model".to_string(), 100, 0.7);
+        assert_eq!(default_filename(&client), "");
+
+        client.title = Some("Reading files in Rust: a guide!".to_string());
+        assert_eq!(default_filename(&client), "reading-files-in-rust-a-guide.json");
+
+        client.title = Some("???".to_string());
+        assert_eq!(default_filename(&client), "");
+    }
+}
@@ -0,0 +1,116 @@
+// src/tree.rs
+//! Every message of a conversation, on every branch. Each message points at
+//! the one it follows, so editing, regenerating or forking starts a new branch
+//! instead of throwing the old continuation away. The client's `messages` is
+//! the branch on screen; it is recorded here as it grows.
+use std::collections::HashMap;
+use crate::api::Message;
+
+#[derive(Debug, Clone, Default)]
+pub struct ConversationTree {
+    /// In the order they were recorded, which is also the order of siblings
+    nodes: Vec<Message>,
+    /// Position in `nodes` by message id
+    index: HashMap<u64, usize>,
+    next_id: u64,
+}
+
+impl ConversationTree {
+    /// Rebuild a tree from saved messages, all of which carry ids
+    pub fn from_messages(nodes: Vec<Message>) -> Self {
+        let mut tree = Self::default();
+        for message in nodes {
+            if let Some(id) = message.id {
+                tree.next_id = tree.next_id.max(id + 1);
+                tree.index.insert(id, tree.nodes.len());
+                tree.nodes.push(message);
+            }
+        }
+        tree
+    }
+
+    pub fn nodes(&self) -> &[Message] {
+        &self.nodes
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Message> {
+        self.index.get(&id).map(|&position| &self.nodes[position])
+    }
+
+    /// Store the current content of `branch`, giving ids to messages that
+    /// don't have one yet. Each new message follows the one before it.
+    pub fn record(&mut self, branch: &mut [Message]) {
+        let mut parent = None;
+        for message in branch.iter_mut() {
+            match message.id.and_then(|id| self.index.get(&id).copied()) {
+                Some(position) => self.nodes[position] = message.clone(),
+                None => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    message.id = Some(id);
+                    message.parent = parent;
+                    self.index.insert(id, self.nodes.len());
+                    self.nodes.push(message.clone());
+                }
+            }
+            parent = message.id;
+        }
+    }
+
+    /// Forget a message nothing follows yet, e.g. a prompt whose request was cancelled
+    pub fn remove_leaf(&mut self, id: u64) {
+        if !self.children(Some(id)).is_empty() {
+            return;
+        }
+        if let Some(position) = self.index.remove(&id) {
+            self.nodes.remove(position);
+            for position in self.index.values_mut().filter(|p| **p > position) {
+                *position -= 1;
+            }
+        }
+    }
+
+    /// Ids of the messages following `parent` (None for the first messages), oldest first
+    pub fn children(&self, parent: Option<u64>) -> Vec<u64> {
+        self.nodes
+            .iter()
+            .filter(|message| message.parent == parent)
+            .filter_map(|message| message.id)
+            .collect()
+    }
+
+    /// Ids of the alternatives to `id`, itself included, oldest first
+    pub fn siblings(&self, id: u64) -> Vec<u64> {
+        match self.get(id) {
+            Some(message) => self.children(message.parent),
+            None => Vec::new(),
+        }
+    }
+
+    /// The branch through `id` that was added to most recently: from the
+    /// root down to `id`, then on through the newest child at each step
+    pub fn branch_through(&self, id: u64) -> Vec<Message> {
+        let mut branch = Vec::new();
+        let mut next = self.get(id);
+        while let Some(message) = next {
+            branch.push(message.clone());
+            next = message.parent.and_then(|parent| self.get(parent));
+        }
+        branch.reverse();
+
+        let mut last = Some(id);
+        while let Some(child) = last.and_then(|id| self.children(Some(id)).last().copied()) {
+            branch.extend(self.get(child).cloned());
+            last = Some(child);
+        }
+        branch
+    }
+
+    /// The most recently extended branch from the start of the conversation
+    pub fn latest_branch(&self) -> Vec<Message> {
+        match self.children(None).last() {
+            Some(&first) => self.branch_through(first),
+            None => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Unit tests for the conversation tree
+//! Tests recording branches, walking them and switching between them
+
+use crate::api::Message;
+use crate::client::ConversationClient;
+use crate::test_support::{message, reply};
+use crate::tree::ConversationTree;
+
+fn contents(messages: &[Message]) -> Vec<&str> {
+    messages.iter().map(|m| m.content.as_str()).collect()
+}
+
+/// A client whose second prompt was edited: "b1" and its reply are on one
+/// branch, "b2" and its reply on the other, which is on screen
+fn client_with_two_branches() -> ConversationClient {
+    let mut client = ConversationClient::new("key".to_string(), "m".to_string(), 100, 0.5);
+    client.messages = vec![
+        message("user", "a"),
+        reply("A", 1, 2),
+        message("user", "b1"),
+        reply("B1", 10, 20),
+    ];
+    client.total_input_tokens = 11;
+    client.total_output_tokens = 22;
+    client.record_branch();
+
+    client.truncate_messages(2);
+    client.messages.push(message("user", "b2"));
+    client.messages.push(reply("B2", 100, 200));
+    client.total_input_tokens += 100;
+    client.total_output_tokens += 200;
+    client.record_branch();
+    client
+}
+
+#[cfg(test)]
+mod record_tests {
+    use super::*;
+
+    /// Each new message gets an id and points at the one before it
+    #[test]
+    fn test_record_links_messages() {
+        let mut tree = ConversationTree::default();
+        let mut branch = vec![message("user", "a"), reply("A", 1, 1)];
+
+        tree.record(&mut branch);
+
+        assert_eq!(tree.nodes().len(), 2);
+        assert_eq!(branch[0].parent, None);
+        assert_eq!(branch[1].parent, branch[0].id);
+        assert_eq!(tree.children(None), vec![branch[0].id.unwrap()]);
+    }
+
+    /// Recording again stores changed content without adding nodes
+    #[test]
+    fn test_record_updates_existing_messages() {
+        let mut tree = ConversationTree::default();
+        let mut branch = vec![message("user", "a"), reply("Par", 1, 1)];
+        tree.record(&mut branch);
+
+        branch[1].content.push_str("tial");
+        tree.record(&mut branch);
+
+        assert_eq!(tree.nodes().len(), 2);
+        assert_eq!(tree.get(branch[1].id.unwrap()).unwrap().content, "Partial");
+    }
+
+    /// A leaf can be forgotten, but not a message something follows
+    #[test]
+    fn test_remove_leaf() {
+        let mut tree = ConversationTree::default();
+        let mut branch = vec![message("user", "a"), reply("A", 1, 1)];
+        tree.record(&mut branch);
+        let (first, second) = (branch[0].id.unwrap(), branch[1].id.unwrap());
+
+        tree.remove_leaf(first);
+        assert_eq!(tree.nodes().len(), 2);
+
+        tree.remove_leaf(second);
+        assert_eq!(tree.nodes().len(), 1);
+        assert!(tree.get(second).is_none());
+        assert_eq!(tree.get(first).unwrap().content, "a");
+    }
+
+    /// Saved nodes rebuild the same tree, and new ids don't collide with them
+    #[test]
+    fn test_from_messages_continues_ids() {
+        let client = client_with_two_branches();
+
+        let mut tree = ConversationTree::from_messages(client.tree.nodes().to_vec());
+        let mut branch = client.messages.clone();
+        branch.push(message("user", "c"));
+        tree.record(&mut branch);
+
+        assert_eq!(tree.nodes().len(), 7);
+        assert!(client.tree.get(branch[4].id.unwrap()).is_none());
+    }
+}
+
+#[cfg(test)]
+mod branch_tests {
+    use super::*;
+
+    /// Truncating keeps the old continuation as a sibling branch
+    #[test]
+    fn test_edit_leaves_old_branch_in_tree() {
+        let client = client_with_two_branches();
+
+        assert_eq!(contents(&client.messages), ["a", "A", "b2", "B2"]);
+        assert_eq!(client.tree.nodes().len(), 6);
+        let siblings = client.tree.siblings(client.messages[2].id.unwrap());
+        assert_eq!(siblings.len(), 2);
+        assert_eq!(client.tree.get(siblings[0]).unwrap().content, "b1");
+    }
+
+    /// A branch runs from the root through the message and on to its newest descendants
+    #[test]
+    fn test_branch_through_follows_newest_children() {
+        let client = client_with_two_branches();
+        let first = client.messages[0].id.unwrap();
+        let old_prompt = client.tree.siblings(client.messages[2].id.unwrap())[0];
+
+        assert_eq!(contents(&client.tree.branch_through(old_prompt)), ["a", "A", "b1", "B1"]);
+        assert_eq!(contents(&client.tree.branch_through(first)), ["a", "A", "b2", "B2"]);
+        assert_eq!(contents(&client.tree.latest_branch()), ["a", "A", "b2", "B2"]);
+    }
+
+    /// Switching swaps the messages after the fork and the tokens their replies used
+    #[test]
+    fn test_switch_branch_swaps_tokens() {
+        let mut client = client_with_two_branches();
+        assert_eq!((client.total_input_tokens, client.total_output_tokens), (101, 202));
+        let old_prompt = client.tree.siblings(client.messages[2].id.unwrap())[0];
+
+        client.switch_branch(old_prompt);
+
+        assert_eq!(contents(&client.messages), ["a", "A", "b1", "B1"]);
+        assert_eq!((client.total_input_tokens, client.total_output_tokens), (11, 22));
+        assert_eq!(client.tree.nodes().len(), 6);
+    }
+}
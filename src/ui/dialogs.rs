@@ -1,10 +1,14 @@
 use ratatui::{
     Frame,
-    widgets::{Block, Borders, Paragraph, Wrap, Clear, List, ListItem},
+    widgets::{Block, Borders, Paragraph, Wrap, Clear, List, ListItem, ListState},
     layout::{Layout, Constraint, Direction, Rect},
     style::{Color, Style},
+    text::{Line, Span, Text},
 };
 use crate::app::AppState;
+use crate::keymap::Action;
+use crate::settings::UI_OPTIONS;
+use crate::utils::text::{wrap_text, calculate_cursor_line, calculate_cursor_position};
 
 /// Helper function to create a block with enhanced borders for dialog distinction
 fn create_enhanced_dialog_block(title: &str) -> Block<'static> {
@@ -34,11 +38,41 @@ pub fn draw_dialogs(f: &mut Frame, app: &mut AppState, size: Rect) {
         draw_load_dialog(f, app, size);
     }
     
+    // Session library overlay
+    if app.show_library_dialog {
+        draw_library_dialog(f, app, size);
+    }
+
+    // Find dialog overlay
+    if app.show_find_dialog {
+        draw_find_dialog(f, app, size);
+    }
+
+    // Key binding help overlay
+    if app.show_help_dialog {
+        draw_help_dialog(f, app, size);
+    }
+
+    // Model picker overlay
+    if app.show_model_dialog {
+        draw_model_dialog(f, app, size);
+    }
+
+    // Settings dialog overlay
+    if app.show_settings_dialog {
+        draw_settings_dialog(f, app, size);
+    }
+
     // Create directory dialog overlay
     if app.show_create_dir_dialog {
         draw_create_dir_dialog(f, app, size);
     }
 
+    // System prompt dialog overlay
+    if app.show_system_dialog {
+        draw_system_dialog(f, app, size);
+    }
+
     // Color configuration dialog overlay
     if app.show_color_dialog {
         draw_color_dialog(f, app, size);
@@ -49,6 +83,11 @@ pub fn draw_dialogs(f: &mut Frame, app: &mut AppState, size: Rect) {
         draw_profile_dialog(f, app, size);
     }
 
+    // Restore prompt shown at startup
+    if app.show_restore_dialog {
+        draw_restore_dialog(f, app, size);
+    }
+
     // Exit confirmation dialog overlay (render last so it appears on top)
     if app.show_exit_dialog {
         draw_exit_dialog(f, app, size);
@@ -157,6 +196,315 @@ fn draw_load_dialog(f: &mut Frame, app: &mut AppState, size: Rect) {
     f.render_stateful_widget(file_list, dialog_area, &mut app.file_list_state);
 }
 
+fn draw_library_dialog(f: &mut Frame, app: &AppState, size: Rect) {
+    let dialog_area = Rect {
+        x: size.width / 10,
+        y: size.height / 8,
+        width: (size.width * 4) / 5,
+        height: (size.height * 3) / 4,
+    };
+    f.render_widget(Clear, dialog_area);
+    let outer = create_enhanced_dialog_block("Session Library");
+    let inner = outer.inner(dialog_area);
+    f.render_widget(outer, dialog_area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Filter, rename or delete prompt
+            Constraint::Min(3),    // Sessions and preview
+            Constraint::Length(1), // Key help
+        ])
+        .split(inner);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(rows[1]);
+
+    let selected = app.library_matches
+        .get(app.library_selection)
+        .and_then(|&position| app.library_sessions.get(position));
+
+    // Top line: what typing currently does
+    let (label, text) = match (&app.library_rename, app.library_confirm_delete, selected) {
+        (_, true, Some(session)) => (format!("Delete \"{}\"? (y/N)", session.title), String::new()),
+        (Some(title), _, _) => ("Rename".to_string(), title.clone()),
+        _ => ("Filter".to_string(), app.library_filter.clone()),
+    };
+    let prompt = Paragraph::new(text.as_str())
+        .block(create_dialog_block(app).title(label))
+        .style(Style::default().bg(Color::DarkGray));
+    f.render_widget(prompt, rows[0]);
+    if !app.library_confirm_delete {
+        f.set_cursor(rows[0].x + 1 + text.chars().count() as u16, rows[0].y + 1);
+    }
+
+    // Sessions matching the filter, two lines each
+    let items: Vec<ListItem> = app.library_matches
+        .iter()
+        .filter_map(|&position| app.library_sessions.get(position))
+        .map(|session| {
+            ListItem::new(vec![
+                Line::from(Span::styled(session.title.clone(), Style::default().fg(Color::White))),
+                Line::from(Span::styled(
+                    format!(
+                        "  {} · {} · {} messages · {} tokens",
+                        session.local_time(),
+                        session.model,
+                        session.message_count,
+                        session.total_tokens
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                )),
+            ])
+        })
+        .collect();
+    let title = format!("Sessions ({} of {})", app.library_matches.len(), app.library_sessions.len());
+    let list = List::new(items)
+        .block(create_dialog_block(app).title(title))
+        .highlight_style(Style::default().bg(Color::Blue))
+        .style(Style::default().bg(Color::Black));
+    let mut state = ListState::default();
+    state.select(selected.map(|_| app.library_selection));
+    f.render_stateful_widget(list, columns[0], &mut state);
+
+    // The opening messages of the highlighted session
+    let mut preview = Vec::new();
+    for message in selected.map(|session| session.preview.as_slice()).unwrap_or_default() {
+        let color = if message.role == "user" { app.colors.user_name } else { app.colors.assistant_name };
+        let mut lines = message.content.lines().filter(|line| !line.trim().is_empty());
+        preview.push(Line::from(vec![
+            Span::styled(format!("[{}]: ", message.role), Style::default().fg(color.to_ratatui_color())),
+            Span::raw(lines.next().unwrap_or_default().to_string()),
+        ]));
+        preview.extend(lines.take(3).map(|line| Line::from(line.to_string())));
+        preview.push(Line::default());
+    }
+    if app.library_sessions.is_empty() {
+        preview.push(Line::from("No sessions yet. Conversations are added as you chat."));
+    }
+    let preview = Paragraph::new(preview)
+        .block(create_dialog_block(app).title("Preview"))
+        .wrap(Wrap { trim: false });
+    f.render_widget(preview, columns[1]);
+
+    let help = Paragraph::new("↑↓ select · Enter resume · F2 rename · Del delete · Esc close")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help, rows[2]);
+}
+
+fn draw_find_dialog(f: &mut Frame, app: &AppState, size: Rect) {
+    let dialog_area = Rect {
+        x: size.width / 10,
+        y: size.height / 8,
+        width: (size.width * 4) / 5,
+        height: (size.height * 3) / 4,
+    };
+    f.render_widget(Clear, dialog_area);
+    let outer = create_enhanced_dialog_block("Find in Saved Conversations");
+    let inner = outer.inner(dialog_area);
+    f.render_widget(outer, dialog_area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Query
+            Constraint::Min(3),    // Hits
+            Constraint::Length(1), // Key help
+        ])
+        .split(inner);
+
+    let query = Paragraph::new(app.find_query.as_str())
+        .block(create_dialog_block(app).title("Search"))
+        .style(Style::default().bg(Color::DarkGray));
+    f.render_widget(query, rows[0]);
+    f.set_cursor(rows[0].x + 1 + app.find_query.chars().count() as u16, rows[0].y + 1);
+
+    // Matching messages, two lines each: where they are, then the text around the match
+    let items: Vec<ListItem> = app.find_hits
+        .iter()
+        .map(|hit| {
+            let color = if hit.role == "user" { app.colors.user_name } else { app.colors.assistant_name };
+            ListItem::new(vec![
+                Line::from(vec![
+                    Span::styled(hit.title.clone(), Style::default().fg(Color::White)),
+                    Span::styled(format!(" · {}", hit.local_time()), Style::default().fg(Color::DarkGray)),
+                ]),
+                Line::from(vec![
+                    Span::styled(format!("  [{}]: ", hit.role), Style::default().fg(color.to_ratatui_color())),
+                    Span::raw(hit.snippet.clone()),
+                ]),
+            ])
+        })
+        .collect();
+    let title = if app.find_query.trim().is_empty() {
+        format!("{} saved conversations", app.find_conversations.len())
+    } else {
+        format!("{} matching messages", app.find_hits.len())
+    };
+    let list = List::new(items)
+        .block(create_dialog_block(app).title(title))
+        .highlight_style(Style::default().bg(Color::Blue))
+        .style(Style::default().bg(Color::Black));
+    let mut state = ListState::default();
+    state.select((!app.find_hits.is_empty()).then_some(app.find_selection));
+    f.render_stateful_widget(list, rows[1], &mut state);
+
+    let help = Paragraph::new("Type to search · ↑↓ select · Enter open at the match · Esc close")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help, rows[2]);
+}
+
+/// The active keymap: what each action does, its chords and its name in the
+/// `[keys]` settings
+fn draw_help_dialog(f: &mut Frame, app: &mut AppState, size: Rect) {
+    let dialog_area = Rect {
+        x: size.width / 10,
+        y: size.height / 8,
+        width: (size.width * 4) / 5,
+        height: (size.height * 3) / 4,
+    };
+    f.render_widget(Clear, dialog_area);
+    let outer = create_enhanced_dialog_block("Key Bindings");
+    let inner = outer.inner(dialog_area);
+    f.render_widget(outer, dialog_area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // Bindings
+            Constraint::Length(1), // Key help
+        ])
+        .split(inner);
+
+    let width = Action::ALL.iter().map(|action| action.description().len()).max().unwrap_or(0);
+    let lines: Vec<Line> = Action::ALL
+        .iter()
+        .map(|&action| Line::from(vec![
+            Span::styled(format!("{:width$}  ", action.description()), Style::default().fg(Color::White)),
+            Span::styled(app.keymap.describe(action), Style::default().fg(Color::Yellow)),
+            Span::styled(format!("  ({})", action.name()), Style::default().fg(Color::DarkGray)),
+        ]))
+        .collect();
+
+    // Keep the last binding at the bottom of the box when scrolled all the way
+    let visible = rows[0].height.saturating_sub(2);
+    let max_scroll = (lines.len() as u16).saturating_sub(visible);
+    app.help_scroll_offset = app.help_scroll_offset.min(max_scroll);
+
+    let bindings = Paragraph::new(Text::from(lines))
+        .block(create_dialog_block(app).title("Rebind in the [keys] section of your settings"))
+        .scroll((app.help_scroll_offset, 0))
+        .style(Style::default().bg(Color::Black));
+    f.render_widget(bindings, rows[0]);
+
+    let help = Paragraph::new("↑↓ scroll · any other key to close")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help, rows[1]);
+}
+
+fn draw_model_dialog(f: &mut Frame, app: &AppState, size: Rect) {
+    let height = app.presets.len().max(1) as u16 + 5;
+    let dialog_area = Rect {
+        x: size.width / 6,
+        y: size.height.saturating_sub(height) / 2,
+        width: (size.width * 2) / 3,
+        height: height.min(size.height),
+    };
+    f.render_widget(Clear, dialog_area);
+    let outer = create_enhanced_dialog_block("Model Presets");
+    let inner = outer.inner(dialog_area);
+    f.render_widget(outer, dialog_area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // Presets
+            Constraint::Length(1), // Key help
+        ])
+        .split(inner);
+
+    let block = create_dialog_block(app).title(format!("Current: {}", app.model_label()));
+    if app.presets.is_empty() {
+        let empty = Paragraph::new("No presets configured. Add [presets.<name>] tables to your settings.")
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .style(Style::default().bg(Color::Black).fg(Color::DarkGray));
+        f.render_widget(empty, rows[0]);
+    } else {
+        let items: Vec<ListItem> = app.presets
+            .iter()
+            .map(|(name, preset)| {
+                let marker = if app.active_preset.as_ref() == Some(name) { "● " } else { "  " };
+                let mut details = vec![preset.model.clone()];
+                if let Some(max_tokens) = preset.max_tokens {
+                    details.push(format!("max tokens {}", max_tokens));
+                }
+                if let Some(temperature) = preset.temperature {
+                    details.push(format!("temperature {}", temperature));
+                }
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{}{}", marker, name), Style::default().fg(Color::White)),
+                    Span::styled(format!(" · {}", details.join(", ")), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::Blue))
+            .style(Style::default().bg(Color::Black));
+        let mut state = ListState::default();
+        state.select(Some(app.model_selection));
+        f.render_stateful_widget(list, rows[0], &mut state);
+    }
+
+    let help = Paragraph::new("↑↓ select · Enter switch · Esc close · /model <id> for any other model")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help, rows[1]);
+}
+
+fn draw_settings_dialog(f: &mut Frame, app: &AppState, size: Rect) {
+    let height = UI_OPTIONS.len() as u16 + 5;
+    let dialog_area = Rect {
+        x: size.width / 6,
+        y: size.height.saturating_sub(height) / 2,
+        width: (size.width * 2) / 3,
+        height: height.min(size.height),
+    };
+    f.render_widget(Clear, dialog_area);
+    let outer = create_enhanced_dialog_block("Settings");
+    let inner = outer.inner(dialog_area);
+    f.render_widget(outer, dialog_area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // Options
+            Constraint::Length(1), // Key help
+        ])
+        .split(inner);
+
+    let items: Vec<ListItem> = UI_OPTIONS
+        .iter()
+        .zip(app.ui.values())
+        .map(|((key, label), on)| {
+            let source = if app.ui_from_project.contains_key(*key) { " (set by project)" } else { "" };
+            ListItem::new(format!("[{}] {}{}", if on { "x" } else { " " }, label, source))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(create_dialog_block(app))
+        .highlight_style(Style::default().bg(Color::Blue))
+        .style(Style::default().bg(Color::Black));
+    let mut state = ListState::default();
+    state.select(Some(app.settings_selection));
+    f.render_stateful_widget(list, rows[0], &mut state);
+
+    let help = Paragraph::new("↑↓ select · Enter/Space toggle and save · Esc close")
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help, rows[1]);
+}
+
 fn draw_create_dir_dialog(f: &mut Frame, app: &AppState, size: Rect) {
     let dialog_area = Rect {
         x: size.width / 4,
@@ -198,6 +546,58 @@ fn draw_create_dir_dialog(f: &mut Frame, app: &AppState, size: Rect) {
     );
 }
 
+fn draw_system_dialog(f: &mut Frame, app: &AppState, size: Rect) {
+    let dialog_area = Rect {
+        x: size.width / 6,
+        y: size.height / 4,
+        width: (size.width * 2) / 3,
+        height: size.height / 2,
+    };
+    
+    // Create outer border area (slightly larger than dialog)
+    let outer_border_area = Rect {
+        x: dialog_area.x.saturating_sub(1),
+        y: dialog_area.y.saturating_sub(1),
+        width: dialog_area.width + 2,
+        height: dialog_area.height + 2,
+    };
+    
+    // Render outer border for visual separation
+    let outer_border = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::White))
+        .style(Style::default().bg(Color::Black));
+    
+    f.render_widget(outer_border, outer_border_area);
+    f.render_widget(Clear, dialog_area);
+    
+    // Keep the cursor line visible when the prompt is longer than the dialog
+    let text_width = dialog_area.width.saturating_sub(2) as usize;
+    let text_height = dialog_area.height.saturating_sub(2) as usize;
+    let cursor_line = calculate_cursor_line(&app.system_prompt_input, app.dialog_cursor_pos, text_width);
+    let scroll_offset = (cursor_line + 1).saturating_sub(text_height);
+    
+    let prompt_input = Paragraph::new(Text::from(wrap_text(&app.system_prompt_input, text_width)))
+        .block(create_dialog_block(app)
+            .title("System Prompt (Enter to apply, empty to clear, Esc to cancel)"))
+        .scroll((scroll_offset as u16, 0))
+        .style(Style::default().bg(Color::Black));
+    f.render_widget(prompt_input, dialog_area);
+    
+    if text_width > 0 {
+        let (cursor_x, cursor_y) = calculate_cursor_position(
+            &app.system_prompt_input,
+            app.dialog_cursor_pos,
+            text_width,
+            scroll_offset,
+        );
+        f.set_cursor(
+            dialog_area.x + 1 + cursor_x as u16,
+            dialog_area.y + 1 + cursor_y as u16,
+        );
+    }
+}
+
 fn draw_exit_dialog(f: &mut Frame, app: &AppState, size: Rect) {
     // Calculate optimal dialog width based on content
     let main_text = "Exit the program?";
@@ -290,6 +690,50 @@ fn draw_exit_dialog(f: &mut Frame, app: &AppState, size: Rect) {
     f.render_widget(highlight, highlight_area);
 }
 
+fn draw_restore_dialog(f: &mut Frame, app: &AppState, size: Rect) {
+    let Some(conversation) = &app.restore_candidate else {
+        return;
+    };
+    let when = chrono::DateTime::parse_from_rfc3339(&conversation.timestamp)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| conversation.timestamp.clone());
+    let text = format!(
+        "Restore the unsaved session from {} ({} messages)?\n\nUse ↑↓ or Y/N to select, Enter to confirm.",
+        when,
+        conversation.messages.len()
+    );
+
+    let dialog_width = std::cmp::min((size.width * 90) / 100, 66);
+    let dialog_area = Rect {
+        x: (size.width.saturating_sub(dialog_width)) / 2,
+        y: (size.height / 2).saturating_sub(3),
+        width: dialog_width,
+        height: 7,
+    };
+
+    f.render_widget(Clear, dialog_area);
+    let restore_dialog = Paragraph::new(text)
+        .block(create_enhanced_dialog_block("Restore Session"))
+        .wrap(Wrap { trim: false });
+    f.render_widget(restore_dialog, dialog_area);
+
+    // Render the choices, highlighting the selected one
+    let options_area = Rect {
+        x: dialog_area.x + 2,
+        y: dialog_area.y + dialog_area.height.saturating_sub(2),
+        width: dialog_area.width.saturating_sub(4),
+        height: 1,
+    };
+    let selected = Style::default().bg(Color::Blue).fg(Color::White);
+    let options = Line::from(vec![
+        Span::raw("  "),
+        Span::styled("[Restore]", if app.restore_selected == 0 { selected } else { Style::default() }),
+        Span::raw("     "),
+        Span::styled("[Discard]", if app.restore_selected == 1 { selected } else { Style::default() }),
+    ]);
+    f.render_widget(Paragraph::new(options), options_area);
+}
+
 fn draw_error_dialog(f: &mut Frame, app: &AppState, size: Rect) {
     let error_area = Rect {
         x: size.width / 4,
@@ -2,7 +2,6 @@
 pub mod render;
 pub mod dialogs;
 pub mod layout;
-pub mod borders;
 
 // Test modules
 #[cfg(test)]
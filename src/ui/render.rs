@@ -3,14 +3,14 @@ use ratatui::{
     Frame,
     widgets::{Block, Borders, Paragraph, Wrap},
     layout::{Layout, Constraint, Direction, Rect},
-    text::Text,
-    style::Style,
+    text::{Span, Text},
+    style::{Color, Modifier, Style},
 };
 use crate::{
     app::AppState,
-    config::{SHIFT_ENTER_SENDS, PROGRESS_FRAMES},
+    config::PROGRESS_FRAMES,
     tui::format_message_for_tui_cached,
-    utils::{text::*, scroll::*},
+    utils::{text::*, scroll::*, search::*},
     ui::dialogs::draw_dialogs,
 };
 
@@ -37,15 +37,41 @@ fn draw_chat(
     app: &mut AppState,
     area: Rect,
 ) {
+    // The message being picked or edited stands out from the rest
+    let highlighted = app.message_selection.or(app.editing_message);
+    let mut highlighted_rows = 0..0;
+    // Line each message starts on
+    let mut message_lines = Vec::with_capacity(app.client.messages.len());
+
+    app.highlight_cache.show_debug_messages = app.ui.show_debug_messages;
     let mut chat_spans = Vec::new();
-    for msg in &app.client.messages {
-        chat_spans.extend(format_message_for_tui_cached(
+    for (index, msg) in app.client.messages.iter().enumerate() {
+        message_lines.push(chat_spans.len());
+        let mut lines = format_message_for_tui_cached(
             &msg.role, 
             &msg.content, 
             &mut app.highlight_cache,
             app.colors.user_name,
             app.colors.assistant_name,
-        ));
+            area.width.saturating_sub(2),
+            app.colors.border_style,
+        );
+        // Messages with alternatives say which branch is on screen
+        let siblings = msg.id.map(|id| app.client.tree.siblings(id)).unwrap_or_default();
+        if let (true, Some(first)) = (siblings.len() > 1, lines.first_mut()) {
+            let position = siblings.iter().position(|&id| Some(id) == msg.id).unwrap_or(0);
+            first.spans.push(Span::styled(
+                format!(" ◀ {}/{} ▶", position + 1, siblings.len()),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if highlighted == Some(index) {
+            highlighted_rows = chat_spans.len()..chat_spans.len() + lines.len();
+            let style = Style::default().add_modifier(Modifier::REVERSED);
+            chat_spans.extend(lines.into_iter().map(|line| line.patch_style(style)));
+        } else {
+            chat_spans.extend(lines);
+        }
     }
 
     // Calculate proper scroll offset if auto_scroll is enabled
@@ -55,11 +81,44 @@ fn draw_chat(
         app.chat_scroll_offset = calculate_chat_scroll_offset(&chat_spans, chat_height, chat_width);
     }
 
-    let chat_title = if app.simulate_mode {
-        "Conversation (SIMULATE MODE)"
-    } else {
-        "Conversation"
-    };
+    // Follow the selection as it moves through the conversation
+    if app.message_selection.is_some() {
+        let chat_height = area.height.saturating_sub(2);
+        let chat_width = area.width.saturating_sub(2);
+        let start = count_visual_lines(&chat_spans[..highlighted_rows.start], chat_width);
+        let end = start + count_visual_lines(&chat_spans[highlighted_rows], chat_width);
+        app.chat_scroll_offset = scroll_to_show(app.chat_scroll_offset, start, end, chat_height);
+    }
+
+    // Mark search hits, bringing the current one into view when it changes
+    if let Some(search) = app.search.as_mut() {
+        let matches = find_matches(&chat_spans, &search.query);
+        search.match_count = matches.len();
+        if let Some(index) = search.start_at.take() {
+            let first_line = message_lines.get(index).copied().unwrap_or(0);
+            search.current = matches.iter().position(|hit| hit.line >= first_line).unwrap_or(0);
+            search.jump = true;
+        }
+        if search.current >= matches.len() {
+            search.current = 0;
+        }
+        if let (true, Some(hit)) = (search.jump, matches.get(search.current)) {
+            let chat_height = area.height.saturating_sub(2);
+            let row = match_row(&chat_spans, hit, area.width.saturating_sub(2));
+            app.chat_scroll_offset = scroll_to_show(app.chat_scroll_offset, row, row + 1, chat_height);
+        }
+        search.jump = false;
+        highlight_matches(&mut chat_spans, &matches, search.current);
+    }
+
+    // The conversation's title once it has one
+    let mut chat_title = app.client.title.clone().unwrap_or_else(|| "Conversation".to_string());
+    if app.simulate_mode {
+        chat_title.push_str(" (SIMULATE MODE)");
+    }
+    if app.message_selection.is_some() {
+        chat_title.push_str(" - Enter to edit, f to fork, Left/Right to switch branch");
+    }
     
     let chat = Paragraph::new(Text::from(chat_spans))
         .block(Block::default()
@@ -81,6 +140,31 @@ fn draw_input(
     app: &mut AppState,
     area: Rect,
 ) {
+    // The search bar takes the place of the input box while it's open
+    if let Some(search) = &app.search {
+        let title = if search.editing {
+            "Search (Enter to step through matches, Esc to close)"
+        } else {
+            "Search (n/N for next/previous, / to edit, Esc to close)"
+        };
+        let search_bar = Paragraph::new(search.query.clone())
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .border_set(app.colors.border_style.to_ratatui_border_set())
+                .title(title)
+                .border_style(Style::default().fg(app.colors.border.to_ratatui_color()))
+                .title_style(Style::default().fg(app.colors.border.to_ratatui_color())))
+            .style(Style::default()
+                .bg(app.colors.background.to_ratatui_color())
+                .fg(app.colors.text.to_ratatui_color()));
+        f.render_widget(search_bar, area);
+        if search.editing {
+            let width = search.query.chars().count() as u16;
+            f.set_cursor(area.x + (width + 1).min(area.width.saturating_sub(2)), area.y + 1);
+        }
+        return;
+    }
+
     let input_lines = wrap_text(&app.input, area.width.saturating_sub(2) as usize);
     let cursor_line = calculate_cursor_line(
         &app.input,
@@ -96,10 +180,11 @@ fn draw_input(
         app.input_scroll_offset = cursor_line as u16;
     }
 
-    let input_title = if SHIFT_ENTER_SENDS {
-        "Input (Shift/Alt+Enter to send, Enter for newline)"
-    } else {
-        "Input (Enter to send, Shift/Alt+Enter for newline)"
+    let input_title = match (app.editing_message.is_some(), app.ui.shift_enter_sends) {
+        (true, true) => "Editing message (Shift/Alt+Enter to resend, Esc to discard the edit)",
+        (true, false) => "Editing message (Enter to resend, Esc to discard the edit)",
+        (false, true) => "Input (Shift/Alt+Enter to send, Enter for newline)",
+        (false, false) => "Input (Enter to send, Shift/Alt+Enter for newline)",
     };
     
     let input_bar = Paragraph::new(Text::from(input_lines))
@@ -143,11 +228,26 @@ fn draw_status(
         .split(area);
 
     // Status bar
-    let status_text = if app.waiting {
+    let status_text = if let (true, Some(retry)) = (app.waiting, app.retry_status) {
+        let remaining = retry.resume_at.saturating_duration_since(std::time::Instant::now());
+        format!(
+            "Retrying ({}/{}) in {}s… (Esc to cancel)",
+            retry.attempt,
+            retry.max_retries,
+            remaining.as_secs_f32().ceil() as u64
+        )
+    } else if app.waiting {
+        // Once the reply has started streaming in, say so rather than "waiting"
+        let receiving = app.client.messages.last()
+            .map(|m| m.role == "assistant")
+            .unwrap_or(false);
         format!(
-            "Waiting for Claude {}",
+            "{} {} (Esc to cancel)",
+            if receiving { "Receiving from Claude" } else { "Waiting for Claude" },
             PROGRESS_FRAMES[app.progress_i % PROGRESS_FRAMES.len()]
         )
+    } else if let Some(search) = &app.search {
+        search.status()
     } else {
         app.status.clone()
     };
@@ -156,7 +256,7 @@ fn draw_status(
         .block(Block::default()
             .borders(Borders::ALL)
             .border_set(app.colors.border_style.to_ratatui_border_set())
-            .title("Status")
+            .title(format!("Status - {}", app.model_label()))
             .border_style(Style::default().fg(app.colors.border.to_ratatui_color()))
             .title_style(Style::default().fg(app.colors.border.to_ratatui_color())))
         .style(Style::default()
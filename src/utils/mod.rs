@@ -1,6 +1,7 @@
 // src/utils/mod.rs
 pub mod text;
 pub mod scroll;
+pub mod search;
 
 // Test modules
 #[cfg(test)]
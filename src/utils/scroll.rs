@@ -6,20 +6,33 @@ pub fn calculate_chat_scroll_offset(
     chat_height: u16,
     chat_width: u16,
 ) -> u16 {
+    count_visual_lines(chat_spans, chat_width).saturating_sub(chat_height)
+}
+
+/// Rows `lines` take up once wrapped to `chat_width`
+pub fn count_visual_lines(lines: &[Line], chat_width: u16) -> u16 {
     let mut total_visual_lines: u16 = 0;
     
-    for line in chat_spans {
+    for line in lines {
         let line_width = line.width() as u16;
         if line_width > chat_width {
-            total_visual_lines += (line_width + chat_width - 1) / chat_width;
+            total_visual_lines += line_width.div_ceil(chat_width);
         } else {
             total_visual_lines += 1;
         }
     }
     
-    if total_visual_lines > chat_height {
-        total_visual_lines - chat_height
+    total_visual_lines
+}
+
+/// Scroll offset that brings the rows `start..end` into view, moving as little
+/// as possible from `offset`. The top wins when they don't all fit.
+pub fn scroll_to_show(offset: u16, start: u16, end: u16, chat_height: u16) -> u16 {
+    if start < offset {
+        start
+    } else if end > offset + chat_height {
+        end.saturating_sub(chat_height).min(start)
     } else {
-        0
+        offset
     }
 }
\ No newline at end of file
@@ -0,0 +1,96 @@
+// src/utils/search.rs
+//! Finding and marking text in the chat pane. Matching runs over the lines as
+//! they are drawn, so hits line up with what is on screen. It ignores case.
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use unicode_width::UnicodeWidthChar;
+
+/// A hit in the chat: line `line`, characters `start..end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Every non-overlapping occurrence of `query` in `lines`, top to bottom
+pub fn find_matches(lines: &[Line], query: &str) -> Vec<SearchMatch> {
+    let query: Vec<char> = query.chars().map(fold).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let text: Vec<char> = line.spans.iter().flat_map(|span| span.content.chars()).map(fold).collect();
+        let mut start = 0;
+        while start + query.len() <= text.len() {
+            if text[start..start + query.len()] == query[..] {
+                matches.push(SearchMatch { line: index, start, end: start + query.len() });
+                start += query.len();
+            } else {
+                start += 1;
+            }
+        }
+    }
+    matches
+}
+
+/// Colour every hit, the current one differently from the rest
+pub fn highlight_matches(lines: &mut [Line<'static>], matches: &[SearchMatch], current: usize) {
+    let other = Style::default().bg(Color::Yellow).fg(Color::Black);
+    let selected = Style::default().bg(Color::LightRed).fg(Color::Black).add_modifier(Modifier::BOLD);
+    for (index, hit) in matches.iter().enumerate() {
+        if let Some(line) = lines.get_mut(hit.line) {
+            let style = if index == current { selected } else { other };
+            restyle(line, hit.start, hit.end, style);
+        }
+    }
+}
+
+/// Patch `style` onto characters `start..end` of `line`, splitting spans as needed
+fn restyle(line: &mut Line<'static>, start: usize, end: usize, style: Style) {
+    let mut spans = Vec::with_capacity(line.spans.len() + 2);
+    let mut position = 0;
+    for span in line.spans.drain(..) {
+        let chars: Vec<char> = span.content.chars().collect();
+        let from = start.clamp(position, position + chars.len()) - position;
+        let to = end.clamp(position, position + chars.len()) - position;
+        position += chars.len();
+        if from == to {
+            spans.push(span);
+            continue;
+        }
+        let part = |range: std::ops::Range<usize>, style: Style| Span::styled(chars[range].iter().collect::<String>(), style);
+        if from > 0 {
+            spans.push(part(0..from, span.style));
+        }
+        spans.push(part(from..to, span.style.patch(style)));
+        if to < chars.len() {
+            spans.push(part(to..chars.len(), span.style));
+        }
+    }
+    line.spans = spans;
+}
+
+/// Row of the chat, once `lines` are wrapped to `width`, that `hit` is on.
+/// Wrapping is assumed to break at the width, as `count_visual_lines` does.
+pub fn match_row(lines: &[Line], hit: &SearchMatch, width: u16) -> u16 {
+    let above = super::scroll::count_visual_lines(&lines[..hit.line.min(lines.len())], width);
+    let before: usize = lines
+        .get(hit.line)
+        .map(|line| {
+            line.spans
+                .iter()
+                .flat_map(|span| span.content.chars())
+                .take(hit.start)
+                .map(|c| c.width().unwrap_or(0))
+                .sum()
+        })
+        .unwrap_or(0);
+    above + (before / width.max(1) as usize) as u16
+}
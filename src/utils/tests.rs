@@ -0,0 +1,75 @@
+//! Unit tests for the chat pane helpers
+//! Tests finding, highlighting and locating search hits in rendered lines
+
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+
+use crate::utils::search::*;
+
+fn line(parts: &[&str]) -> Line<'static> {
+    Line::from(parts.iter().map(|part| Span::raw(part.to_string())).collect::<Vec<_>>())
+}
+
+fn text(line: &Line) -> String {
+    line.spans.iter().map(|span| span.content.as_ref()).collect()
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_ignore_case_and_span_boundaries() {
+        let lines = vec![line(&["[user]: ", "Lock", "file bug"]), line(&["no hit"]), line(&["lockfile, LOCKFILE"])];
+        let matches = find_matches(&lines, "lockfile");
+
+        assert_eq!(
+            matches,
+            vec![
+                SearchMatch { line: 0, start: 8, end: 16 },
+                SearchMatch { line: 2, start: 0, end: 8 },
+                SearchMatch { line: 2, start: 10, end: 18 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_do_not_overlap() {
+        let lines = vec![line(&["aaaa"])];
+        assert_eq!(find_matches(&lines, "aa").len(), 2);
+        assert!(find_matches(&lines, "").is_empty());
+    }
+
+    #[test]
+    fn test_highlight_splits_spans() {
+        let mut lines = vec![line(&["[user]: ", "Lock", "file bug"])];
+        let matches = find_matches(&lines, "kfi");
+        highlight_matches(&mut lines, &matches, 0);
+
+        let contents: Vec<&str> = lines[0].spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(contents, vec!["[user]: ", "Loc", "k", "fi", "le bug"]);
+        assert_eq!(text(&lines[0]), "[user]: Lockfile bug");
+        assert_eq!(lines[0].spans[1].style, Style::default());
+        assert!(lines[0].spans[2].style.bg.is_some());
+        assert_eq!(lines[0].spans[2].style, lines[0].spans[3].style);
+    }
+
+    #[test]
+    fn test_current_match_stands_out() {
+        let mut lines = vec![line(&["one one"])];
+        let matches = find_matches(&lines, "one");
+        highlight_matches(&mut lines, &matches, 1);
+
+        assert_eq!(lines[0].spans[0].style.bg, Some(Color::Yellow));
+        assert_ne!(lines[0].spans[2].style.bg, Some(Color::Yellow));
+    }
+
+    #[test]
+    fn test_match_row_counts_wrapped_lines() {
+        let lines = vec![line(&["x".repeat(25).as_str()]), line(&["short"]), line(&[&format!("{}needle", "y".repeat(12))])];
+        let hit = find_matches(&lines, "needle")[0];
+
+        // Three rows for the first line, one for the second, then the second row of the third
+        assert_eq!(match_row(&lines, &hit, 10), 5);
+    }
+}
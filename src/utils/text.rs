@@ -11,7 +11,7 @@ pub fn wrap_text(text: &str, width: usize) -> Vec<Line<'static>> {
     
     let text_lines: Vec<&str> = text.split('\n').collect();
     
-    for (_i, line) in text_lines.iter().enumerate() {
+    for line in text_lines.iter() {
         if line.is_empty() {
             lines.push(Line::from(""));
         } else if line.chars().count() <= width {
@@ -118,14 +118,12 @@ pub fn move_cursor_up(text: &str, cursor_pos: usize, width: usize) -> usize {
     
     let prev_line_start = line_start_positions[current_line - 1];
     let mut target_pos = prev_line_start;
-    let mut col = 0;
     
-    for (i, ch) in text[prev_line_start..].chars().enumerate() {
+    for (col, ch) in text[prev_line_start..].chars().enumerate() {
         if col >= current_col || ch == '\n' || col >= width {
             break;
         }
-        target_pos = prev_line_start + i + 1;
-        col += 1;
+        target_pos = prev_line_start + col + 1;
     }
     
     target_pos.min(cursor_pos - 1)
@@ -174,14 +172,12 @@ pub fn move_cursor_down(text: &str, cursor_pos: usize, width: usize) -> usize {
     
     let next_line_start = line_start_positions[cursor_line + 1];
     let mut target_pos = next_line_start;
-    let mut col = 0;
     
-    for (i, ch) in text[next_line_start..].chars().enumerate() {
-        if col >= cursor_col || ch == '\n' || (i > 0 && col >= width) {
+    for (col, ch) in text[next_line_start..].chars().enumerate() {
+        if col >= cursor_col || ch == '\n' || (col > 0 && col >= width) {
             break;
         }
-        target_pos = next_line_start + i + 1;
-        col += 1;
+        target_pos = next_line_start + col + 1;
     }
     
     target_pos.max(cursor_pos + 1)